@@ -10,7 +10,7 @@
 
 #[openbrush::contract]
 pub mod crab_swap_factory {
-    use ink_env::{hash::{Sha2x256, HashOutput}, DefaultEnvironment};
+    use ink_env::hash::{Sha2x256, HashOutput};
     use ink_lang::{ToAccountId, reflect::ContractEventBase};
     use ink_storage::{Mapping, traits::{SpreadLayout, PackedLayout, StorageLayout}};
     use ink_storage::traits::SpreadAllocate;
@@ -22,11 +22,33 @@ pub mod crab_swap_factory {
     use ink_lang::codegen::Env;
     use ink_lang::codegen::EmitEvent;
     use crabswap::traits::core::factory::*;
+    use crabswap::traits::core::pool_guardian::PoolGuardianRef;
+    use crabswap::traits::core::pool_upgrade::PoolUpgradeRef;
+    use crabswap::traits::core::pool_owner_action::PoolOwnerActionsRef;
+    use crabswap::traits::core::pool_state::PoolStateRef;
+    use crabswap::traits::core::pool_action::PoolActionRef;
+    use ink_prelude::vec::Vec;
     use openbrush::contracts::{
         ownable::*,
         psp34::*,
     };
     use ink_prelude::string::String;
+    use openbrush::modifiers;
+    // the shortest delay between scheduling and executing a pool upgrade, one day in milliseconds
+    pub const MIN_UPGRADE_DELAY: u64 = 86_400_000;
+    // the value endowed to new pools until the owner changes it, covers the storage deposit of a pool
+    pub const DEFAULT_POOL_ENDOWMENT: Balance = 125_000_000;
+
+    // a pool code upgrade waiting for its timelock
+    #[derive(Debug, Default, PartialEq, Eq, Encode, Decode, SpreadLayout, PackedLayout)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, StorageLayout))]
+    pub struct PendingUpgrade {
+        pub code_hash: Hash,
+        pub pools: Vec<AccountId>,
+        // the earliest timestamp the upgrade can be executed at
+        pub eta: u64,
+    }
+
     // pub const  ACCUMULATOR_CODE_HASH:&str = "52ea1e3471f4d4b8e41c34dfbb79db8b899a3f93be7bcb53cc16f011b81d3ffb";
 
     #[ink(storage)]
@@ -46,6 +68,28 @@ pub mod crab_swap_factory {
         ownable: OwnableData,
         next_id: u8,
         pool_code_hash:Hash,
+        // every pool created by the factory, by creation index
+        pub all_pools:Mapping<u32,AccountId>,
+        pub all_pools_length:u32,
+        // may pause and unpause pools besides the owner
+        pub guardian:Address,
+        // the timelock of pool upgrades in milliseconds
+        pub upgrade_delay:u64,
+        pub pending_upgrades:Mapping<u32,PendingUpgrade>,
+        pub next_upgrade_id:u32,
+        // may collect protocol fees from every pool besides the owner
+        pub fee_collector:Address,
+        // the (feeProtocol0, feeProtocol1) pools of a fee tier are initialized with
+        pub fee_protocol_default:Mapping<u32,(u8,u8)>,
+        // the pools of each fee tier, by (fee, index within the tier)
+        pub pools_by_fee:Mapping<(u32,u32),AccountId>,
+        pub pools_by_fee_length:Mapping<u32,u32>,
+        // the fee charged for creating a pool, kept by the factory
+        pub pool_creation_fee:Balance,
+        // the value endowed to every new pool to cover its storage deposit
+        pub pool_endowment:Balance,
+        // the creation fees collected and not yet withdrawn by the owner
+        pub creation_fees_collected:Balance,
     }
 
 
@@ -66,6 +110,7 @@ pub mod crab_swap_factory {
     /// @param fee The fee collected upon every swap in the pool, denominated in hundredths of a bip
     /// @param tickSpacing The minimum number of ticks between initialized ticks
     /// @param pool The address of the created pool
+    /// @param hooks The hooks contract of the pool, ADDRESS0 for none
     #[ink(event)]
     pub struct PoolCreated{
         #[ink(topic)]
@@ -76,6 +121,7 @@ pub mod crab_swap_factory {
         fee:Uint24,
         tick_spacing:Int24,
         pool:Address,
+        hooks:Address,
     }
 
     /// @notice Emitted when a new fee amount is enabled for pool creation via the factory
@@ -89,6 +135,91 @@ pub mod crab_swap_factory {
         tick_spacing:Int24,
     }
 
+    /// @notice Emitted when the guardian of the factory is changed
+    /// @param old_guardian The guardian before the change
+    /// @param new_guardian The guardian after the change
+    #[ink(event)]
+    pub struct GuardianChanged{
+        #[ink(topic)]
+        old_guardian:Address,
+        #[ink(topic)]
+        new_guardian:Address,
+    }
+
+    /// @notice Emitted when a pool code upgrade is scheduled
+    /// @param id The id of the upgrade
+    /// @param code_hash The new pool code hash
+    /// @param pools The pools to upgrade
+    /// @param eta The earliest timestamp the upgrade can be executed at
+    #[ink(event)]
+    pub struct UpgradeScheduled{
+        #[ink(topic)]
+        id:u32,
+        code_hash:Hash,
+        pools:Vec<AccountId>,
+        eta:u64,
+    }
+
+    /// @notice Emitted when a scheduled upgrade is cancelled
+    #[ink(event)]
+    pub struct UpgradeCancelled{
+        #[ink(topic)]
+        id:u32,
+    }
+
+    /// @notice Emitted when a scheduled upgrade is pushed to its pools
+    #[ink(event)]
+    pub struct UpgradeExecuted{
+        #[ink(topic)]
+        id:u32,
+        code_hash:Hash,
+    }
+
+    /// @notice Emitted when the default protocol fee of a fee tier is changed
+    /// @param fee The fee tier
+    /// @param fee_protocol0 The new default protocol fee for token0
+    /// @param fee_protocol1 The new default protocol fee for token1
+    #[ink(event)]
+    pub struct FeeProtocolDefaultChanged{
+        #[ink(topic)]
+        fee:Uint24,
+        fee_protocol0:u8,
+        fee_protocol1:u8,
+    }
+
+    /// @notice Emitted when the protocol fee collector is changed
+    #[ink(event)]
+    pub struct FeeCollectorChanged{
+        #[ink(topic)]
+        old_fee_collector:Address,
+        #[ink(topic)]
+        new_fee_collector:Address,
+    }
+
+    /// @notice Emitted when the value required to create a pool is changed
+    /// @param pool_creation_fee The fee kept by the factory
+    /// @param pool_endowment The storage deposit endowed to the new pool
+    #[ink(event)]
+    pub struct PoolCreationCostChanged{
+        pool_creation_fee:Balance,
+        pool_endowment:Balance,
+    }
+
+    /// @notice Emitted when the owner withdraws collected pool creation fees
+    #[ink(event)]
+    pub struct CreationFeesWithdrawn{
+        #[ink(topic)]
+        recipient:Address,
+        amount:Balance,
+    }
+
+    /// @notice Emitted when the upgrade timelock is changed
+    #[ink(event)]
+    pub struct UpgradeDelayChanged{
+        old_delay:u64,
+        new_delay:u64,
+    }
+
     impl PSP34 for FactoryContract {}
 
     impl Ownable for FactoryContract{}
@@ -130,7 +261,14 @@ pub mod crab_swap_factory {
         //此处原有modifier,限制不可以使用delegateCall的方式调用该方法,因为ink!中没有delegate call 调用,所以按时不使用NoDelegateCall
         #[ink(message,payable)]
         fn create_pool(&mut self,fee:u32,token_a:Address,token_b:Address)->AccountId{
+            self.create_pool_with_config(fee,token_a,token_b,PoolConfig::default())
+        }
+
+        #[ink(message,payable)]
+        fn create_pool_with_config(&mut self,fee:u32,token_a:Address,token_b:Address,config:PoolConfig)->AccountId{
             assert!(token_a!=token_b,"token A should not equals token B");
+            // a pair and fee tier has a single pool, so anyone could squat it with their own hooks or fee controller
+            assert!(config==PoolConfig::default() || self.env().caller()==self.ownable.owner,"only owner may configure pools");
             let (token0,token1);
             if token_a < token_b {
                 token0 = token_a;
@@ -145,12 +283,26 @@ pub mod crab_swap_factory {
             let tick_spacing = fee_amount_tick_spacing_option.unwrap_or(0);
             assert!(tick_spacing!=0,"tick spacing should not be zero!");
             assert!(self.pool_map.get((token0,token1,fee)).is_none(),"pool have been exist!");
+            let transfer_value = self.env().transferred_value();
+            let cost = self.get_pool_creation_cost();
+            assert!(transfer_value>=cost,"insufficient value for pool creation");
             let address_this = self.env().account_id();
 
             //because the contract deploy difference with solidity,so cancel the deployer contract.
             //start deploy the pool contract and initial.
-            let pool = self.deploy(address_this,token0,token1,fee,tick_spacing);
+            let hooks = config.hooks;
+            let pool = self.deploy(address_this,token0,token1,fee,tick_spacing,config);
             self.pool_map.insert((token0,token1,fee),&pool);
+            self.all_pools.insert(self.all_pools_length,&pool);
+            self.all_pools_length += 1;
+            let tier_length = self.pools_by_fee_length.get(fee).unwrap_or(0);
+            self.pools_by_fee.insert((fee,tier_length),&pool);
+            self.pools_by_fee_length.insert(fee,&(tier_length+1));
+            self.creation_fees_collected += self.pool_creation_fee;
+            // return whatever was sent above the exact cost
+            if transfer_value > cost {
+                self.env().transfer(self.env().caller(),transfer_value-cost).expect("refund failed");
+            }
             // self.env().emit_event(PoolCreated {
             //     token0,
             //     token1,
@@ -165,9 +317,35 @@ pub mod crab_swap_factory {
                 fee,
                 tick_spacing,
                 pool,
+                hooks,
             });
             pool
         }
+
+        #[ink(message)]
+        fn get_all_pools_length(&self)->u32{
+            self.all_pools_length
+        }
+
+        #[ink(message)]
+        fn get_all_pools(&self,index:u32)->AccountId{
+            self.all_pools.get(index).unwrap_or(ADDRESS0.into())
+        }
+
+        #[ink(message)]
+        fn get_fee_collector(&self)->Address{
+            self.fee_collector
+        }
+
+        #[ink(message)]
+        fn get_fee_protocol_default(&self,fee:u32)->(u8,u8){
+            self.fee_protocol_default.get(fee).unwrap_or((0,0))
+        }
+
+        #[ink(message)]
+        fn get_pool_creation_cost(&self)->Balance{
+            self.pool_creation_fee + self.pool_endowment
+        }
     }
 
     impl FactoryContract {
@@ -176,6 +354,8 @@ pub mod crab_swap_factory {
             ink_lang::utils::initialize_contract(|instance: &mut Self| {
                 let caller = instance.env().caller();
                 instance._init_with_owner(caller);
+                instance.upgrade_delay = MIN_UPGRADE_DELAY;
+                instance.pool_endowment = DEFAULT_POOL_ENDOWMENT;
                 instance.fee_amount_tick_spacing.insert(500,&10);
                 // instance.env().emit_event(FeeAmountEnabled{
                 //     fee:500,
@@ -230,6 +410,282 @@ pub mod crab_swap_factory {
             self.ownable.owner
         }
 
+        #[ink(message)]
+        pub fn get_guardian(&self)->Address{
+            self.guardian
+        }
+
+        /// @notice Sets the guardian, who may pause and unpause pools
+        /// @dev Must be called by the current owner
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn set_guardian(&mut self,guardian:Address)->Result<(), OwnableError>{
+            let old_guardian = self.guardian;
+            self.guardian = guardian;
+            ink_lang::codegen::EmitEvent::<FactoryContract>::emit_event(self.env(), GuardianChanged{
+                old_guardian,
+                new_guardian:guardian,
+            });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn get_pools_by_fee_length(&self,fee:u32)->u32{
+            self.pools_by_fee_length.get(fee).unwrap_or(0)
+        }
+
+        #[ink(message)]
+        pub fn get_pools_by_fee(&self,fee:u32,index:u32)->AccountId{
+            self.pools_by_fee.get((fee,index)).unwrap_or(ADDRESS0.into())
+        }
+
+        /// @notice Sets the protocol fee new pools of a fee tier are initialized with
+        /// @dev Existing pools keep their protocol fee until apply_fee_protocol_default is called
+        /// @param fee_protocol0 0 or between 4 and 10, see PoolOwnerActions#setFeeProtocol
+        /// @param fee_protocol1 0 or between 4 and 10, see PoolOwnerActions#setFeeProtocol
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn set_fee_protocol_default(&mut self,fee:u32,fee_protocol0:u8,fee_protocol1:u8)->Result<(), OwnableError>{
+            assert!(self.fee_amount_tick_spacing.get(fee).is_some(),"fee amount not enabled");
+            assert!(
+                (fee_protocol0 == 0 || (fee_protocol0 >= 4 && fee_protocol0 <= 10))
+                    && (fee_protocol1 == 0 || (fee_protocol1 >= 4 && fee_protocol1 <= 10)),
+                "invalid fee protocol"
+            );
+            self.fee_protocol_default.insert(fee,&(fee_protocol0,fee_protocol1));
+            ink_lang::codegen::EmitEvent::<FactoryContract>::emit_event(self.env(), FeeProtocolDefaultChanged{
+                fee,
+                fee_protocol0,
+                fee_protocol1,
+            });
+            Ok(())
+        }
+
+        /// @notice Sets the default protocol fee of a fee tier on its existing pools with index in [start, start + limit)
+        /// @dev Called in batches so tiers with many pools stay within the block weight. Pools not initialized yet are
+        /// skipped, they get the default when they are initialized
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn apply_fee_protocol_default(&mut self,fee:u32,start:u32,limit:u32)->Result<(), OwnableError>{
+            let (fee_protocol0,fee_protocol1) = self.get_fee_protocol_default(fee);
+            let length = self.get_pools_by_fee_length(fee);
+            let end = if start.saturating_add(limit) > length { length } else { start + limit };
+            for index in start..end {
+                let pool = self.pools_by_fee.get((fee,index)).unwrap();
+                // a pool has no price until it is initialized
+                if PoolActionRef::getSlot0(&pool).sqrtPriceX96.value.is_zero() {
+                    continue;
+                }
+                PoolOwnerActionsRef::setFeeProtocol(&pool,fee_protocol0,fee_protocol1);
+            }
+            Ok(())
+        }
+
+        /// @notice Sets the value required to create a pool, see get_pool_creation_cost
+        /// @param pool_creation_fee The fee kept by the factory for every pool created
+        /// @param pool_endowment The value endowed to every new pool, must cover its storage deposit on the chain
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn set_pool_creation_cost(&mut self,pool_creation_fee:Balance,pool_endowment:Balance)->Result<(), OwnableError>{
+            assert!(pool_creation_fee.checked_add(pool_endowment).is_some(),"cost overflow");
+            self.pool_creation_fee = pool_creation_fee;
+            self.pool_endowment = pool_endowment;
+            ink_lang::codegen::EmitEvent::<FactoryContract>::emit_event(self.env(), PoolCreationCostChanged{
+                pool_creation_fee,
+                pool_endowment,
+            });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn get_pool_creation_fee(&self)->Balance{
+            self.pool_creation_fee
+        }
+
+        #[ink(message)]
+        pub fn get_pool_endowment(&self)->Balance{
+            self.pool_endowment
+        }
+
+        #[ink(message)]
+        pub fn get_creation_fees_collected(&self)->Balance{
+            self.creation_fees_collected
+        }
+
+        /// @notice Sends the collected pool creation fees to the recipient
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn withdraw_creation_fees(&mut self,recipient:Address)->Result<(), OwnableError>{
+            let amount = self.creation_fees_collected;
+            self.creation_fees_collected = 0;
+            if amount > 0 {
+                self.env().transfer(recipient,amount).expect("withdraw failed");
+            }
+            ink_lang::codegen::EmitEvent::<FactoryContract>::emit_event(self.env(), CreationFeesWithdrawn{
+                recipient,
+                amount,
+            });
+            Ok(())
+        }
+
+        /// @notice Sets the account allowed to collect protocol fees from every pool, ADDRESS0 for none
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn set_fee_collector(&mut self,fee_collector:Address)->Result<(), OwnableError>{
+            let old_fee_collector = self.fee_collector;
+            self.fee_collector = fee_collector;
+            ink_lang::codegen::EmitEvent::<FactoryContract>::emit_event(self.env(), FeeCollectorChanged{
+                old_fee_collector,
+                new_fee_collector:fee_collector,
+            });
+            Ok(())
+        }
+
+        /// @notice Blocks swap, mint and flash on a pool, burn and collect stay open
+        #[ink(message)]
+        pub fn pause_pool(&mut self,pool:Address){
+            self.only_guardian_or_owner();
+            self.assert_pool(pool);
+            PoolGuardianRef::setPaused(&pool,true);
+        }
+
+        #[ink(message)]
+        pub fn unpause_pool(&mut self,pool:Address){
+            self.only_guardian_or_owner();
+            self.assert_pool(pool);
+            PoolGuardianRef::setPaused(&pool,false);
+        }
+
+        /// @notice Pauses the pools created by the factory with index in [start, start + limit)
+        /// @dev Called in batches so all pools can be paused within the block weight
+        #[ink(message)]
+        pub fn pause_all(&mut self,start:u32,limit:u32){
+            self.only_guardian_or_owner();
+            self.set_paused_range(start,limit,true);
+        }
+
+        /// @notice Unpauses the pools created by the factory with index in [start, start + limit)
+        #[ink(message)]
+        pub fn unpause_all(&mut self,start:u32,limit:u32){
+            self.only_guardian_or_owner();
+            self.set_paused_range(start,limit,false);
+        }
+
+        /// @notice Sets the circuit breaker of a pool, which pauses it after a swap moving the tick more than max_tick_move
+        /// @param max_tick_move The threshold in ticks, 0 disables the circuit breaker
+        #[ink(message)]
+        pub fn set_max_tick_move(&mut self,pool:Address,max_tick_move:u32){
+            self.only_guardian_or_owner();
+            self.assert_pool(pool);
+            PoolGuardianRef::setMaxTickMove(&pool,max_tick_move);
+        }
+
+        #[ink(message)]
+        pub fn get_upgrade_delay(&self)->u64{
+            self.upgrade_delay
+        }
+
+        #[ink(message)]
+        pub fn get_pending_upgrade(&self,id:u32)->Option<PendingUpgrade>{
+            self.pending_upgrades.get(id)
+        }
+
+        /// @notice Sets the timelock of pool upgrades, at least MIN_UPGRADE_DELAY
+        /// @dev Only applies to upgrades scheduled afterwards
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn set_upgrade_delay(&mut self,delay:u64)->Result<(), OwnableError>{
+            assert!(delay>=MIN_UPGRADE_DELAY,"delay too short");
+            let old_delay = self.upgrade_delay;
+            self.upgrade_delay = delay;
+            ink_lang::codegen::EmitEvent::<FactoryContract>::emit_event(self.env(), UpgradeDelayChanged{
+                old_delay,
+                new_delay:delay,
+            });
+            Ok(())
+        }
+
+        /// @notice Schedules switching the given pools to new code once the timelock has passed
+        /// @param code_hash The hash of the already uploaded pool code
+        /// @param pools The pools to upgrade
+        /// @return id The id to execute or cancel the upgrade with
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn schedule_upgrade(&mut self,code_hash:Hash,pools:Vec<AccountId>)->Result<u32, OwnableError>{
+            assert!(!pools.is_empty(),"no pools");
+            for pool in pools.iter() {
+                self.assert_pool(*pool);
+            }
+            let id = self.next_upgrade_id;
+            let eta = self.env().block_timestamp() + self.upgrade_delay;
+            self.pending_upgrades.insert(id,&PendingUpgrade{
+                code_hash,
+                pools:pools.clone(),
+                eta,
+            });
+            self.next_upgrade_id += 1;
+            ink_lang::codegen::EmitEvent::<FactoryContract>::emit_event(self.env(), UpgradeScheduled{
+                id,
+                code_hash,
+                pools,
+                eta,
+            });
+            Ok(id)
+        }
+
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn cancel_upgrade(&mut self,id:u32)->Result<(), OwnableError>{
+            assert!(self.pending_upgrades.get(id).is_some(),"upgrade not exist");
+            self.pending_upgrades.remove(id);
+            ink_lang::codegen::EmitEvent::<FactoryContract>::emit_event(self.env(), UpgradeCancelled{
+                id,
+            });
+            Ok(())
+        }
+
+        /// @notice Pushes a scheduled upgrade to its pools and runs their migration
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn execute_upgrade(&mut self,id:u32)->Result<(), OwnableError>{
+            let upgrade = self.pending_upgrades.get(id).expect("upgrade not exist");
+            assert!(self.env().block_timestamp()>=upgrade.eta,"upgrade is timelocked");
+            self.pending_upgrades.remove(id);
+            for pool in upgrade.pools.iter() {
+                PoolUpgradeRef::setCodeHash(pool,upgrade.code_hash);
+                PoolUpgradeRef::migrate(pool);
+            }
+            ink_lang::codegen::EmitEvent::<FactoryContract>::emit_event(self.env(), UpgradeExecuted{
+                id,
+                code_hash:upgrade.code_hash,
+            });
+            Ok(())
+        }
+
+        /// @notice Enables a fee amount with the given tickSpacing
+        /// @dev Fee amounts may never be removed once enabled
+        /// @param fee The fee amount to enable, denominated in hundredths of a bip (i.e. 1e-6)
+        /// @param tick_spacing The spacing between ticks to be enforced for all pools created with the given fee amount
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn enable_fee_amount(&mut self,fee:u32,tick_spacing:Int24)->Result<(), OwnableError>{
+            // require(fee < 1000000);
+            assert!(fee<1000000,"fee too large");
+            // tick spacing is capped at 16384 to prevent the situation where tickSpacing is so large that
+            // TickBitmap#nextInitializedTickWithinOneWord overflows int24 container from a valid tick
+            // 16384 ticks represents a >5x price change with ticks of 1 bips
+            // require(tickSpacing > 0 && tickSpacing < 16384);
+            assert!(tick_spacing>0 && tick_spacing<16384,"invalid tick spacing");
+            // require(feeAmountTickSpacing[fee] == 0);
+            assert!(self.fee_amount_tick_spacing.get(fee).is_none(),"fee amount exist");
+            self.fee_amount_tick_spacing.insert(fee,&tick_spacing);
+            ink_lang::codegen::EmitEvent::<FactoryContract>::emit_event(self.env(), FeeAmountEnabled{
+                fee,
+                tick_spacing,
+            });
+            Ok(())
+        }
+
         #[ink(message)]
         pub fn get_fee_amount_tick_spacing(&self,key:u32)->Int24{
             ink_env::debug_println!("fee_amount_tick_spacing is:{:?}",self.fee_amount_tick_spacing);
@@ -238,20 +694,40 @@ pub mod crab_swap_factory {
 
 
 
-        fn deploy(&mut self,address_this: Address, token0: Address, token1: Address, fee: Uint24, tick_spacing: Int24) -> AccountId {
+        fn only_guardian_or_owner(&self){
+            let caller = self.env().caller();
+            assert!(caller==self.ownable.owner || (self.guardian!=ADDRESS0.into() && caller==self.guardian),"not guardian");
+        }
+
+        /// @dev The pool must be registered under its own tokens and fee tier, which a fake pool cannot be
+        fn assert_pool(&self,pool:Address){
+            let token0 = PoolStateRef::token0(&pool);
+            let token1 = PoolStateRef::token1(&pool);
+            let fee = PoolStateRef::feeTier(&pool);
+            assert!(self.pool_map.get((token0,token1,fee))==Some(pool),"not a factory pool");
+        }
+
+        fn set_paused_range(&mut self,start:u32,limit:u32,paused:bool){
+            let length = self.all_pools_length;
+            let end = if start.saturating_add(limit) > length { length } else { start + limit };
+            for index in start..end {
+                let pool = self.all_pools.get(index).unwrap();
+                PoolGuardianRef::setPaused(&pool,paused);
+            }
+        }
+
+        fn deploy(&mut self,address_this: Address, token0: Address, token1: Address, fee: Uint24, tick_spacing: Int24, config: PoolConfig) -> AccountId {
             // ink_env::debug_println!("address_this is: {:?}",address_this);
             // ink_env::debug_println!("token0 is: {:?}",token0);
             // ink_env::debug_println!("token1 is: {:?}",token1);
             // ink_env::debug_println!("fee is: {:?}",fee);
             // ink_env::debug_println!("tick_spacing is: {:?}",tick_spacing);
-            let transfer_value = ink_env::transferred_value::<DefaultEnvironment>();
-            ink_env::debug_println!("transfer_value------------ is: {:?}",transfer_value);
             let encodable = (address_this, token0, token1,fee); // Implements `scale::Encode`
             let mut salt = <Sha2x256 as HashOutput>::Type::default(); // 256-bit buffer
             ink_env::hash_encoded::<Sha2x256, _>(&encodable, &mut salt);
             // factory:Address,token0: Address, token1: Address, fee: Uint24, tick_spacing: Int24
-            let pool_address = PoolContractRef::new(address_this,token0, token1, fee, tick_spacing)
-                    .endowment(transfer_value/4)
+            let pool_address = PoolContractRef::new(address_this,token0, token1, fee, tick_spacing, config)
+                    .endowment(self.pool_endowment)
                     .code_hash(self.pool_code_hash.clone())
                     .salt_bytes(salt)
                     .instantiate()
@@ -261,4 +737,133 @@ pub mod crab_swap_factory {
 
     }
 
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use ink_lang as ink;
+
+        fn default_accounts() -> ink_env::test::DefaultAccounts<ink_env::DefaultEnvironment> {
+            ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+        }
+
+        fn set_next_caller(caller: AccountId) {
+            ink_env::test::set_caller::<ink_env::DefaultEnvironment>(caller);
+        }
+
+        fn set_timestamp(timestamp: u64) {
+            ink_env::test::set_block_timestamp::<ink_env::DefaultEnvironment>(timestamp);
+        }
+
+        /// @dev A factory owned by alice with an upgrade pending since the given timestamp. The upgrade has no pools,
+        /// schedule_upgrade only accepts pools the factory created, which the off-chain environment can not deploy
+        fn setup(scheduled_at: u64) -> (FactoryContract, u32) {
+            set_next_caller(default_accounts().alice);
+            let mut factory = FactoryContract::new();
+            let id = factory.next_upgrade_id;
+            factory.pending_upgrades.insert(id,&PendingUpgrade{
+                code_hash:Hash::from([1;32]),
+                pools:Vec::new(),
+                eta:scheduled_at + factory.get_upgrade_delay(),
+            });
+            factory.next_upgrade_id += 1;
+            (factory, id)
+        }
+
+        #[ink::test]
+        fn pool_creation_cost_defaults_to_the_endowment() {
+            let (factory, _) = setup(0);
+            assert_eq!(factory.get_pool_creation_fee(), 0);
+            assert_eq!(factory.get_pool_endowment(), DEFAULT_POOL_ENDOWMENT);
+            assert_eq!(factory.get_pool_creation_cost(), DEFAULT_POOL_ENDOWMENT);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "cost overflow")]
+        fn pool_creation_cost_can_not_overflow() {
+            let (mut factory, _) = setup(0);
+            let _ = factory.set_pool_creation_cost(Balance::MAX, 1);
+        }
+
+        #[ink::test]
+        fn upgrade_delay_defaults_to_the_minimum() {
+            let (factory, _) = setup(0);
+            assert_eq!(factory.get_upgrade_delay(), MIN_UPGRADE_DELAY);
+        }
+
+        #[ink::test]
+        fn owner_sets_the_upgrade_delay() {
+            let (mut factory, _) = setup(0);
+            factory.set_upgrade_delay(MIN_UPGRADE_DELAY * 2).unwrap();
+            assert_eq!(factory.get_upgrade_delay(), MIN_UPGRADE_DELAY * 2);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "delay too short")]
+        fn upgrade_delay_can_not_go_below_the_minimum() {
+            let (mut factory, _) = setup(0);
+            let _ = factory.set_upgrade_delay(MIN_UPGRADE_DELAY - 1);
+        }
+
+        #[ink::test]
+        fn upgrade_delay_is_set_by_the_owner_only() {
+            let (mut factory, _) = setup(0);
+            set_next_caller(default_accounts().bob);
+            assert_eq!(factory.set_upgrade_delay(MIN_UPGRADE_DELAY * 2), Err(OwnableError::CallerIsNotOwner));
+            assert_eq!(factory.get_upgrade_delay(), MIN_UPGRADE_DELAY);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "no pools")]
+        fn schedule_upgrade_needs_pools() {
+            let (mut factory, _) = setup(0);
+            let _ = factory.schedule_upgrade(Hash::from([1;32]),Vec::new());
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "upgrade is timelocked")]
+        fn execute_upgrade_waits_for_the_delay() {
+            set_timestamp(1_000);
+            let (mut factory, id) = setup(1_000);
+            set_timestamp(1_000 + MIN_UPGRADE_DELAY - 1);
+            let _ = factory.execute_upgrade(id);
+        }
+
+        #[ink::test]
+        fn execute_upgrade_after_the_delay() {
+            set_timestamp(1_000);
+            let (mut factory, id) = setup(1_000);
+            set_timestamp(1_000 + MIN_UPGRADE_DELAY);
+            factory.execute_upgrade(id).unwrap();
+            assert_eq!(factory.get_pending_upgrade(id), None);
+        }
+
+        #[ink::test]
+        fn execute_upgrade_is_owner_only() {
+            let (mut factory, id) = setup(0);
+            set_timestamp(MIN_UPGRADE_DELAY);
+            set_next_caller(default_accounts().bob);
+            assert_eq!(factory.execute_upgrade(id), Err(OwnableError::CallerIsNotOwner));
+            assert!(factory.get_pending_upgrade(id).is_some());
+        }
+
+        #[ink::test]
+        fn cancel_upgrade_removes_it() {
+            let (mut factory, id) = setup(0);
+            set_next_caller(default_accounts().bob);
+            assert_eq!(factory.cancel_upgrade(id), Err(OwnableError::CallerIsNotOwner));
+            set_next_caller(default_accounts().alice);
+            factory.cancel_upgrade(id).unwrap();
+            assert_eq!(factory.get_pending_upgrade(id), None);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "upgrade not exist")]
+        fn cancelled_upgrade_can_not_be_executed() {
+            let (mut factory, id) = setup(0);
+            factory.cancel_upgrade(id).unwrap();
+            set_timestamp(MIN_UPGRADE_DELAY);
+            let _ = factory.execute_upgrade(id);
+        }
+    }
+
 }
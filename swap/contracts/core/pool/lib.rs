@@ -15,12 +15,13 @@ pub mod crab_swap_pool {
     };
     use libs::{
         core::{
-            oracle::Observations, LiquidityMath, Position, SqrtPriceMath, Tick, TickBitmap,
+            oracle::{Observation, Observations}, LiquidityMath, Position, SqrtPriceMath, Tick, TickBitmap,
             TickMath,
         },
         getTickAtSqrtRatio,
+        periphery::PositionKey,
     };
-    use primitives::{Address, Int24, Int256, Uint160, Uint256, I256, U160, U256};
+    use primitives::{Address, Int24, Int256, Uint160, Uint256, I256, I56, U160, U256};
     use scale::{Decode, Encode};
     type Uint24 = u32;
     use openbrush::contracts::psp22::extensions::metadata::*;
@@ -37,17 +38,20 @@ pub mod crab_swap_pool {
     use libs::swap::FullMath;
     use crabswap::traits::core::pool_owner_action::PoolOwnerActions;
     use crabswap::traits::core::pool_owner_action::poolowneractions_external;
+    use crabswap::traits::core::pool_state::*;
+    use crabswap::traits::core::pool_hooks::*;
+    use crabswap::traits::core::pool_guardian::*;
+    use crabswap::traits::core::pool_upgrade::*;
+    use crabswap::traits::core::pool_derived_state::*;
+    use crabswap::traits::core::access_policy::AccessPolicyRef;
+    use crabswap::traits::core::factory::{FactoryRef, PoolConfig};
+    use primitives::ADDRESS0;
     use openbrush::contracts::traits::ownable::OwnableRef;
 
-    // accumulated protocol fees in token0/token1 units
-    #[derive(
-        Debug, Default, PartialEq, Eq, Encode, Decode, SpreadLayout, SpreadAllocate, PackedLayout,
-    )]
-    #[cfg_attr(feature = "std", derive(StorageLayout))]
-    pub struct ProtocolFees {
-        token0: u128,
-        token1: u128,
-    }
+    // the storage version written by this code, bump it and extend migrate() when the layout changes
+    pub const STORAGE_VERSION: u32 = 1;
+    // the gas the burn hooks may use, the burn goes on without them when they run out
+    pub const BURN_HOOK_GAS_LIMIT: u64 = 10_000_000_000;
 
     #[ink(storage)]
     #[derive(SpreadAllocate, NoDelegateCallStorage)]
@@ -64,7 +68,10 @@ pub mod crab_swap_pool {
         pub factory: Address,
         pub token0: Address,
         pub token1: Address,
+        // the current swap fee, only ever different from feeTier in dynamic-fee pools
         pub fee: Uint24,
+        // the fee the pool was created with, which keys the pool in the factory
+        pub feeTier: Uint24,
         pub tickSpacing: Int24,
         pub max_liquidity_per_tick: u128,
         pub slot0: Slot0,
@@ -79,6 +86,9 @@ pub mod crab_swap_pool {
 
         pub protocolFees: ProtocolFees,
         pub liquidity: u128,
+        // the token0/token1 balances the pool has accounted for, kept in sync instead of querying balance_of
+        pub reserve0: u128,
+        pub reserve1: u128,
         // mapping(int24 => Tick.Info) pub ticks;
         pub ticks: Mapping<Int24, Tick::Info>,
         // /// @inheritdoc IUniswapV3PoolState
@@ -86,9 +96,27 @@ pub mod crab_swap_pool {
         pub tickBitmap: Mapping<i16, Uint256>,
         // /// @inheritdoc IUniswapV3PoolState
         // mapping(bytes32 => Position.Info) public override positions;
-        pub positions: Mapping<(Address, Int24, Int24), Position::Info>,
+        // keyed by PositionKey::compute(owner, tickLower, tickUpper, salt)
+        pub positions: Mapping<[u8; 32], Position::Info>,
         /// @inheritdoc IUniswapV3PoolState
         pub observations: Observations,
+        // the PoolHooks contract fixed at creation, ADDRESS0 for none
+        pub hooks: Address,
+        // the hooks bitmask reported by the hooks contract at creation
+        pub hookPermissions: u16,
+        // whether setFee is enabled, and who besides the factory owner may call it within which bounds
+        pub dynamicFee: bool,
+        pub feeController: Address,
+        pub minFee: Uint24,
+        pub maxFee: Uint24,
+        // whether swap, mint and flash are blocked, set by the factory or the circuit breaker
+        pub paused: bool,
+        // the circuit breaker threshold in ticks per swap, 0 if disabled
+        pub maxTickMove: u32,
+        // the version of the storage layout, see STORAGE_VERSION
+        pub storageVersion: u32,
+        // the AccessPolicy contract gating mint and swap, ADDRESS0 for a permissionless pool
+        pub accessPolicy: Address,
     }
 
     impl NoDelegateCall for PoolContract {}
@@ -172,13 +200,95 @@ pub mod crab_swap_pool {
     {
         // require(msg.sender == IUniswapV3Factory(factory).owner());
         // _;
+        // the factory itself is accepted too, for the owner actions it applies to batches of pools
+        // the factory is checked first, asking a calling factory for its owner would re-enter it and trap
+        let msg_sender = ink_env::caller::<DefaultEnvironment>();
+        let factory: Address = instance.get_factory();
+        assert!(msg_sender == factory || msg_sender == OwnableRef::owner(&factory));
+        body(instance)
+    }
+
+    #[modifier_definition]
+    pub fn onlyFactory<T, F, R>(instance: &mut T, body: F) -> R
+    where
+        T: PoolOwnerActions,
+        F: FnOnce(&mut T) -> R,
+    {
+        let msg_sender = ink_env::caller::<DefaultEnvironment>();
+        assert!(msg_sender == instance.get_factory(), "not factory");
+        body(instance)
+    }
+
+    #[modifier_definition]
+    pub fn onlyFactoryOwnerOrFeeCollector<T, F, R>(instance: &mut T, body: F) -> R
+    where
+        T: PoolOwnerActions,
+        F: FnOnce(&mut T) -> R,
+    {
         let msg_sender = ink_env::caller::<DefaultEnvironment>();
-        let factory_owner:Address = instance.get_factory();
-        let owner = OwnableRef::owner(&factory_owner);
-        assert!(msg_sender == owner);
+        let factory: Address = instance.get_factory();
+        assert!(
+            msg_sender == OwnableRef::owner(&factory)
+                || msg_sender == FactoryRef::get_fee_collector(&factory),
+            "not fee collector"
+        );
         body(instance)
     }
 
+    impl PoolGuardian for PoolContract {
+        #[ink(message)]
+        #[modifiers(onlyFactory)]
+        fn setPaused(&mut self, paused: bool) {
+            self._setPaused(paused);
+        }
+
+        #[ink(message)]
+        #[modifiers(onlyFactory)]
+        fn setMaxTickMove(&mut self, maxTickMove: u32) {
+            self.maxTickMove = maxTickMove;
+        }
+
+        #[ink(message)]
+        fn paused(&self) -> bool {
+            self.paused
+        }
+
+        #[ink(message)]
+        fn maxTickMove(&self) -> u32 {
+            self.maxTickMove
+        }
+    }
+
+    impl PoolUpgrade for PoolContract {
+        #[ink(message)]
+        #[modifiers(onlyFactory)]
+        fn setCodeHash(&mut self, code_hash: Hash) {
+            ink_env::set_code_hash(code_hash.as_ref()).expect("set code hash failed!");
+            self.env().emit_event(CodeHashSet { code_hash });
+        }
+
+        #[ink(message)]
+        #[modifiers(onlyFactory)]
+        fn migrate(&mut self) {
+            let fromVersion = self.storageVersion;
+            assert!(fromVersion <= STORAGE_VERSION, "storage is newer than code");
+            if fromVersion == STORAGE_VERSION {
+                return;
+            }
+            // version specific migrations go here, in order
+            self.storageVersion = STORAGE_VERSION;
+            self.env().emit_event(Migrated {
+                fromVersion,
+                toVersion: STORAGE_VERSION,
+            });
+        }
+
+        #[ink(message)]
+        fn storageVersion(&self) -> u32 {
+            self.storageVersion
+        }
+    }
+
     impl PoolOwnerActions for PoolContract {
         // function setFeeProtocol(uint8 feeProtocol0, uint8 feeProtocol1) external override lock onlyFactoryOwner {
         #[ink(message)]
@@ -206,7 +316,7 @@ pub mod crab_swap_pool {
 
         #[ink(message)]
         #[modifiers(lock)]
-        #[modifiers(onlyFactoryOwner)]
+        #[modifiers(onlyFactoryOwnerOrFeeCollector)]
         fn collectProtocol(
             &mut self,
             recipient:Address,
@@ -234,6 +344,7 @@ pub mod crab_swap_pool {
             if amount0 > 0 {
                 if amount0 == self.protocolFees.token0 { amount0 = amount0-1;} // ensure that the slot is not cleared, for gas savings
                 self.protocolFees.token0 -= amount0;
+                self.reserve0 -= amount0;
                 PSP22Ref::transfer(&mut self.token0, recipient, amount0, vec![0u8])
                     .expect("token0 transfer error!");
             }
@@ -245,6 +356,7 @@ pub mod crab_swap_pool {
             if amount1 > 0 {
                 if amount1 == self.protocolFees.token1 { amount1 = amount1-1;} // ensure that the slot is not cleared, for gas savings
                 self.protocolFees.token1 -= amount1;
+                self.reserve1 -= amount1;
                 PSP22Ref::transfer(&mut self.token1, recipient, amount1, vec![0u8])
                     .expect("token1 transfer error!");
             }
@@ -260,12 +372,226 @@ pub mod crab_swap_pool {
 
         }
 
+        #[ink(message)]
+        #[modifiers(lock)]
+        fn setFee(&mut self, fee: Uint24) {
+            assert!(self.dynamicFee, "static fee pool");
+            let msg_sender = ink_env::caller::<DefaultEnvironment>();
+            assert!(
+                (self.feeController != ADDRESS0.into() && msg_sender == self.feeController)
+                    || msg_sender == OwnableRef::owner(&self.factory),
+                "not fee controller"
+            );
+            assert!(fee >= self.minFee && fee <= self.maxFee, "fee out of bounds");
+            let feeOld = self.fee;
+            self.fee = fee;
+            self.env().emit_event(SetFee {
+                feeOld,
+                feeNew: fee,
+            });
+        }
+
         #[ink(message)]
         fn get_factory(&self)->Address{
             self.factory
         }
     }
 
+    impl PoolState for PoolContract {
+        #[ink(message)]
+        fn token0(&self) -> Address {
+            self.token0
+        }
+
+        #[ink(message)]
+        fn token1(&self) -> Address {
+            self.token1
+        }
+
+        #[ink(message)]
+        fn fee(&self) -> Uint24 {
+            self.fee
+        }
+
+        #[ink(message)]
+        fn feeTier(&self) -> Uint24 {
+            self.feeTier
+        }
+
+        #[ink(message)]
+        fn dynamicFee(&self) -> (bool, Address, Uint24, Uint24) {
+            (self.dynamicFee, self.feeController, self.minFee, self.maxFee)
+        }
+
+        #[ink(message)]
+        fn maxLiquidityPerTick(&self) -> u128 {
+            self.maxLiquidityPerTick
+        }
+
+        #[ink(message)]
+        fn feeGrowthGlobal0X128(&self) -> U256 {
+            self.feeGrowthGlobal0X128.value
+        }
+
+        #[ink(message)]
+        fn feeGrowthGlobal1X128(&self) -> U256 {
+            self.feeGrowthGlobal1X128.value
+        }
+
+        #[ink(message)]
+        fn protocolFees(&self) -> ProtocolFees {
+            self.protocolFees.clone()
+        }
+
+        #[ink(message)]
+        fn liquidity(&self) -> u128 {
+            self.liquidity
+        }
+
+        #[ink(message)]
+        fn getReserves(&self) -> (u128, u128) {
+            (self.reserve0, self.reserve1)
+        }
+
+        #[ink(message)]
+        fn hooks(&self) -> (Address, u16) {
+            (self.hooks, self.hookPermissions)
+        }
+
+        #[ink(message)]
+        fn accessPolicy(&self) -> Address {
+            self.accessPolicy
+        }
+
+        #[ink(message)]
+        fn ticks(&self, tick: Int24) -> Tick::Info {
+            self.ticks.get(tick).unwrap_or_default()
+        }
+
+        #[ink(message)]
+        fn tickBitmap(&self, wordPosition: i16) -> U256 {
+            self.tickBitmap.get(wordPosition).unwrap_or_default().value
+        }
+
+        #[ink(message)]
+        fn observations(&self, index: u16) -> Observation {
+            self.observations.obs.get(index).unwrap_or_default()
+        }
+
+        #[ink(message)]
+        fn getFeeGrowthInside(&self, tickLower: Int24, tickUpper: Int24) -> (U256, U256) {
+            checkTicks(tickLower, tickUpper);
+            self._getFeeGrowthInside(
+                tickLower,
+                tickUpper,
+                self.slot0.tick,
+                self.feeGrowthGlobal0X128.value,
+                self.feeGrowthGlobal1X128.value,
+            )
+        }
+
+        #[ink(message)]
+        fn pool_state(&self) -> PoolStateInfo {
+            PoolStateInfo {
+                factory: self.factory,
+                token0: self.token0,
+                token1: self.token1,
+                fee: self.fee,
+                feeTier: self.feeTier,
+                tickSpacing: self.tickSpacing,
+                maxLiquidityPerTick: self.maxLiquidityPerTick,
+                slot0: self.slot0.clone(),
+                feeGrowthGlobal0X128: self.feeGrowthGlobal0X128.value,
+                feeGrowthGlobal1X128: self.feeGrowthGlobal1X128.value,
+                protocolFees: self.protocolFees.clone(),
+                liquidity: self.liquidity,
+                reserve0: self.reserve0,
+                reserve1: self.reserve1,
+                hooks: self.hooks,
+                hookPermissions: self.hookPermissions,
+            }
+        }
+    }
+
+    impl PoolDerivedState for PoolContract {
+        #[ink(message)]
+        fn observe(&self, secondsAgos: Vec<u64>) -> (Vec<I56>, Vec<U160>) {
+            // return
+            //     observations.observe(
+            //         _blockTimestamp(),
+            //         secondsAgos,
+            //         slot0.tick,
+            //         slot0.observationIndex,
+            //         liquidity,
+            //         slot0.observationCardinality
+            //     );
+            self.observations.observe(
+                self.env().block_timestamp(),
+                secondsAgos,
+                self.slot0.tick,
+                self.slot0.observationIndex,
+                self.liquidity,
+                self.slot0.observationCardinality,
+            )
+        }
+
+        #[ink(message)]
+        fn snapshotCumulativesInside(&self, tickLower: Int24, tickUpper: Int24) -> (I56, U160, u64) {
+            checkTicks(tickLower, tickUpper);
+            let lower: Tick::Info = self.ticks.get(tickLower).unwrap_or_default();
+            let upper: Tick::Info = self.ticks.get(tickUpper).unwrap_or_default();
+            // require(lower.initialized);
+            // require(upper.initialized);
+            assert!(lower.initialized, "TLI");
+            assert!(upper.initialized, "TUI");
+
+            // the accumulators wrap like the unchecked arithmetic of the solidity pool
+            if self.slot0.tick < tickLower {
+                (
+                    lower.tickCumulativeOutside.wrapping_sub(upper.tickCumulativeOutside),
+                    lower
+                        .secondsPerLiquidityOutsideX128
+                        .value
+                        .overflowing_sub(upper.secondsPerLiquidityOutsideX128.value)
+                        .0,
+                    lower.secondsOutside.wrapping_sub(upper.secondsOutside),
+                )
+            } else if self.slot0.tick < tickUpper {
+                let time = self.env().block_timestamp();
+                let (tickCumulative, secondsPerLiquidityCumulativeX128) = self.observations.observeSingle(
+                    time,
+                    0,
+                    self.slot0.tick,
+                    self.slot0.observationIndex,
+                    self.liquidity,
+                    self.slot0.observationCardinality,
+                );
+                (
+                    tickCumulative
+                        .wrapping_sub(lower.tickCumulativeOutside)
+                        .wrapping_sub(upper.tickCumulativeOutside),
+                    secondsPerLiquidityCumulativeX128
+                        .overflowing_sub(lower.secondsPerLiquidityOutsideX128.value)
+                        .0
+                        .overflowing_sub(upper.secondsPerLiquidityOutsideX128.value)
+                        .0,
+                    time.wrapping_sub(lower.secondsOutside)
+                        .wrapping_sub(upper.secondsOutside),
+                )
+            } else {
+                (
+                    upper.tickCumulativeOutside.wrapping_sub(lower.tickCumulativeOutside),
+                    upper
+                        .secondsPerLiquidityOutsideX128
+                        .value
+                        .overflowing_sub(lower.secondsPerLiquidityOutsideX128.value)
+                        .0,
+                    upper.secondsOutside.wrapping_sub(lower.secondsOutside),
+                )
+            }
+        }
+    }
+
     impl PoolAction for PoolContract {
         #[ink(message)]
         fn get_tickspacing(&self)->Int24{
@@ -274,6 +600,39 @@ pub mod crab_swap_pool {
 
         #[ink(message)]
         #[modifiers(lock)]
+        fn skim(&mut self, recipient: Address) -> (u128, u128) {
+            let amount0: u128 = self.balance0().as_u128().saturating_sub(self.reserve0);
+            let amount1: u128 = self.balance1().as_u128().saturating_sub(self.reserve1);
+            if amount0 > 0 {
+                PSP22Ref::transfer(&mut self.token0, recipient, amount0, vec![0u8])
+                    .expect("token0 transfer error!");
+            }
+            if amount1 > 0 {
+                PSP22Ref::transfer(&mut self.token1, recipient, amount1, vec![0u8])
+                    .expect("token1 transfer error!");
+            }
+            self.env().emit_event(Skim {
+                recipient,
+                amount0,
+                amount1,
+            });
+            (amount0, amount1)
+        }
+
+        #[ink(message)]
+        #[modifiers(lock)]
+        fn sync(&mut self) {
+            self.reserve0 = self.balance0().as_u128();
+            self.reserve1 = self.balance1().as_u128();
+            self.env().emit_event(Sync {
+                reserve0: self.reserve0,
+                reserve1: self.reserve1,
+            });
+        }
+
+        #[ink(message)]
+        #[modifiers(whenNotPaused)]
+        #[modifiers(lock)]
         #[modifiers(noDelegateCall)]
         fn flash(&mut self, recipient: Address, amount0: U256, amount1: U256, data: Vec<u8>) {
             // uint128 _liquidity = liquidity;
@@ -289,16 +648,19 @@ pub mod crab_swap_pool {
                 FullMath::mulDivRoundingUp(amount0, U256::from(self.fee), U256::from(1e6 as u64));
             let fee1: U256 =
                 FullMath::mulDivRoundingUp(amount1, U256::from(self.fee), U256::from(1e6 as u64));
-            let balance0Before: U256 = self.balance0();
-            let balance1Before: U256 = self.balance1();
+            // the reserves stand in for the balances before, so only the balances after are queried
+            let balance0Before: U256 = U256::from(self.reserve0);
+            let balance1Before: U256 = U256::from(self.reserve1);
 
             // if (amount0 > 0) TransferHelper.safeTransfer(token0, recipient, amount0);
             // if (amount1 > 0) TransferHelper.safeTransfer(token1, recipient, amount1);
             if amount0 > U256::zero() {
+                self.reserve0 -= amount0.as_u128();
                 PSP22Ref::transfer(&mut self.token0, recipient, amount0.as_u128(), vec![0u8])
                     .expect("token1 transfer error!");
             }
             if amount1 > U256::zero() {
+                self.reserve1 -= amount1.as_u128();
                 PSP22Ref::transfer(&mut self.token1, recipient, amount1.as_u128(), vec![0u8])
                     .expect("token1 transfer error!");
             }
@@ -320,6 +682,8 @@ pub mod crab_swap_pool {
             // uint256 paid1 = balance1After - balance1Before;
             let paid0: U256 = balance0After - balance0Before;
             let paid1: U256 = balance1After - balance1Before;
+            self.reserve0 = balance0After.as_u128();
+            self.reserve1 = balance1After.as_u128();
 
             // if (paid0 > 0) {
             //     uint8 feeProtocol0 = slot0.feeProtocol % 16;
@@ -388,6 +752,7 @@ pub mod crab_swap_pool {
         //     bytes calldata data
         // ) external override noDelegateCall returns (int256 amount0, int256 amount1) {
         #[ink(message)]
+        #[modifiers(whenNotPaused)]
         #[modifiers(noDelegateCall)]
         fn swap(
             &mut self,
@@ -425,6 +790,24 @@ pub mod crab_swap_pool {
 
             //     slot0.unlocked = false;
             self.slot0.unlocked = false;
+            let msg_sender = ink_env::caller::<DefaultEnvironment>();
+            if self.accessPolicy != ADDRESS0.into() {
+                assert!(
+                    AccessPolicyRef::canTrade(&self.accessPolicy, msg_sender, recipient),
+                    "trade not allowed"
+                );
+            }
+            if self._hasHook(BEFORE_SWAP_FLAG) {
+                PoolHooksRef::beforeSwap(
+                    &self.hooks,
+                    msg_sender,
+                    recipient,
+                    zeroForOne,
+                    amountSpecified,
+                    sqrtPriceLimitX96,
+                )
+                .expect("hook rejected swap");
+            }
             //     SwapCache memory cache =
             //         SwapCache({
             //             liquidityStart: liquidity,
@@ -766,20 +1149,16 @@ pub mod crab_swap_pool {
 
             // do the transfers and collect payment
             // if (zeroForOne) {
-            let msg_sender = ink_env::caller::<DefaultEnvironment>();
             if zeroForOne {
                 //     if (amount1 < 0) TransferHelper.safeTransfer(token1, recipient, uint256(-amount1));
                 if amount1 < 0 {
-                    PSP22Ref::transfer(
-                        &mut self.token1,
-                        recipient,
-                        u128::try_from(-amount1).expect("i128 to 128 error!"),
-                        vec![0u8],
-                    )
-                    .expect("token0 transfer error!");
+                    let amount1Out = u128::try_from(-amount1).expect("i128 to 128 error!");
+                    self.reserve1 -= amount1Out;
+                    PSP22Ref::transfer(&mut self.token1, recipient, amount1Out, vec![0u8])
+                        .expect("token0 transfer error!");
                 }
                 //     uint256 balance0Before = balance0();
-                let balance0Before: U256 = self.balance0();
+                let balance0Before: U256 = U256::from(self.reserve0);
                 //     IUniswapV3SwapCallback(msg.sender).uniswapV3SwapCallback(amount0, amount1, data);
                 ink_env::debug_println!("-------------+1");
                 SwapCallbackRef::swapCallback_builder(&msg_sender, amount0, amount1, data)
@@ -792,22 +1171,20 @@ pub mod crab_swap_pool {
                     balance0Before + (U256::from(amount0)) <= self.balance0(),
                     "IIA"
                 );
+                self.reserve0 += u128::try_from(amount0).expect("i128 to 128 error!");
             } else {
                 // } else {
                 // if (amount0 < 0) TransferHelper.safeTransfer(token0, recipient, uint256(-amount0));
                 if amount0 < 0 {
-                    PSP22Ref::transfer(
-                        &mut self.token0,
-                        recipient,
-                        u128::try_from(-amount0).expect("i128 to 128 error!"),
-                        vec![0u8],
-                    )
-                    .expect("token0 transfer error!");
+                    let amount0Out = u128::try_from(-amount0).expect("i128 to 128 error!");
+                    self.reserve0 -= amount0Out;
+                    PSP22Ref::transfer(&mut self.token0, recipient, amount0Out, vec![0u8])
+                        .expect("token0 transfer error!");
                 }
                 //     uint256 balance1Before = balance1();
                 //     IUniswapV3SwapCallback(msg.sender).uniswapV3SwapCallback(amount0, amount1, data);
                 //     require(balance1Before.add(uint256(amount1)) <= balance1(), 'IIA');
-                let balance1Before: U256 = self.balance1();
+                let balance1Before: U256 = U256::from(self.reserve1);
                 ink_env::debug_println!("-------------+3");
                 SwapCallbackRef::swapCallback_builder(&msg_sender, amount0, amount1, data)
                     .call_flags(CallFlags::default().set_allow_reentry(true))
@@ -818,6 +1195,7 @@ pub mod crab_swap_pool {
                     balance1Before + (U256::from(amount1)) <= self.balance1(),
                     "IIA"
                 );
+                self.reserve1 += u128::try_from(amount1).expect("i128 to 128 error!");
             }
 
             //     emit Swap(msg.sender, recipient, amount0, amount1, state.sqrtPriceX96, state.liquidity, state.tick);
@@ -830,6 +1208,30 @@ pub mod crab_swap_pool {
                 liquidity: state.liquidity,
                 tick: state.tick,
             });
+            if self._hasHook(AFTER_SWAP_FLAG) {
+                PoolHooksRef::afterSwap(
+                    &self.hooks,
+                    msg_sender,
+                    recipient,
+                    zeroForOne,
+                    amount0,
+                    amount1,
+                    state.sqrtPriceX96,
+                    state.tick,
+                )
+                .expect("hook rejected swap");
+            }
+
+            // trip the circuit breaker, the swap itself goes through but the pool is paused after it
+            if self.maxTickMove > 0
+                && (state.tick - slot0Start.tick).unsigned_abs() > self.maxTickMove
+            {
+                self.env().emit_event(CircuitBreakerTripped {
+                    tickBefore: slot0Start.tick,
+                    tickAfter: state.tick,
+                });
+                self._setPaused(true);
+            }
 
             //     slot0.unlocked = true;
             self.slot0.unlocked = true;
@@ -853,15 +1255,18 @@ pub mod crab_swap_pool {
             tickUpper: Int24,
             amount0Requested: u128,
             amount1Requested: u128,
+            salt: Option<[u8; 32]>,
         ) -> (u128, u128) {
             //     // we don't need to checkTicks here, because invalid positions will never have non-zero tokensOwed{0,1}
             //     Position.Info storage position = positions.get(msg.sender, tickLower, tickUpper);
             ink_env::debug_println!("^^^^^^^^^^^^^^^^1");
             let msg_sender: AccountId = ink_env::caller::<DefaultEnvironment>();
             ink_env::debug_println!("^^^^^^^^^^^^^^^^2");
+            let salt = salt.unwrap_or_default();
+            let positionKey = PositionKey::compute(msg_sender, tickLower, tickUpper, salt);
             let mut position: Position::Info = self
                 .positions
-                .get((msg_sender, tickLower, tickUpper))
+                .get(positionKey)
                 .expect("position is not exist!");
             ink_env::debug_println!("^^^^^^^^^^^^^^^^3");
             //     amount0 = amount0Requested > position.tokensOwed0 ? position.tokensOwed0 : amount0Requested;
@@ -885,6 +1290,7 @@ pub mod crab_swap_pool {
             if amount0 > 0 {
                 ink_env::debug_println!("^^^^^^^^^^^^^^^^6");
                 position.tokensOwed0 -= amount0;
+                self.reserve0 -= amount0;
                 ink_env::debug_println!("^^^^^^^^^^^^^^^^7");
                 // TransferHelper::safeTransfer instead of transfer of PSP22.
                 PSP22Ref::transfer(&mut self.token0, recipient, amount0, vec![0u8])
@@ -898,6 +1304,7 @@ pub mod crab_swap_pool {
             if amount1 > 0 {
                 ink_env::debug_println!("^^^^^^^^^^^^^^^^9");
                 position.tokensOwed1 -= amount1;
+                self.reserve1 -= amount1;
                 ink_env::debug_println!("^^^^^^^^^^^^^^^^10");
                 PSP22Ref::transfer(&mut self.token1, recipient, amount1, vec![0u8])
                     .expect("token1 transfer error!");
@@ -909,6 +1316,7 @@ pub mod crab_swap_pool {
                 recipient,
                 tickLower,
                 tickUpper,
+                salt,
                 amount0,
                 amount1,
             });
@@ -921,19 +1329,25 @@ pub mod crab_swap_pool {
             //         amount1,
             //     });
             ink_env::debug_println!("^^^^^^^^^^^^^^^^12");
-            self.positions
-                .insert((msg_sender, tickLower, tickUpper), &position);
+            self.positions.insert(positionKey, &position);
             ink_env::debug_println!("^^^^^^^^^^^^^^^^13");
             (amount0, amount1)
         }
 
         /// @inheritdoc IUniswapV3PoolActions
-        /// @dev not locked because it initializes unlocked
+        /// @dev not locked because it initializes unlocked, the pool is only locked around the hooks
         // #[ink(message, payable)]
         #[ink(message, payable)]
         fn initialize(&mut self, sqrtPriceX96: U160) {
             // require(slot0.sqrtPriceX96 == 0, 'AI');
             assert!(self.slot0.sqrtPriceX96.value.is_zero(), "AI");
+            let msg_sender = ink_env::caller::<DefaultEnvironment>();
+            if self._hasHook(BEFORE_INITIALIZE_FLAG) {
+                self.slot0.unlocked = false;
+                PoolHooksRef::beforeInitialize(&self.hooks, msg_sender, sqrtPriceX96)
+                    .expect("hook rejected initialize");
+                self.slot0.unlocked = true;
+            }
             // int24 tick = TickMath.getTickAtSqrtRatio(sqrtPriceX96);
             let tick: Int24 = getTickAtSqrtRatio(sqrtPriceX96);
             // (uint16 cardinality, uint16 cardinalityNext) = observations.initialize(_blockTimestamp());
@@ -948,17 +1362,26 @@ pub mod crab_swap_pool {
             //     feeProtocol: 0,
             //     unlocked: true
             // });
+            // the protocol fee starts at the default the factory holds for the fee tier
+            let (feeProtocol0, feeProtocol1) =
+                FactoryRef::get_fee_protocol_default(&self.factory, self.feeTier);
             self.slot0 = Slot0 {
                 sqrtPriceX96: Uint160::new_with_u256(sqrtPriceX96),
                 tick: tick,
                 observationIndex: 0,
                 observationCardinality: cardinality,
                 observationCardinalityNext: cardinalityNext,
-                feeProtocol: 0,
+                feeProtocol: feeProtocol0 + (feeProtocol1 << 4),
                 unlocked: true,
             };
             // emit Initialize(sqrtPriceX96, tick);
             self.env().emit_event(Initialize { sqrtPriceX96, tick });
+            if self._hasHook(AFTER_INITIALIZE_FLAG) {
+                self.slot0.unlocked = false;
+                PoolHooksRef::afterInitialize(&self.hooks, msg_sender, sqrtPriceX96, tick)
+                    .expect("hook rejected initialize");
+                self.slot0.unlocked = true;
+            }
         }
 
         #[ink(message)]
@@ -977,15 +1400,27 @@ pub mod crab_swap_pool {
             position_address: Address,
             tick_lower: Int24,
             tick_upper: Int24,
+            salt: Option<[u8; 32]>,
         ) -> Position::Info {
             self.positions
-                .get((position_address, tick_lower, tick_upper))
+                .get(PositionKey::compute(
+                    position_address,
+                    tick_lower,
+                    tick_upper,
+                    salt.unwrap_or_default(),
+                ))
                 .unwrap_or(Default::default())
         }
 
         #[ink(message)]
         #[modifiers(lock)]
-        fn burn(&mut self, tickLower: Int24, tickUpper: Int24, amount: u128) -> (U256, U256) {
+        fn burn(
+            &mut self,
+            tickLower: Int24,
+            tickUpper: Int24,
+            amount: u128,
+            salt: Option<[u8; 32]>,
+        ) -> (U256, U256) {
             // (Position.Info storage position, int256 amount0Int, int256 amount1Int) =
             // _modifyPosition(
             //     ModifyPositionParams({
@@ -996,11 +1431,20 @@ pub mod crab_swap_pool {
             //     })
             // );
             let msg_sender = ink_env::caller::<DefaultEnvironment>();
+            let salt = salt.unwrap_or_default();
+            // the burn hooks are notified only, neither an error nor a trap of the hook can block the withdrawal of
+            // liquidity, and their gas is capped so they can not exhaust it either
+            if self._hasHook(BEFORE_BURN_FLAG) {
+                let _ = PoolHooksRef::beforeBurn_builder(&self.hooks, msg_sender, tickLower, tickUpper, amount, salt)
+                    .gas_limit(BURN_HOOK_GAS_LIMIT)
+                    .fire();
+            }
             let (mut position, amount0Int, amount1Int) =
                 self._modifyPosition(ModifyPositionParams {
                     owner: msg_sender,
                     tickLower,
                     tickUpper,
+                    salt,
                     liquidityDelta: -i128::try_from(amount).expect("amount to i128 failed!"),
                 });
 
@@ -1021,21 +1465,41 @@ pub mod crab_swap_pool {
             if amount1 > U256::zero() {
                 position.tokensOwed1 += amount1.as_u128();
             }
+            self.positions.insert(
+                PositionKey::compute(msg_sender, tickLower, tickUpper, salt),
+                &position,
+            );
             self.env().emit_event(Burn {
                 owner: msg_sender,
                 tickLower,
                 tickUpper,
+                salt,
                 amount,
                 amount0,
                 amount1,
             });
             // emit Burn(msg.sender, tickLower, tickUpper, amount, amount0, amount1);
+            if self._hasHook(AFTER_BURN_FLAG) {
+                let _ = PoolHooksRef::afterBurn_builder(
+                    &self.hooks,
+                    msg_sender,
+                    tickLower,
+                    tickUpper,
+                    amount,
+                    salt,
+                    amount0,
+                    amount1,
+                )
+                .gas_limit(BURN_HOOK_GAS_LIMIT)
+                .fire();
+            }
             (amount0, amount1)
         }
 
         /// @inheritdoc IUniswapV3PoolActions
         /// @dev noDelegateCall is applied indirectly via _modifyPosition
         #[ink(message)]
+        #[modifiers(whenNotPaused)]
         #[modifiers(lock)]
         fn mint(
             &mut self,
@@ -1044,6 +1508,7 @@ pub mod crab_swap_pool {
             tickUpper: Int24,
             amount: u128,
             data: Vec<u8>,
+            salt: Option<[u8; 32]>,
         ) -> (U256, U256) {
             //uint256 amount0, uint256 amount1
             ink_env::debug_println!("--------------1");
@@ -1058,10 +1523,31 @@ pub mod crab_swap_pool {
             //             liquidityDelta: int256(amount).toInt128()
             //         })
             //     );
+            let salt = salt.unwrap_or_default();
+            let manager_address: AccountId = ink_env::caller::<DefaultEnvironment>();
+            if self.accessPolicy != ADDRESS0.into() {
+                assert!(
+                    AccessPolicyRef::canProvideLiquidity(&self.accessPolicy, recipient),
+                    "liquidity not allowed"
+                );
+            }
+            if self._hasHook(BEFORE_MINT_FLAG) {
+                PoolHooksRef::beforeMint(
+                    &self.hooks,
+                    manager_address,
+                    recipient,
+                    tickLower,
+                    tickUpper,
+                    amount,
+                    salt,
+                )
+                .expect("hook rejected mint");
+            }
             let (_, amount0Int, amount1Int) = self._modifyPosition(ModifyPositionParams {
                 owner: recipient,
                 tickLower: tickLower,
                 tickUpper: tickUpper,
+                salt,
                 liquidityDelta: i128::try_from(amount).unwrap(),
             });
             ink_env::debug_println!("--------------2");
@@ -1069,18 +1555,11 @@ pub mod crab_swap_pool {
             let amount0: U256 = U256::from(amount0Int);
             let amount1: U256 = U256::from(amount1Int);
 
-            let mut balance0Before: U256 = U256::zero();
-            let mut balance1Before: U256 = U256::zero();
             // if (amount0 > 0) balance0Before = balance0();
-            if amount0 > U256::from(0) {
-                balance0Before = self.balance0();
-            }
             // if (amount1 > 0) balance1Before = balance1();
-            if amount1 > U256::from(0) {
-                balance1Before = self.balance1();
-            }
+            let balance0Before: U256 = U256::from(self.reserve0);
+            let balance1Before: U256 = U256::from(self.reserve1);
             ink_env::debug_println!("**************3");
-            let manager_address: AccountId = ink_env::caller::<DefaultEnvironment>();
             ink_env::debug_println!("manager_address is:{:?}", manager_address);
             ink_env::debug_println!("amount0 is:{:?}", amount0);
             ink_env::debug_println!("amount1 is:{:?}", amount1);
@@ -1101,9 +1580,11 @@ pub mod crab_swap_pool {
             // ink_env::debug_println!("balance is:{:?}",balance);
             if amount0 > U256::from(0) {
                 assert!(balance0Before + amount0 <= self.balance0(), "M0");
+                self.reserve0 += amount0.as_u128();
             }
             if amount1 > U256::from(0) {
                 assert!(balance1Before + amount1 <= self.balance1(), "M1");
+                self.reserve1 += amount1.as_u128();
             }
             ink_env::debug_println!("**************4");
             // emit Mint(msg.sender, recipient, tickLower, tickUpper, amount, amount0, amount1);
@@ -1112,10 +1593,25 @@ pub mod crab_swap_pool {
                 owner: recipient,
                 tickLower,
                 tickUpper,
+                salt,
                 amount,
                 amount0,
                 amount1,
             });
+            if self._hasHook(AFTER_MINT_FLAG) {
+                PoolHooksRef::afterMint(
+                    &self.hooks,
+                    manager_address,
+                    recipient,
+                    tickLower,
+                    tickUpper,
+                    amount,
+                    salt,
+                    amount0,
+                    amount1,
+                )
+                .expect("hook rejected mint");
+            }
             ink_env::debug_println!("**************5");
             (amount0, amount1)
         }
@@ -1173,6 +1669,7 @@ pub mod crab_swap_pool {
     /// @param owner The owner of the position and recipient of any minted liquidity
     /// @param tickLower The lower tick of the position
     /// @param tickUpper The upper tick of the position
+    /// @param salt The salt of the position, zero for the default position
     /// @param amount The amount of liquidity minted to the position range
     /// @param amount0 How much token0 was required for the minted liquidity
     /// @param amount1 How much token1 was required for the minted liquidity
@@ -1186,6 +1683,7 @@ pub mod crab_swap_pool {
         tickLower: Int24,
         // #[ink(topic)]
         tickUpper: Int24,
+        salt: [u8; 32],
         amount: u128,
         amount0: U256,
         amount1: U256,
@@ -1196,6 +1694,7 @@ pub mod crab_swap_pool {
     /// @param owner The owner of the position for which fees are collected
     /// @param tickLower The lower tick of the position
     /// @param tickUpper The upper tick of the position
+    /// @param salt The salt of the position, zero for the default position
     /// @param amount0 The amount of token0 fees collected
     /// @param amount1 The amount of token1 fees collected
     #[ink(event)]
@@ -1207,6 +1706,7 @@ pub mod crab_swap_pool {
         tickLower: Int24,
         #[ink(topic)]
         tickUpper: Int24,
+        salt: [u8; 32],
         amount0: u128,
         amount1: u128,
     }
@@ -1217,6 +1717,8 @@ pub mod crab_swap_pool {
         // the lower and upper tick of the position
         tickLower: Int24,
         tickUpper: Int24,
+        // distinguishes positions of the same owner on the same range
+        salt: [u8; 32],
         // any change in liquidity
         liquidityDelta: i128,
     }
@@ -1229,6 +1731,7 @@ pub mod crab_swap_pool {
             token1: Address,
             fee: Uint24,
             tickSpacing: Int24,
+            config: PoolConfig,
         ) -> Self {
             // (factory, token0, token1, fee, _tickSpacing) = IUniswapV3PoolDeployer(msg.sender).parameters();
             //
@@ -1237,6 +1740,7 @@ pub mod crab_swap_pool {
                 instance.token0 = token0;
                 instance.token1 = token1;
                 instance.fee = fee;
+                instance.feeTier = fee;
                 instance.tickSpacing = tickSpacing;
                 instance.fee_growth_global0_x128 = Uint160::new();
                 instance.fee_growth_global1_x128 = Uint160::new();
@@ -1248,6 +1752,29 @@ pub mod crab_swap_pool {
                 instance.observations = Observations::new();
                 instance.maxLiquidityPerTick = Tick::tickSpacingToMaxLiquidityPerTick(tickSpacing);
                 instance.protocolFees = Default::default();
+                instance.reserve0 = 0;
+                instance.reserve1 = 0;
+                instance.storageVersion = STORAGE_VERSION;
+                instance.accessPolicy = config.access_policy;
+                instance.hooks = config.hooks;
+                instance.hookPermissions = if config.hooks == ADDRESS0.into() {
+                    0
+                } else {
+                    PoolHooksRef::getHookPermissions(&config.hooks)
+                };
+                if config.dynamic_fee {
+                    assert!(
+                        config.min_fee <= fee && fee <= config.max_fee && config.max_fee < 1000000,
+                        "fee bounds"
+                    );
+                    instance.dynamicFee = true;
+                    instance.feeController = config.fee_controller;
+                    instance.minFee = config.min_fee;
+                    instance.maxFee = config.max_fee;
+                } else {
+                    instance.minFee = fee;
+                    instance.maxFee = fee;
+                }
                 instance.no_delegate_call.original = ink_env::account_id::<DefaultEnvironment>();
                 ink_env::debug_println!("----------------6");
             })
@@ -1273,6 +1800,7 @@ pub mod crab_swap_pool {
                 params.owner,
                 params.tickLower,
                 params.tickUpper,
+                params.salt,
                 params.liquidityDelta,
                 _slot0.tick,
             );
@@ -1341,19 +1869,22 @@ pub mod crab_swap_pool {
         /// @param owner the owner of the position
         /// @param tickLower the lower tick of the position's tick range
         /// @param tickUpper the upper tick of the position's tick range
+        /// @param salt the salt of the position
         /// @param tick the current tick, passed to avoid sloads
         fn _updatePosition(
             &mut self,
             owner: Address,
             tickLower: Int24,
             tickUpper: Int24,
+            salt: [u8; 32],
             liquidityDelta: i128,
             tick: Int24,
         ) -> Position::Info {
-            // position = positions.get(owner, tickLower, tickUpper);
+            // position = positions.get(owner, tickLower, tickUpper, salt);
+            let positionKey = PositionKey::compute(owner, tickLower, tickUpper, salt);
             let mut position: Position::Info = self
                 .positions
-                .get((owner, tickLower, tickUpper))
+                .get(positionKey)
                 .unwrap_or(Default::default());
             ink_env::debug_println!("++++++++++++7");
             let _feeGrowthGlobal0X128: U256 = self.feeGrowthGlobal0X128.value; // LOAD for gas optimization
@@ -1439,7 +1970,7 @@ pub mod crab_swap_pool {
                 }
             }
             ink_env::debug_println!("++++++++++++14");
-            let (feeGrowthInside0X128, feeGrowthInside1X128) = self.getFeeGrowthInside(
+            let (feeGrowthInside0X128, feeGrowthInside1X128) = self._getFeeGrowthInside(
                 tickLower,
                 tickUpper,
                 tick,
@@ -1448,8 +1979,7 @@ pub mod crab_swap_pool {
             );
             ink_env::debug_println!("++++++++++++15");
             position.update(liquidityDelta, feeGrowthInside0X128, feeGrowthInside1X128);
-            self.positions
-                .insert((owner, tickLower, tickUpper), &position);
+            self.positions.insert(positionKey, &position);
             // clear any tick data that is no longer needed
             if liquidityDelta < 0 {
                 if flippedLower {
@@ -1462,6 +1992,25 @@ pub mod crab_swap_pool {
             position
         }
 
+        /// @dev Pause or unpause the pool and emit the matching event
+        fn _setPaused(&mut self, paused: bool) {
+            if self.paused == paused {
+                return;
+            }
+            self.paused = paused;
+            let account = ink_env::caller::<DefaultEnvironment>();
+            if paused {
+                self.env().emit_event(Paused { account });
+            } else {
+                self.env().emit_event(Unpaused { account });
+            }
+        }
+
+        /// @dev Whether the pool has a hooks contract that asked for the given hook
+        fn _hasHook(&self, flag: u16) -> bool {
+            self.hooks != ADDRESS0.into() && self.hookPermissions & flag != 0
+        }
+
         /// @dev Returns the block timestamp truncated to 32 bits, i.e. mod 2**32. This method is overridden in tests.
         pub fn _blockTimestamp(&self) -> u64 {
             // return uint32(block.timestamp); // truncation is desired
@@ -1477,8 +2026,8 @@ pub mod crab_swap_pool {
         /// @param feeGrowthGlobal1X128 The all-time global fee growth, per unit of liquidity, in token1
         /// @return feeGrowthInside0X128 The all-time fee growth in token0, per unit of liquidity, inside the position's tick boundaries
         /// @return feeGrowthInside1X128 The all-time fee growth in token1, per unit of liquidity, inside the position's tick boundaries
-        pub fn getFeeGrowthInside(
-            &mut self,
+        fn _getFeeGrowthInside(
+            &self,
             tickLower: Int24,
             tickUpper: Int24,
             tickCurrent: Int24,
@@ -1587,6 +2136,7 @@ pub mod crab_swap_pool {
     /// @param owner The owner of the position for which liquidity is removed
     /// @param tickLower The lower tick of the position
     /// @param tickUpper The upper tick of the position
+    /// @param salt The salt of the position, zero for the default position
     /// @param amount The amount of liquidity to remove
     /// @param amount0 The amount of token0 withdrawn
     /// @param amount1 The amount of token1 withdrawn
@@ -1598,11 +2148,83 @@ pub mod crab_swap_pool {
         tickLower: Int24,
         #[ink(topic)]
         tickUpper: Int24,
+        salt: [u8; 32],
         amount: u128,
         amount0: U256,
         amount1: U256,
     }
 
+    /// @notice Emitted when the swap fee of a dynamic-fee pool is changed
+    /// @param feeOld The previous fee in hundredths of a bip
+    /// @param feeNew The updated fee in hundredths of a bip
+    #[ink(event)]
+    pub struct SetFee {
+        feeOld: Uint24,
+        feeNew: Uint24,
+    }
+
+    /// @notice Emitted when the pool is paused
+    /// @param account The factory, or the swapper whose swap tripped the circuit breaker
+    #[ink(event)]
+    pub struct Paused {
+        #[ink(topic)]
+        account: Address,
+    }
+
+    /// @notice Emitted when the pool is unpaused
+    /// @param account The factory
+    #[ink(event)]
+    pub struct Unpaused {
+        #[ink(topic)]
+        account: Address,
+    }
+
+    /// @notice Emitted when a swap moved the tick by more than maxTickMove and paused the pool
+    /// @param tickBefore The tick before the swap
+    /// @param tickAfter The tick after the swap
+    #[ink(event)]
+    pub struct CircuitBreakerTripped {
+        tickBefore: Int24,
+        tickAfter: Int24,
+    }
+
+    /// @notice Emitted when the code of the pool is replaced by the factory
+    /// @param code_hash The hash of the new pool code
+    #[ink(event)]
+    pub struct CodeHashSet {
+        code_hash: Hash,
+    }
+
+    /// @notice Emitted when the storage of the pool is migrated after an upgrade
+    /// @param fromVersion The storage version before the migration
+    /// @param toVersion The storage version after the migration
+    #[ink(event)]
+    pub struct Migrated {
+        fromVersion: u32,
+        toVersion: u32,
+    }
+
+    /// @notice Emitted when token balances above the reserves are sent out
+    /// @param recipient The address that received the excess tokens
+    /// @param amount0 The amount of token0 sent
+    /// @param amount1 The amount of token1 sent
+    #[ink(event)]
+    pub struct Skim {
+        #[ink(topic)]
+        recipient: Address,
+        amount0: u128,
+        amount1: u128,
+    }
+
+    /// @notice Emitted when the reserves are re-read from the token balances
+    /// @param reserve0 The token0 reserve after the sync
+    /// @param reserve1 The token1 reserve after the sync
+    #[ink(event)]
+    pub struct Sync {
+        reserve0: u128,
+        reserve1: u128,
+    }
+
     #[cfg(test)]
     mod tests {
         use super::*;
@@ -1621,18 +2243,155 @@ pub mod crab_swap_pool {
             let default_accounts = default_accounts();
 
             set_next_caller(default_accounts.alice);
-            // factory:Address,token0: Address, token1: Address, fee: Uint24, tickSpacing: Int24
+            // factory:Address,token0: Address, token1: Address, fee: Uint24, tickSpacing: Int24, config: PoolConfig
             let pool_contract = PoolContract::new(
                 default_accounts.alice,
                 default_accounts.alice,
                 default_accounts.alice,
                 500,
                 10,
+                PoolConfig::default(),
             );
             ink_env::debug_println!("test success:{:?}", pool_contract);
             println!("test success:{:?}", pool_contract);
             // assert_eq!(weth9_contract.metadata.name,Some(String::from("weth9")));
         }
+
+        fn new_pool(config: PoolConfig) -> PoolContract {
+            let default_accounts = default_accounts();
+            set_next_caller(default_accounts.alice);
+            PoolContract::new(
+                default_accounts.alice,
+                default_accounts.bob,
+                default_accounts.charlie,
+                500,
+                10,
+                config,
+            )
+        }
+
+        #[ink::test]
+        fn getters_return_the_constructor_arguments() {
+            let default_accounts = default_accounts();
+            let pool = new_pool(PoolConfig::default());
+            assert_eq!(pool.token0(), default_accounts.bob);
+            assert_eq!(pool.token1(), default_accounts.charlie);
+            assert_eq!(pool.fee(), 500);
+            assert_eq!(pool.feeTier(), 500);
+            assert_eq!(pool.get_tickspacing(), 10);
+            assert_eq!(pool.get_factory(), default_accounts.alice);
+            assert_eq!(
+                pool.maxLiquidityPerTick(),
+                Tick::tickSpacingToMaxLiquidityPerTick(10)
+            );
+            assert_eq!(pool.dynamicFee(), (false, ADDRESS0.into(), 500, 500));
+            assert_eq!(pool.hooks(), (ADDRESS0.into(), 0));
+            assert_eq!(pool.accessPolicy(), ADDRESS0.into());
+            assert_eq!(pool.liquidity(), 0);
+            assert_eq!(pool.getReserves(), (0, 0));
+            assert_eq!(pool.feeGrowthGlobal0X128(), U256::zero());
+            assert_eq!(pool.feeGrowthGlobal1X128(), U256::zero());
+            assert_eq!(pool.protocolFees(), ProtocolFees::default());
+            assert_eq!(pool.storageVersion(), STORAGE_VERSION);
+            assert!(!pool.paused());
+            assert_eq!(pool.maxTickMove(), 0);
+            assert!(!pool.ticks(10).initialized);
+            assert_eq!(pool.tickBitmap(0), U256::zero());
+        }
+
+        #[ink::test]
+        fn dynamic_fee_getter_returns_the_config() {
+            let default_accounts = default_accounts();
+            let pool = new_pool(PoolConfig {
+                dynamic_fee: true,
+                fee_controller: default_accounts.django,
+                min_fee: 100,
+                max_fee: 3000,
+                ..Default::default()
+            });
+            assert_eq!(pool.dynamicFee(), (true, default_accounts.django, 100, 3000));
+            assert_eq!(pool.fee(), 500);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "fee bounds")]
+        fn dynamic_fee_must_start_within_its_bounds() {
+            new_pool(PoolConfig {
+                dynamic_fee: true,
+                min_fee: 1000,
+                max_fee: 3000,
+                ..Default::default()
+            });
+        }
+
+        fn dynamic_fee_pool() -> PoolContract {
+            new_pool(PoolConfig {
+                dynamic_fee: true,
+                fee_controller: default_accounts().django,
+                min_fee: 100,
+                max_fee: 3000,
+                ..Default::default()
+            })
+        }
+
+        #[ink::test]
+        fn fee_controller_sets_the_fee_within_its_bounds() {
+            let mut pool = dynamic_fee_pool();
+            set_next_caller(default_accounts().django);
+            pool.setFee(100);
+            assert_eq!(pool.fee(), 100);
+            pool.setFee(3000);
+            assert_eq!(pool.fee(), 3000);
+            // the fee tier the pool is registered under stays
+            assert_eq!(pool.feeTier(), 500);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "fee out of bounds")]
+        fn set_fee_rejects_a_fee_above_the_bounds() {
+            let mut pool = dynamic_fee_pool();
+            set_next_caller(default_accounts().django);
+            pool.setFee(3001);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "fee out of bounds")]
+        fn set_fee_rejects_a_fee_below_the_bounds() {
+            let mut pool = dynamic_fee_pool();
+            set_next_caller(default_accounts().django);
+            pool.setFee(99);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "static fee pool")]
+        fn set_fee_rejects_a_static_fee_pool() {
+            let mut pool = new_pool(PoolConfig {
+                fee_controller: default_accounts().django,
+                ..Default::default()
+            });
+            set_next_caller(default_accounts().django);
+            pool.setFee(500);
+        }
+
+        #[ink::test]
+        fn pool_state_matches_the_getters() {
+            let pool = new_pool(PoolConfig::default());
+            let state = pool.pool_state();
+            assert_eq!(state.factory, pool.get_factory());
+            assert_eq!(state.token0, pool.token0());
+            assert_eq!(state.token1, pool.token1());
+            assert_eq!(state.fee, pool.fee());
+            assert_eq!(state.feeTier, pool.feeTier());
+            assert_eq!(state.tickSpacing, pool.get_tickspacing());
+            assert_eq!(state.maxLiquidityPerTick, pool.maxLiquidityPerTick());
+            assert_eq!(state.slot0, pool.getSlot0());
+            assert_eq!(state.feeGrowthGlobal0X128, pool.feeGrowthGlobal0X128());
+            assert_eq!(state.feeGrowthGlobal1X128, pool.feeGrowthGlobal1X128());
+            assert_eq!(state.protocolFees, pool.protocolFees());
+            assert_eq!(state.liquidity, pool.liquidity());
+            assert_eq!((state.reserve0, state.reserve1), pool.getReserves());
+            assert_eq!((state.hooks, state.hookPermissions), pool.hooks());
+        }
     }
 
     /// @dev Common checks for valid tick inputs.
@@ -0,0 +1,602 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#![feature(min_specialization)]
+
+/// @title M-of-N multisig with a timelock, meant to own the factory
+/// @notice A signer proposes a batch of calls, the other signers approve it. Once the threshold is reached the
+/// proposal is queued and can be executed after the minimum delay. Only approvals of the current signers count, both
+/// for queueing and for executing, and a removed signer's approvals do not come back when they are added again. A
+/// proposal that has not been executed can be canceled by its proposer, or by a proposal calling this contract. Changing
+/// the signers, the threshold or the delay goes through a proposal calling this contract.
+#[openbrush::contract]
+pub mod governor {
+    use ink_env::call::{build_call, Call, ExecutionInput, Selector};
+    use ink_env::{CallFlags, DefaultEnvironment};
+    use ink_lang::codegen::Env;
+    use ink_lang::codegen::EmitEvent;
+    use ink_prelude::vec::Vec;
+    use ink_storage::traits::{PackedLayout, SpreadAllocate, SpreadLayout};
+    use ink_storage::Mapping;
+    use scale::{Decode, Encode, Output};
+
+    // the most signers the contract can have, bounds the signer loops
+    pub const MAX_SIGNERS: u32 = 50;
+    // the shortest delay between queueing and executing a proposal, one day in milliseconds
+    pub const MIN_DELAY: u64 = 86_400_000;
+
+    /// @notice A call made by the governor when a proposal is executed
+    #[derive(Debug, Clone, PartialEq, Eq, Encode, Decode, SpreadLayout, PackedLayout)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout))]
+    pub struct Transaction {
+        // the contract to call
+        pub callee: AccountId,
+        // the selector of the message to call
+        pub selector: [u8; 4],
+        // the scale encoded arguments of the message
+        pub input: Vec<u8>,
+        // the value transferred with the call
+        pub transferred_value: Balance,
+        // the gas limit of the call, 0 forwards all remaining gas
+        pub gas_limit: u64,
+        // must be true when the call comes back into the governor, e.g. to change the threshold
+        pub allow_reentry: bool,
+    }
+
+    #[derive(Debug, Default, Clone, PartialEq, Eq, Encode, Decode, SpreadLayout, PackedLayout)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout))]
+    pub struct Proposal {
+        pub proposer: AccountId,
+        pub calls: Vec<Transaction>,
+        // the earliest timestamp of execution, 0 until the threshold is reached
+        pub eta: u64,
+        pub executed: bool,
+        pub canceled: bool,
+    }
+
+    /// @dev Writes already encoded arguments as they are, so the raw input of a Transaction can be forwarded
+    struct CallInput<'a>(&'a [u8]);
+
+    impl<'a> Encode for CallInput<'a> {
+        fn encode_to<T: Output + ?Sized>(&self, dest: &mut T) {
+            dest.write(self.0);
+        }
+    }
+
+    #[ink(storage)]
+    #[derive(SpreadAllocate)]
+    pub struct GovernorContract {
+        signers: Vec<AccountId>,
+        is_signer: Mapping<AccountId, bool>,
+        // approvals needed to queue a proposal
+        threshold: u32,
+        // the delay between queueing and execution in milliseconds
+        min_delay: u64,
+        proposals: Mapping<u32, Proposal>,
+        // keyed by (proposal, signer, epoch of the signer)
+        approved: Mapping<(u32, AccountId, u32), bool>,
+        // bumped when a signer is removed, which drops all approvals given before
+        signer_epochs: Mapping<AccountId, u32>,
+        next_proposal_id: u32,
+    }
+
+    /// @notice Emitted when a proposal is created
+    #[ink(event)]
+    pub struct Proposed {
+        #[ink(topic)]
+        id: u32,
+        #[ink(topic)]
+        proposer: AccountId,
+        calls: Vec<Transaction>,
+    }
+
+    /// @notice Emitted when a signer approves a proposal
+    #[ink(event)]
+    pub struct Approved {
+        #[ink(topic)]
+        id: u32,
+        #[ink(topic)]
+        signer: AccountId,
+        approvals: u32,
+    }
+
+    /// @notice Emitted when a proposal reaches the threshold
+    /// @param eta The earliest timestamp the proposal can be executed at
+    #[ink(event)]
+    pub struct Queued {
+        #[ink(topic)]
+        id: u32,
+        eta: u64,
+    }
+
+    #[ink(event)]
+    pub struct Executed {
+        #[ink(topic)]
+        id: u32,
+    }
+
+    #[ink(event)]
+    pub struct Canceled {
+        #[ink(topic)]
+        id: u32,
+        #[ink(topic)]
+        signer: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct SignerAdded {
+        #[ink(topic)]
+        signer: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct SignerRemoved {
+        #[ink(topic)]
+        signer: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct ThresholdChanged {
+        threshold: u32,
+    }
+
+    #[ink(event)]
+    pub struct MinDelayChanged {
+        min_delay: u64,
+    }
+
+    impl GovernorContract {
+        /// @param signers The initial signers, without duplicates
+        /// @param threshold The approvals needed to queue a proposal
+        /// @param min_delay The delay between queueing and execution in milliseconds, at least MIN_DELAY
+        #[ink(constructor)]
+        pub fn new(signers: Vec<AccountId>, threshold: u32, min_delay: u64) -> Self {
+            ink_lang::utils::initialize_contract(|instance: &mut Self| {
+                assert!(signers.len() as u32 <= MAX_SIGNERS, "too many signers");
+                for signer in signers.iter() {
+                    assert!(!instance.is_signer.get(signer).unwrap_or(false), "duplicate signer");
+                    instance.is_signer.insert(signer, &true);
+                }
+                instance.signers = signers;
+                ensure_requirement(instance.signers.len() as u32, threshold);
+                instance.threshold = threshold;
+                ensure_delay(min_delay);
+                instance.min_delay = min_delay;
+            })
+        }
+
+        /// @notice Proposes a batch of calls, counting as the proposer's approval
+        /// @return id The id of the proposal
+        #[ink(message)]
+        pub fn propose(&mut self, calls: Vec<Transaction>) -> u32 {
+            let caller = self.ensure_signer();
+            assert!(!calls.is_empty(), "no calls");
+            let id = self.next_proposal_id;
+            self.next_proposal_id += 1;
+            self.proposals.insert(
+                id,
+                &Proposal {
+                    proposer: caller,
+                    calls: calls.clone(),
+                    ..Default::default()
+                },
+            );
+            self.env().emit_event(Proposed {
+                id,
+                proposer: caller,
+                calls,
+            });
+            self.approve(id);
+            id
+        }
+
+        /// @notice Approves a proposal, queueing it once the threshold is reached
+        #[ink(message)]
+        pub fn approve(&mut self, id: u32) {
+            let caller = self.ensure_signer();
+            let mut proposal = self.proposals.get(id).expect("proposal not exist");
+            assert!(!proposal.canceled && !proposal.executed, "proposal closed");
+            assert!(!self.has_approved(id, caller), "already approved");
+            self.approved.insert((id, caller, self.signer_epoch(caller)), &true);
+            let approvals = self.get_approvals(id);
+            self.env().emit_event(Approved {
+                id,
+                signer: caller,
+                approvals,
+            });
+            if proposal.eta == 0 && approvals >= self.threshold {
+                proposal.eta = self.env().block_timestamp() + self.min_delay;
+                self.env().emit_event(Queued {
+                    id,
+                    eta: proposal.eta,
+                });
+            }
+            self.proposals.insert(id, &proposal);
+        }
+
+        /// @notice Executes a queued proposal once its delay has passed
+        /// @dev Callable by anyone, the whole execution reverts if any of the calls fails
+        #[ink(message, payable)]
+        pub fn execute(&mut self, id: u32) {
+            let mut proposal = self.proposals.get(id).expect("proposal not exist");
+            assert!(!proposal.canceled && !proposal.executed, "proposal closed");
+            assert!(proposal.eta != 0, "proposal not queued");
+            assert!(self.env().block_timestamp() >= proposal.eta, "proposal is timelocked");
+            // signers removed since the proposal was queued no longer count
+            assert!(self.get_approvals(id) >= self.threshold, "not enough approvals");
+            proposal.executed = true;
+            self.proposals.insert(id, &proposal);
+            for call in proposal.calls.iter() {
+                build_call::<DefaultEnvironment>()
+                    .call_type(
+                        Call::new()
+                            .callee(call.callee)
+                            .gas_limit(call.gas_limit)
+                            .transferred_value(call.transferred_value),
+                    )
+                    .call_flags(CallFlags::default().set_allow_reentry(call.allow_reentry))
+                    .exec_input(
+                        ExecutionInput::new(Selector::new(call.selector)).push_arg(CallInput(&call.input)),
+                    )
+                    .returns::<()>()
+                    .fire()
+                    .expect("proposal call failed");
+            }
+            self.env().emit_event(Executed { id });
+        }
+
+        /// @notice Cancels a proposal that has not been executed
+        /// @dev Only by its proposer while they are a signer, or by the governor itself through a proposal, so a single
+        /// signer can not block the proposals of the others
+        #[ink(message)]
+        pub fn cancel(&mut self, id: u32) {
+            let caller = self.env().caller();
+            let mut proposal = self.proposals.get(id).expect("proposal not exist");
+            assert!(
+                caller == self.env().account_id()
+                    || (caller == proposal.proposer && self.is_signer.get(caller).unwrap_or(false)),
+                "not proposer"
+            );
+            assert!(!proposal.canceled && !proposal.executed, "proposal closed");
+            proposal.canceled = true;
+            self.proposals.insert(id, &proposal);
+            self.env().emit_event(Canceled { id, signer: caller });
+        }
+
+        /// @notice Adds a signer, only through a proposal
+        #[ink(message)]
+        pub fn add_signer(&mut self, signer: AccountId) {
+            self.ensure_from_self();
+            assert!((self.signers.len() as u32) < MAX_SIGNERS, "too many signers");
+            assert!(!self.is_signer.get(signer).unwrap_or(false), "already signer");
+            self.is_signer.insert(signer, &true);
+            self.signers.push(signer);
+            self.env().emit_event(SignerAdded { signer });
+        }
+
+        /// @notice Removes a signer, only through a proposal
+        /// @dev The threshold is lowered if it would exceed the remaining signers. The approvals of the signer are
+        /// dropped, they do not count again if the signer is added back
+        #[ink(message)]
+        pub fn remove_signer(&mut self, signer: AccountId) {
+            self.ensure_from_self();
+            assert!(self.is_signer.get(signer).unwrap_or(false), "not signer");
+            self.is_signer.remove(signer);
+            self.signer_epochs.insert(signer, &(self.signer_epoch(signer) + 1));
+            self.signers.retain(|s| *s != signer);
+            let signers = self.signers.len() as u32;
+            assert!(signers > 0, "last signer");
+            if self.threshold > signers {
+                self.threshold = signers;
+                self.env().emit_event(ThresholdChanged {
+                    threshold: signers,
+                });
+            }
+            self.env().emit_event(SignerRemoved { signer });
+        }
+
+        /// @notice Changes the approvals needed to queue a proposal, only through a proposal
+        #[ink(message)]
+        pub fn change_threshold(&mut self, threshold: u32) {
+            self.ensure_from_self();
+            ensure_requirement(self.signers.len() as u32, threshold);
+            self.threshold = threshold;
+            self.env().emit_event(ThresholdChanged { threshold });
+        }
+
+        /// @notice Changes the delay of proposals queued afterwards, only through a proposal
+        #[ink(message)]
+        pub fn set_min_delay(&mut self, min_delay: u64) {
+            self.ensure_from_self();
+            ensure_delay(min_delay);
+            self.min_delay = min_delay;
+            self.env().emit_event(MinDelayChanged { min_delay });
+        }
+
+        #[ink(message)]
+        pub fn get_signers(&self) -> Vec<AccountId> {
+            self.signers.clone()
+        }
+
+        #[ink(message)]
+        pub fn get_threshold(&self) -> u32 {
+            self.threshold
+        }
+
+        #[ink(message)]
+        pub fn get_min_delay(&self) -> u64 {
+            self.min_delay
+        }
+
+        #[ink(message)]
+        pub fn get_proposal(&self, id: u32) -> Option<Proposal> {
+            self.proposals.get(id)
+        }
+
+        /// @notice The approvals of a proposal by the current signers
+        #[ink(message)]
+        pub fn get_approvals(&self, id: u32) -> u32 {
+            self.signers
+                .iter()
+                .filter(|signer| self.has_approved(id, **signer))
+                .count() as u32
+        }
+
+        /// @notice Whether the signer approved the proposal since they were last added
+        #[ink(message)]
+        pub fn has_approved(&self, id: u32, signer: AccountId) -> bool {
+            self.approved.get((id, signer, self.signer_epoch(signer))).unwrap_or(false)
+        }
+
+        fn signer_epoch(&self, signer: AccountId) -> u32 {
+            self.signer_epochs.get(signer).unwrap_or(0)
+        }
+
+        fn ensure_signer(&self) -> AccountId {
+            let caller = self.env().caller();
+            assert!(self.is_signer.get(caller).unwrap_or(false), "not signer");
+            caller
+        }
+
+        fn ensure_from_self(&self) {
+            assert_eq!(self.env().caller(), self.env().account_id(), "only by proposal");
+        }
+    }
+
+    /// @dev The threshold must be reachable by the signers and at least one
+    fn ensure_requirement(signers: u32, threshold: u32) {
+        assert!(0 < threshold && threshold <= signers, "invalid threshold");
+    }
+    /// @dev A shorter delay would let the signers act before anyone can react
+    fn ensure_delay(min_delay: u64) {
+        assert!(min_delay >= MIN_DELAY, "delay too short");
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use ink_lang as ink;
+
+        fn default_accounts() -> ink_env::test::DefaultAccounts<DefaultEnvironment> {
+            ink_env::test::default_accounts::<DefaultEnvironment>()
+        }
+
+        fn set_next_caller(caller: AccountId) {
+            ink_env::test::set_caller::<DefaultEnvironment>(caller);
+        }
+
+        fn set_timestamp(timestamp: u64) {
+            ink_env::test::set_block_timestamp::<DefaultEnvironment>(timestamp);
+        }
+
+        /// @dev alice, bob and charlie with a threshold of 2, alice proposes a call
+        fn setup() -> (GovernorContract, u32) {
+            let accounts = default_accounts();
+            set_next_caller(accounts.alice);
+            let mut governor = GovernorContract::new(
+                vec![accounts.alice, accounts.bob, accounts.charlie],
+                2,
+                MIN_DELAY,
+            );
+            let id = governor.propose(vec![Transaction {
+                callee: accounts.django,
+                selector: [0; 4],
+                input: vec![],
+                transferred_value: 0,
+                gas_limit: 0,
+                allow_reentry: false,
+            }]);
+            (governor, id)
+        }
+
+        fn as_governor(governor: &mut GovernorContract, f: impl FnOnce(&mut GovernorContract)) {
+            set_next_caller(ink_env::test::callee::<DefaultEnvironment>());
+            f(governor);
+        }
+
+        #[ink::test]
+        fn proposal_is_queued_at_threshold() {
+            let accounts = default_accounts();
+            set_timestamp(1_000);
+            let (mut governor, id) = setup();
+            assert_eq!(governor.get_approvals(id), 1);
+            assert_eq!(governor.get_proposal(id).unwrap().eta, 0);
+
+            set_next_caller(accounts.bob);
+            governor.approve(id);
+            assert_eq!(governor.get_approvals(id), 2);
+            assert_eq!(governor.get_proposal(id).unwrap().eta, 1_000 + MIN_DELAY);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "already approved")]
+        fn approve_twice_fails() {
+            let (mut governor, id) = setup();
+            governor.approve(id);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "not signer")]
+        fn approve_by_non_signer_fails() {
+            let accounts = default_accounts();
+            let (mut governor, id) = setup();
+            set_next_caller(accounts.eve);
+            governor.approve(id);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "proposal not queued")]
+        fn execute_below_threshold_fails() {
+            let (mut governor, id) = setup();
+            set_timestamp(MIN_DELAY * 2);
+            governor.execute(id);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "proposal is timelocked")]
+        fn execute_before_eta_fails() {
+            let accounts = default_accounts();
+            set_timestamp(1_000);
+            let (mut governor, id) = setup();
+            set_next_caller(accounts.bob);
+            governor.approve(id);
+            set_timestamp(1_000 + MIN_DELAY - 1);
+            governor.execute(id);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "not enough approvals")]
+        fn removed_signer_approval_does_not_count() {
+            let accounts = default_accounts();
+            set_timestamp(1_000);
+            let (mut governor, id) = setup();
+            set_next_caller(accounts.bob);
+            governor.approve(id);
+            as_governor(&mut governor, |governor| governor.remove_signer(accounts.bob));
+            assert_eq!(governor.get_approvals(id), 1);
+            assert_eq!(governor.get_threshold(), 2);
+            set_timestamp(1_000 + MIN_DELAY);
+            governor.execute(id);
+        }
+
+        #[ink::test]
+        fn removed_signer_approval_does_not_queue() {
+            let accounts = default_accounts();
+            let (mut governor, id) = setup();
+            as_governor(&mut governor, |governor| governor.remove_signer(accounts.alice));
+            set_next_caller(accounts.bob);
+            governor.approve(id);
+            assert_eq!(governor.get_approvals(id), 1);
+            assert_eq!(governor.get_proposal(id).unwrap().eta, 0);
+
+            set_next_caller(accounts.charlie);
+            governor.approve(id);
+            assert_eq!(governor.get_approvals(id), 2);
+            assert!(governor.get_proposal(id).unwrap().eta != 0);
+        }
+
+        #[ink::test]
+        fn readded_signer_approval_does_not_count() {
+            let accounts = default_accounts();
+            let (mut governor, id) = setup();
+            set_next_caller(accounts.bob);
+            governor.approve(id);
+            assert_eq!(governor.get_approvals(id), 2);
+            as_governor(&mut governor, |governor| {
+                governor.remove_signer(accounts.bob);
+                governor.add_signer(accounts.bob);
+            });
+            assert!(!governor.has_approved(id, accounts.bob));
+            assert_eq!(governor.get_approvals(id), 1);
+
+            // the signer can approve again
+            set_next_caller(accounts.bob);
+            governor.approve(id);
+            assert_eq!(governor.get_approvals(id), 2);
+        }
+
+        #[ink::test]
+        fn proposer_cancels() {
+            let accounts = default_accounts();
+            let (mut governor, id) = setup();
+            set_next_caller(accounts.alice);
+            governor.cancel(id);
+            assert!(governor.get_proposal(id).unwrap().canceled);
+        }
+
+        #[ink::test]
+        fn governor_cancels_through_a_proposal() {
+            let (mut governor, id) = setup();
+            as_governor(&mut governor, |governor| governor.cancel(id));
+            assert!(governor.get_proposal(id).unwrap().canceled);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "not proposer")]
+        fn cancel_by_other_signer_fails() {
+            let accounts = default_accounts();
+            let (mut governor, id) = setup();
+            set_next_caller(accounts.bob);
+            governor.cancel(id);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "not proposer")]
+        fn cancel_by_removed_proposer_fails() {
+            let accounts = default_accounts();
+            let (mut governor, id) = setup();
+            as_governor(&mut governor, |governor| governor.remove_signer(accounts.alice));
+            set_next_caller(accounts.alice);
+            governor.cancel(id);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "proposal closed")]
+        fn approve_canceled_proposal_fails() {
+            let accounts = default_accounts();
+            let (mut governor, id) = setup();
+            governor.cancel(id);
+            set_next_caller(accounts.bob);
+            governor.approve(id);
+        }
+
+        #[ink::test]
+        fn remove_signer_lowers_threshold() {
+            let accounts = default_accounts();
+            let (mut governor, _) = setup();
+            as_governor(&mut governor, |governor| {
+                governor.change_threshold(3);
+                governor.remove_signer(accounts.charlie);
+            });
+            assert_eq!(governor.get_threshold(), 2);
+            assert_eq!(governor.get_signers(), vec![accounts.alice, accounts.bob]);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "only by proposal")]
+        fn remove_signer_by_signer_fails() {
+            let accounts = default_accounts();
+            let (mut governor, _) = setup();
+            governor.remove_signer(accounts.bob);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "delay too short")]
+        fn constructor_rejects_short_delay() {
+            let accounts = default_accounts();
+            GovernorContract::new(vec![accounts.alice], 1, 0);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "delay too short")]
+        fn set_min_delay_rejects_short_delay() {
+            let (mut governor, _) = setup();
+            as_governor(&mut governor, |governor| governor.set_min_delay(MIN_DELAY - 1));
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "invalid threshold")]
+        fn constructor_rejects_unreachable_threshold() {
+            let accounts = default_accounts();
+            GovernorContract::new(vec![accounts.alice, accounts.bob], 3, MIN_DELAY);
+        }
+    }
+}
@@ -0,0 +1,210 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#![feature(min_specialization)]
+
+/// @title Protocol fee treasury
+/// @notice Collects protocol fees from batches of the factory's pools and keeps per-token totals of what was
+/// collected and what was paid out. Payouts only go to recipients approved by the owner, e.g. a buy-and-burn or a
+/// staking contract. The treasury must be set as fee collector of the factory.
+#[openbrush::contract]
+pub mod treasury {
+    use crabswap::traits::core::factory::FactoryRef;
+    use crabswap::traits::core::pool_owner_action::PoolOwnerActionsRef;
+    use crabswap::traits::core::pool_state::PoolStateRef;
+    use ink_env::CallFlags;
+    use ink_lang::codegen::EmitEvent;
+    use ink_lang::codegen::Env;
+    use ink_prelude::vec;
+    use ink_prelude::vec::Vec;
+    use ink_storage::traits::SpreadAllocate;
+    use ink_storage::Mapping;
+    use openbrush::contracts::ownable::*;
+    use openbrush::contracts::psp22::*;
+    use openbrush::modifiers;
+    use primitives::Address;
+
+    #[ink(storage)]
+    #[derive(Default, SpreadAllocate, OwnableStorage)]
+    pub struct TreasuryContract {
+        #[OwnableStorageField]
+        ownable: OwnableData,
+        factory: Address,
+        // all-time protocol fees collected, by token
+        accrued: Mapping<Address, u128>,
+        // all-time amounts paid out, by token
+        distributed: Mapping<Address, u128>,
+        approved_recipients: Mapping<Address, bool>,
+    }
+
+    /// @notice Emitted for every pool the protocol fees were collected from
+    #[ink(event)]
+    pub struct Collected {
+        #[ink(topic)]
+        pool: Address,
+        token0: Address,
+        token1: Address,
+        amount0: u128,
+        amount1: u128,
+    }
+
+    /// @notice Emitted when the owner approves or revokes a distribution recipient
+    #[ink(event)]
+    pub struct RecipientApproved {
+        #[ink(topic)]
+        recipient: Address,
+        approved: bool,
+    }
+
+    /// @notice Emitted when collected fees are paid out
+    #[ink(event)]
+    pub struct Distributed {
+        #[ink(topic)]
+        token: Address,
+        #[ink(topic)]
+        recipient: Address,
+        amount: u128,
+    }
+
+    impl Ownable for TreasuryContract {}
+
+    impl PSP22Receiver for TreasuryContract {
+        #[ink(message)]
+        fn before_received(
+            &mut self,
+            _operator: AccountId,
+            _from: AccountId,
+            _value: Balance,
+            _data: Vec<u8>,
+        ) -> Result<(), PSP22ReceiverError> {
+            Ok(())
+        }
+    }
+
+    impl TreasuryContract {
+        #[ink(constructor)]
+        pub fn new(factory: Address) -> Self {
+            ink_lang::utils::initialize_contract(|instance: &mut Self| {
+                let caller = instance.env().caller();
+                instance._init_with_owner(caller);
+                instance.factory = factory;
+            })
+        }
+
+        /// @notice Collects all protocol fees of the given pools into the treasury
+        /// @dev Every pool must have been created by the factory, a fake one could report any amounts
+        /// @return amounts The token0 and token1 amounts collected from each pool
+        #[ink(message)]
+        pub fn collect(&mut self, pools: Vec<Address>) -> Vec<(u128, u128)> {
+            let mut amounts = vec![];
+            for pool in pools {
+                amounts.push(self._collect(pool));
+            }
+            amounts
+        }
+
+        /// @notice Collects the protocol fees of the factory's pools with index in [start, start + limit)
+        /// @return amounts The token0 and token1 amounts collected from each pool
+        #[ink(message)]
+        pub fn collect_range(&mut self, start: u32, limit: u32) -> Vec<(u128, u128)> {
+            let length = FactoryRef::get_all_pools_length(&self.factory);
+            let end = if start.saturating_add(limit) > length {
+                length
+            } else {
+                start + limit
+            };
+            let mut amounts = vec![];
+            for index in start..end {
+                let pool = FactoryRef::get_all_pools(&self.factory, index);
+                amounts.push(self._collect(pool));
+            }
+            amounts
+        }
+
+        /// @notice Approves or revokes a recipient of distributions
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn set_recipient(&mut self, recipient: Address, approved: bool) -> Result<(), OwnableError> {
+            if approved {
+                self.approved_recipients.insert(recipient, &true);
+            } else {
+                self.approved_recipients.remove(recipient);
+            }
+            self.env().emit_event(RecipientApproved { recipient, approved });
+            Ok(())
+        }
+
+        /// @notice Pays out collected fees to an approved recipient
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn distribute(
+            &mut self,
+            token: Address,
+            recipient: Address,
+            amount: u128,
+        ) -> Result<(), OwnableError> {
+            assert!(self.is_approved_recipient(recipient), "recipient not approved");
+            PSP22Ref::transfer(&token, recipient, amount, vec![0u8])
+                .expect("token transfer error!");
+            let distributed = self.distributed.get(token).unwrap_or(0);
+            self.distributed.insert(token, &(distributed + amount));
+            self.env().emit_event(Distributed {
+                token,
+                recipient,
+                amount,
+            });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn get_factory(&self) -> Address {
+            self.factory
+        }
+
+        #[ink(message)]
+        pub fn get_accrued(&self, token: Address) -> u128 {
+            self.accrued.get(token).unwrap_or(0)
+        }
+
+        #[ink(message)]
+        pub fn get_distributed(&self, token: Address) -> u128 {
+            self.distributed.get(token).unwrap_or(0)
+        }
+
+        #[ink(message)]
+        pub fn is_approved_recipient(&self, recipient: Address) -> bool {
+            self.approved_recipients.get(recipient).unwrap_or(false)
+        }
+
+        /// @dev Every pool must have been created by the factory, a fake one could report any amounts
+        fn _collect(&mut self, pool: Address) -> (u128, u128) {
+            let token0 = PoolStateRef::token0(&pool);
+            let token1 = PoolStateRef::token1(&pool);
+            let fee = PoolStateRef::feeTier(&pool);
+            assert!(
+                FactoryRef::get_pool(&self.factory, fee, token0, token1) == pool,
+                "not a factory pool"
+            );
+            let address_this = self.env().account_id();
+            let (amount0, amount1) = PoolOwnerActionsRef::collectProtocol_builder(
+                &pool,
+                address_this,
+                u128::MAX,
+                u128::MAX,
+            )
+            .call_flags(CallFlags::default().set_allow_reentry(true))
+            .fire()
+            .unwrap();
+            let accrued0 = self.accrued.get(token0).unwrap_or(0);
+            self.accrued.insert(token0, &(accrued0 + amount0));
+            let accrued1 = self.accrued.get(token1).unwrap_or(0);
+            self.accrued.insert(token1, &(accrued1 + amount1));
+            self.env().emit_event(Collected {
+                pool,
+                token0,
+                token1,
+                amount0,
+                amount1,
+            });
+            (amount0, amount1)
+        }
+    }
+}
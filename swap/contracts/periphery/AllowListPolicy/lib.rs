@@ -0,0 +1,150 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#![feature(min_specialization)]
+#![allow(non_snake_case)]
+
+/// @title Allow-list access policy for permissioned pools
+/// @notice Accounts holding LIQUIDITY_PROVIDER may receive minted liquidity, and a swap goes through when both its
+/// sender and its recipient hold TRADER. Both roles are managed by LIST_ADMIN, which is managed by the default admin.
+/// Position managers mint to themselves for the owners of their positions. The default admin grants MANAGER to the
+/// managers trusted to check those owners against this policy, like the NonfungiblePositionManager does.
+#[openbrush::contract]
+pub mod allow_list_policy {
+    use crabswap::traits::core::access_policy::*;
+    use ink_lang::codegen::EmitEvent;
+    use ink_lang::codegen::Env;
+    use ink_prelude::vec::Vec;
+    use ink_storage::traits::SpreadAllocate;
+    use openbrush::contracts::access_control::*;
+    use openbrush::modifiers;
+    use primitives::Address;
+
+    pub const LIST_ADMIN: RoleType = ink_lang::selector_id!("LIST_ADMIN");
+    pub const LIQUIDITY_PROVIDER: RoleType = ink_lang::selector_id!("LIQUIDITY_PROVIDER");
+    pub const TRADER: RoleType = ink_lang::selector_id!("TRADER");
+    pub const MANAGER: RoleType = ink_lang::selector_id!("MANAGER");
+
+    #[ink(storage)]
+    #[derive(Default, SpreadAllocate, AccessControlStorage)]
+    pub struct AllowListPolicyContract {
+        #[AccessControlStorageField]
+        access: AccessControlData,
+    }
+
+    /// @notice Emitted when an account is granted a role
+    #[ink(event)]
+    pub struct RoleGranted {
+        #[ink(topic)]
+        role: RoleType,
+        #[ink(topic)]
+        grantee: AccountId,
+        #[ink(topic)]
+        grantor: Option<AccountId>,
+    }
+
+    /// @notice Emitted when a role is revoked from or renounced by an account
+    #[ink(event)]
+    pub struct RoleRevoked {
+        #[ink(topic)]
+        role: RoleType,
+        #[ink(topic)]
+        account: AccountId,
+        #[ink(topic)]
+        sender: AccountId,
+    }
+
+    /// @notice Emitted when the admin role of a role is changed
+    #[ink(event)]
+    pub struct RoleAdminChanged {
+        #[ink(topic)]
+        role: RoleType,
+        previous_admin_role: RoleType,
+        new_admin_role: RoleType,
+    }
+
+    impl AccessControl for AllowListPolicyContract {}
+
+    impl AccessControlInternal for AllowListPolicyContract {
+        fn _emit_role_admin_changed(
+            &mut self,
+            role: RoleType,
+            previous_admin_role: RoleType,
+            new_admin_role: RoleType,
+        ) {
+            self.env().emit_event(RoleAdminChanged {
+                role,
+                previous_admin_role,
+                new_admin_role,
+            });
+        }
+
+        fn _emit_role_granted(&mut self, role: RoleType, grantee: AccountId, grantor: Option<AccountId>) {
+            self.env().emit_event(RoleGranted {
+                role,
+                grantee,
+                grantor,
+            });
+        }
+
+        fn _emit_role_revoked(&mut self, role: RoleType, account: AccountId, sender: AccountId) {
+            self.env().emit_event(RoleRevoked {
+                role,
+                account,
+                sender,
+            });
+        }
+    }
+
+    impl AccessPolicy for AllowListPolicyContract {
+        #[ink(message)]
+        fn canProvideLiquidity(&self, account: Address) -> bool {
+            self.has_role(LIQUIDITY_PROVIDER, account) || self.has_role(MANAGER, account)
+        }
+
+        #[ink(message)]
+        fn canTrade(&self, sender: Address, recipient: Address) -> bool {
+            self.has_role(TRADER, sender) && self.has_role(TRADER, recipient)
+        }
+    }
+
+    impl AllowListPolicyContract {
+        /// @notice The deployer becomes default admin and list admin
+        #[ink(constructor)]
+        pub fn new() -> Self {
+            ink_lang::utils::initialize_contract(|instance: &mut Self| {
+                let caller = instance.env().caller();
+                instance._init_with_admin(caller);
+                instance._set_role_admin(LIST_ADMIN, DEFAULT_ADMIN_ROLE);
+                instance._set_role_admin(LIQUIDITY_PROVIDER, LIST_ADMIN);
+                instance._set_role_admin(TRADER, LIST_ADMIN);
+                instance._set_role_admin(MANAGER, DEFAULT_ADMIN_ROLE);
+                instance._setup_role(LIST_ADMIN, caller);
+            })
+        }
+
+        /// @notice Grants LIQUIDITY_PROVIDER or TRADER to a batch of verified accounts
+        #[ink(message)]
+        #[modifiers(only_role(LIST_ADMIN))]
+        pub fn grant_many(&mut self, role: RoleType, accounts: Vec<AccountId>) -> Result<(), AccessControlError> {
+            assert!(role == LIQUIDITY_PROVIDER || role == TRADER, "not a list role");
+            for account in accounts {
+                if !self.has_role(role, account) {
+                    self._setup_role(role, account);
+                }
+            }
+            Ok(())
+        }
+
+        /// @notice Revokes LIQUIDITY_PROVIDER or TRADER from a batch of accounts
+        #[ink(message)]
+        #[modifiers(only_role(LIST_ADMIN))]
+        pub fn revoke_many(&mut self, role: RoleType, accounts: Vec<AccountId>) -> Result<(), AccessControlError> {
+            assert!(role == LIQUIDITY_PROVIDER || role == TRADER, "not a list role");
+            for account in accounts {
+                if self.has_role(role, account) {
+                    self.revoke_role(role, account)?;
+                }
+            }
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,467 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#![feature(min_specialization)]
+#![allow(non_snake_case)]
+
+/// @title Auto-compounding vault for position NFTs
+/// @notice Position NFTs sent here are held by the vault, and the sender gets shares equal to the position's liquidity.
+/// Anyone can then join a vaulted position with deposit, getting shares in proportion to the value added: deposit first
+/// compounds the position, then prices the deposit against the position and its leftover tokens at the pool price. Anyone
+/// can call compound, which collects the position's fees, swaps the excess side through the pool to the ratio of the
+/// range and adds the result as liquidity, so every share is worth more liquidity afterwards. The caller of compound
+/// keeps a small bounty of the fees.
+/// @dev The compounding swap trades at the pool price without a slippage bound, it is only meant for fee amounts
+#[openbrush::contract]
+pub mod compounding_vault {
+    use crabswap::traits::core::factory::FactoryRef;
+    use crabswap::traits::core::pool_action::PoolActionRef;
+    use crabswap::traits::periphery::position_manager::PositionManagerRef;
+    use crabswap::traits::periphery::swap_callback::{swapcallback_external, SwapCallback};
+    use ink_env::CallFlags;
+    use ink_lang::codegen::EmitEvent;
+    use ink_lang::codegen::Env;
+    use ink_prelude::vec;
+    use ink_prelude::vec::Vec;
+    use ink_storage::traits::{PackedLayout, SpreadAllocate, SpreadLayout};
+    use ink_storage::Mapping;
+    use libs::core::TickMath;
+    use libs::periphery::LiquidityAmounts;
+    use libs::swap::FullMath;
+    use openbrush::contracts::psp22::*;
+    use openbrush::contracts::psp34::*;
+    use primitives::{Address, Int24, Int256, Uint24, ADDRESS0, U160, U256};
+    use scale::{Decode, Encode};
+
+    // the largest compounding bounty, 2% of the collected fees
+    pub const MAX_BOUNTY_BPS: u16 = 200;
+
+    /// @notice A position NFT held by the vault
+    #[derive(Debug, Default, Clone, PartialEq, Eq, Encode, Decode, SpreadLayout, PackedLayout)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout))]
+    pub struct VaultPosition {
+        pub pool: Address,
+        pub token0: Address,
+        pub token1: Address,
+        pub fee: Uint24,
+        pub tick_lower: Int24,
+        pub tick_upper: Int24,
+        pub total_shares: u128,
+        // the tokens left over by the last compounding, added with the next one
+        pub leftover0: u128,
+        pub leftover1: u128,
+    }
+
+    #[ink(storage)]
+    #[derive(SpreadAllocate)]
+    pub struct CompoundingVaultContract {
+        factory: Address,
+        nonfungible_position_manager: Address,
+        // the share of the collected fees paid to the caller of compound, in basis points
+        bounty_bps: u16,
+        // by position token id
+        positions: Mapping<Id, VaultPosition>,
+        // by (position token id, holder)
+        shares: Mapping<(Id, Address), u128>,
+    }
+
+    /// @notice Emitted when shares of a position are issued
+    #[ink(event)]
+    pub struct Deposited {
+        #[ink(topic)]
+        token_id: Id,
+        #[ink(topic)]
+        owner: Address,
+        liquidity: u128,
+        shares: u128,
+    }
+
+    /// @notice Emitted when shares of a position are redeemed
+    #[ink(event)]
+    pub struct Withdrawn {
+        #[ink(topic)]
+        token_id: Id,
+        #[ink(topic)]
+        owner: Address,
+        recipient: Address,
+        shares: u128,
+        amount0: u128,
+        amount1: u128,
+    }
+
+    /// @notice Emitted when the fees of a position are added to its liquidity
+    /// @param liquidity The liquidity added to the position
+    /// @param bounty0 The token0 paid to the caller
+    /// @param bounty1 The token1 paid to the caller
+    #[ink(event)]
+    pub struct Compounded {
+        #[ink(topic)]
+        token_id: Id,
+        #[ink(topic)]
+        caller: Address,
+        liquidity: u128,
+        bounty0: u128,
+        bounty1: u128,
+    }
+
+    impl PSP22Receiver for CompoundingVaultContract {
+        #[ink(message)]
+        fn before_received(
+            &mut self,
+            _operator: AccountId,
+            _from: AccountId,
+            _value: Balance,
+            _data: Vec<u8>,
+        ) -> Result<(), PSP22ReceiverError> {
+            Ok(())
+        }
+    }
+
+    impl PSP34Receiver for CompoundingVaultContract {
+        /// @notice Puts a position NFT sent by the position manager into the vault, issuing shares to the sender
+        /// @dev Positions minted straight to the vault have no sender to issue the shares to and are rejected
+        #[ink(message)]
+        fn before_received(
+            &mut self,
+            _operator: AccountId,
+            from: AccountId,
+            id: Id,
+            _data: Vec<u8>,
+        ) -> Result<(), PSP34ReceiverError> {
+            assert!(
+                self.env().caller() == self.nonfungible_position_manager,
+                "not a position nft"
+            );
+            assert!(from != ADDRESS0.into(), "mint the position to yourself, then transfer it");
+            let (_, _, token0, token1, fee, tick_lower, tick_upper, liquidity, _, _, _, _) =
+                PositionManagerRef::positions(&self.nonfungible_position_manager, id.clone());
+            assert!(liquidity > 0, "position has no liquidity");
+            let pool = FactoryRef::get_pool(&self.factory, fee, token0, token1);
+            self.positions.insert(
+                &id,
+                &VaultPosition {
+                    pool,
+                    token0,
+                    token1,
+                    fee,
+                    tick_lower,
+                    tick_upper,
+                    total_shares: liquidity,
+                    leftover0: 0,
+                    leftover1: 0,
+                },
+            );
+            self.shares.insert((&id, from), &liquidity);
+            self.env().emit_event(Deposited {
+                token_id: id,
+                owner: from,
+                liquidity,
+                shares: liquidity,
+            });
+            Ok(())
+        }
+    }
+
+    impl SwapCallback for CompoundingVaultContract {
+        /// @notice Pays the pool for the swap made by compound
+        #[ink(message)]
+        fn swapCallback(&mut self, amount0Delta: Int256, amount1Delta: Int256, data: Vec<u8>) {
+            let token_id: Id = Decode::decode(&mut data.as_ref()).expect("call back data parse error!");
+            let position = self.positions.get(&token_id).expect("position not in vault");
+            assert!(self.env().caller() == position.pool, "call back is not pool");
+            if amount0Delta > 0 {
+                PSP22Ref::transfer(&position.token0, position.pool, amount0Delta as u128, vec![0u8])
+                    .expect("token0 transfer error!");
+            }
+            if amount1Delta > 0 {
+                PSP22Ref::transfer(&position.token1, position.pool, amount1Delta as u128, vec![0u8])
+                    .expect("token1 transfer error!");
+            }
+        }
+    }
+
+    impl CompoundingVaultContract {
+        /// @param bounty_bps The share of the collected fees paid to the caller of compound, at most MAX_BOUNTY_BPS
+        #[ink(constructor)]
+        pub fn new(factory: Address, nonfungible_position_manager: Address, bounty_bps: u16) -> Self {
+            ink_lang::utils::initialize_contract(|instance: &mut Self| {
+                assert!(bounty_bps <= MAX_BOUNTY_BPS, "bounty too large");
+                instance.factory = factory;
+                instance.nonfungible_position_manager = nonfungible_position_manager;
+                instance.bounty_bps = bounty_bps;
+            })
+        }
+
+        /// @notice Adds liquidity to a vaulted position and issues shares for it
+        /// @dev The caller must have approved the vault to spend the amounts, what is not used is returned. The position
+        /// is compounded first, so its uncollected fees belong to the existing shares, and the caller gets the bounty
+        /// @return shares The shares issued to the caller
+        #[ink(message)]
+        pub fn deposit(&mut self, token_id: Id, amount0: u128, amount1: u128, deadline: u64) -> u128 {
+            assert!(self.positions.get(&token_id).is_some(), "position not in vault");
+            let caller = self.env().caller();
+            let address_this = self.env().account_id();
+            self._compound(token_id.clone(), caller, deadline);
+            let mut position = self.positions.get(&token_id).expect("position not in vault");
+
+            // the position and its leftover tokens, valued in token1 at the pool price
+            let sqrtPriceX96 = PoolActionRef::getSlot0(&position.pool).sqrtPriceX96.value;
+            let (held0, held1) = LiquidityAmounts::getAmountsForLiquidity(
+                sqrtPriceX96,
+                TickMath::getSqrtRatioAtTick(position.tick_lower),
+                TickMath::getSqrtRatioAtTick(position.tick_upper),
+                self._liquidity(token_id.clone()),
+            );
+            let value_before = LiquidityAmounts::valueInToken1(sqrtPriceX96, held0 + position.leftover0)
+                + held1
+                + position.leftover1;
+            assert!(!value_before.is_zero(), "position has no value");
+
+            if amount0 > 0 {
+                PSP22Ref::transfer_from(&position.token0, caller, address_this, amount0, vec![0u8])
+                    .expect("token0 transfer error!");
+            }
+            if amount1 > 0 {
+                PSP22Ref::transfer_from(&position.token1, caller, address_this, amount1, vec![0u8])
+                    .expect("token1 transfer error!");
+            }
+            let (liquidity, used0, used1) = self._increase_liquidity(&position, token_id.clone(), amount0, amount1, deadline);
+            self._send(position.token0, caller, amount0 - used0);
+            self._send(position.token1, caller, amount1 - used1);
+
+            // shares keep their proportion to the value of the position, adding liquidity does not move the price
+            let value = LiquidityAmounts::valueInToken1(sqrtPriceX96, U256::from(used0)) + used1;
+            let shares = FullMath::mulDiv(value, U256::from(position.total_shares), value_before).as_u128();
+            assert!(shares > 0, "deposit too small");
+            position.total_shares += shares;
+            self.positions.insert(&token_id, &position);
+            let held = self.shares.get((&token_id, caller)).unwrap_or(0);
+            self.shares.insert((&token_id, caller), &(held + shares));
+            self.env().emit_event(Deposited {
+                token_id,
+                owner: caller,
+                liquidity,
+                shares,
+            });
+            shares
+        }
+
+        /// @notice Redeems shares of a position for their part of its liquidity and leftover tokens
+        /// @dev Redeeming the last shares also sends the emptied position NFT to the recipient
+        /// @return amount0 The token0 sent to the recipient
+        /// @return amount1 The token1 sent to the recipient
+        #[ink(message)]
+        pub fn withdraw(&mut self, token_id: Id, shares: u128, recipient: Address, deadline: u64) -> (u128, u128) {
+            let mut position = self.positions.get(&token_id).expect("position not in vault");
+            let caller = self.env().caller();
+            let held = self.shares.get((&token_id, caller)).unwrap_or(0);
+            assert!(shares > 0 && shares <= held, "insufficient shares");
+
+            let total_shares = U256::from(position.total_shares);
+            let liquidity =
+                FullMath::mulDiv(U256::from(self._liquidity(token_id.clone())), U256::from(shares), total_shares).as_u128();
+            let (mut amount0, mut amount1) = (0, 0);
+            if liquidity > 0 {
+                let (owed0, owed1) = PositionManagerRef::decreaseLiquidity(
+                    &self.nonfungible_position_manager,
+                    token_id.clone(),
+                    liquidity,
+                    U256::zero(),
+                    U256::zero(),
+                    deadline,
+                );
+                // only the withdrawn principal is collected, the fees stay for compounding
+                let (collected0, collected1) = PositionManagerRef::collect(
+                    &self.nonfungible_position_manager,
+                    token_id.clone(),
+                    recipient,
+                    owed0.as_u128(),
+                    owed1.as_u128(),
+                );
+                amount0 += collected0.as_u128();
+                amount1 += collected1.as_u128();
+            }
+            let leftover0 = FullMath::mulDiv(U256::from(position.leftover0), U256::from(shares), total_shares).as_u128();
+            let leftover1 = FullMath::mulDiv(U256::from(position.leftover1), U256::from(shares), total_shares).as_u128();
+            position.leftover0 -= leftover0;
+            position.leftover1 -= leftover1;
+            self._send(position.token0, recipient, leftover0);
+            self._send(position.token1, recipient, leftover1);
+            amount0 += leftover0;
+            amount1 += leftover1;
+
+            position.total_shares -= shares;
+            self.shares.insert((&token_id, caller), &(held - shares));
+            if position.total_shares == 0 {
+                let (collected0, collected1) = PositionManagerRef::collect(
+                    &self.nonfungible_position_manager,
+                    token_id.clone(),
+                    recipient,
+                    u128::MAX,
+                    u128::MAX,
+                );
+                amount0 += collected0.as_u128();
+                amount1 += collected1.as_u128();
+                self.positions.remove(&token_id);
+                PSP34Ref::transfer(&self.nonfungible_position_manager, recipient, token_id.clone(), vec![])
+                    .expect("nft transfer error!");
+            } else {
+                self.positions.insert(&token_id, &position);
+            }
+            self.env().emit_event(Withdrawn {
+                token_id,
+                owner: caller,
+                recipient,
+                shares,
+                amount0,
+                amount1,
+            });
+            (amount0, amount1)
+        }
+
+        /// @notice Collects the fees of a vaulted position, swaps them to the ratio of its range and adds them as liquidity
+        /// @dev Callable by anyone, the caller gets bounty_bps of the collected fees
+        /// @return liquidity The liquidity added to the position
+        #[ink(message)]
+        pub fn compound(&mut self, token_id: Id, deadline: u64) -> u128 {
+            assert!(self.positions.get(&token_id).is_some(), "position not in vault");
+            let caller = self.env().caller();
+            self._compound(token_id, caller, deadline)
+        }
+
+        #[ink(message)]
+        pub fn get_position(&self, token_id: Id) -> Option<VaultPosition> {
+            self.positions.get(&token_id)
+        }
+
+        #[ink(message)]
+        pub fn get_shares(&self, token_id: Id, owner: Address) -> u128 {
+            self.shares.get((&token_id, owner)).unwrap_or(0)
+        }
+
+        #[ink(message)]
+        pub fn get_bounty_bps(&self) -> u16 {
+            self.bounty_bps
+        }
+
+        /// @dev Compounds a vaulted position, paying the bounty to caller
+        fn _compound(&mut self, token_id: Id, caller: Address, deadline: u64) -> u128 {
+            let mut position = self.positions.get(&token_id).expect("position not in vault");
+            let address_this = self.env().account_id();
+
+            // the fees are delivered to this contract, which reenters it through PSP22Receiver
+            let (fees0, fees1) = PositionManagerRef::collect_builder(
+                &self.nonfungible_position_manager,
+                token_id.clone(),
+                address_this,
+                u128::MAX,
+                u128::MAX,
+            )
+            .call_flags(CallFlags::default().set_allow_reentry(true))
+            .fire()
+            .unwrap();
+            let bounty0 = fees0.as_u128() * self.bounty_bps as u128 / 10000;
+            let bounty1 = fees1.as_u128() * self.bounty_bps as u128 / 10000;
+            let mut amount0 = fees0.as_u128() - bounty0 + position.leftover0;
+            let mut amount1 = fees1.as_u128() - bounty1 + position.leftover1;
+
+            let sqrtRatioAX96 = TickMath::getSqrtRatioAtTick(position.tick_lower);
+            let sqrtRatioBX96 = TickMath::getSqrtRatioAtTick(position.tick_upper);
+            let sqrtPriceX96 = PoolActionRef::getSlot0(&position.pool).sqrtPriceX96.value;
+            let (zeroForOne, amountIn) = LiquidityAmounts::getSwapAmountForRatio(
+                sqrtPriceX96,
+                sqrtRatioAX96,
+                sqrtRatioBX96,
+                U256::from(amount0),
+                U256::from(amount1),
+            );
+            if !amountIn.is_zero() {
+                // the pool calls back swapCallback to be paid
+                let (amount0Delta, amount1Delta) = PoolActionRef::swap_builder(
+                    &position.pool,
+                    address_this,
+                    zeroForOne,
+                    amountIn.as_u128() as Int256,
+                    if zeroForOne {
+                        U160::from_dec_str(TickMath::MIN_SQRT_RATIO).unwrap() + 1
+                    } else {
+                        U160::from_dec_str(TickMath::MAX_SQRT_RATIO).unwrap() - 1
+                    },
+                    scale::Encode::encode(&token_id),
+                )
+                .call_flags(CallFlags::default().set_allow_reentry(true))
+                .fire()
+                .unwrap();
+                amount0 = (amount0 as Int256 - amount0Delta) as u128;
+                amount1 = (amount1 as Int256 - amount1Delta) as u128;
+            }
+
+            let mut liquidity = 0;
+            let sqrtPriceX96 = PoolActionRef::getSlot0(&position.pool).sqrtPriceX96.value;
+            if LiquidityAmounts::getLiquidityForAmounts(
+                sqrtPriceX96,
+                sqrtRatioAX96,
+                sqrtRatioBX96,
+                U256::from(amount0),
+                U256::from(amount1),
+            ) > 0
+            {
+                let (added, used0, used1) =
+                    self._increase_liquidity(&position, token_id.clone(), amount0, amount1, deadline);
+                liquidity = added;
+                amount0 -= used0;
+                amount1 -= used1;
+            }
+            position.leftover0 = amount0;
+            position.leftover1 = amount1;
+            self.positions.insert(&token_id, &position);
+
+            self._send(position.token0, caller, bounty0);
+            self._send(position.token1, caller, bounty1);
+            self.env().emit_event(Compounded {
+                token_id,
+                caller,
+                liquidity,
+                bounty0,
+                bounty1,
+            });
+            liquidity
+        }
+
+        /// @dev Adds liquidity to the position from this contract's tokens
+        /// @return liquidity, amount0, amount1 The liquidity added and the tokens used for it
+        fn _increase_liquidity(
+            &mut self,
+            position: &VaultPosition,
+            token_id: Id,
+            amount0: u128,
+            amount1: u128,
+            deadline: u64,
+        ) -> (u128, u128, u128) {
+            PSP22Ref::approve(&position.token0, self.nonfungible_position_manager, amount0)
+                .expect("token0 approve error!");
+            PSP22Ref::approve(&position.token1, self.nonfungible_position_manager, amount1)
+                .expect("token1 approve error!");
+            let (liquidity, used0, used1) = PositionManagerRef::increaseLiquidity(
+                &self.nonfungible_position_manager,
+                token_id,
+                U256::from(amount0),
+                U256::from(amount1),
+                U256::zero(),
+                U256::zero(),
+                deadline,
+            );
+            (liquidity, used0.as_u128(), used1.as_u128())
+        }
+
+        fn _liquidity(&self, token_id: Id) -> u128 {
+            let (_, _, _, _, _, _, _, liquidity, _, _, _, _) =
+                PositionManagerRef::positions(&self.nonfungible_position_manager, token_id);
+            liquidity
+        }
+
+        fn _send(&self, token: Address, to: Address, amount: u128) {
+            if amount > 0 {
+                PSP22Ref::transfer(&token, to, amount, vec![0u8]).expect("token transfer error!");
+            }
+        }
+    }
+}
@@ -0,0 +1,208 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#![feature(min_specialization)]
+
+/// @title Time-locked liquidity positions
+/// @notice Position NFTs sent here stay in custody until their unlock time, which proves the liquidity cannot be pulled
+/// before then. The owner of a lock keeps the fees, which can be collected during the lock, and can extend the lock
+/// but not shorten it. After the unlock time the owner can withdraw the NFT.
+#[openbrush::contract]
+pub mod liquidity_locker {
+    use crabswap::traits::core::factory::FactoryRef;
+    use crabswap::traits::periphery::position_manager::PositionManagerRef;
+    use ink_lang::codegen::EmitEvent;
+    use ink_lang::codegen::Env;
+    use ink_prelude::vec::Vec;
+    use ink_storage::traits::{PackedLayout, SpreadAllocate, SpreadLayout};
+    use ink_storage::Mapping;
+    use openbrush::contracts::psp34::*;
+    use primitives::{Address, ADDRESS0, U256};
+    use scale::{Decode, Encode};
+
+    #[derive(Debug, Default, Clone, PartialEq, Eq, Encode, Decode, SpreadLayout, PackedLayout)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout))]
+    pub struct Lock {
+        // the account that locked the position, which gets its fees and the NFT back
+        pub owner: Address,
+        pub pool: Address,
+        // the block timestamp the NFT can be withdrawn from
+        pub unlock_time: u64,
+    }
+
+    #[ink(storage)]
+    #[derive(SpreadAllocate)]
+    pub struct LiquidityLockerContract {
+        factory: Address,
+        nonfungible_position_manager: Address,
+        // by position token id
+        locks: Mapping<Id, Lock>,
+    }
+
+    /// @notice Emitted when a position NFT is locked
+    /// @param liquidity The liquidity of the position at the time of locking
+    #[ink(event)]
+    pub struct Locked {
+        #[ink(topic)]
+        token_id: Id,
+        #[ink(topic)]
+        owner: Address,
+        #[ink(topic)]
+        pool: Address,
+        liquidity: u128,
+        unlock_time: u64,
+    }
+
+    /// @notice Emitted when the lock of a position NFT is extended
+    /// @param liquidity The liquidity of the position at the time of the extension
+    #[ink(event)]
+    pub struct LockExtended {
+        #[ink(topic)]
+        token_id: Id,
+        #[ink(topic)]
+        owner: Address,
+        #[ink(topic)]
+        pool: Address,
+        liquidity: u128,
+        unlock_time: u64,
+    }
+
+    /// @notice Emitted when an unlocked position NFT is withdrawn
+    #[ink(event)]
+    pub struct Unlocked {
+        #[ink(topic)]
+        token_id: Id,
+        #[ink(topic)]
+        owner: Address,
+        to: Address,
+    }
+
+    impl PSP34Receiver for LiquidityLockerContract {
+        /// @notice Locks a position NFT sent by the position manager until the unlock time
+        /// @dev data must hold the encoded u64 unlock time
+        #[ink(message)]
+        fn before_received(
+            &mut self,
+            _operator: AccountId,
+            from: AccountId,
+            id: Id,
+            data: Vec<u8>,
+        ) -> Result<(), PSP34ReceiverError> {
+            assert!(
+                self.env().caller() == self.nonfungible_position_manager,
+                "not a position nft"
+            );
+            let unlock_time = u64::decode(&mut &data[..]).expect("invalid unlock time");
+            assert!(
+                unlock_time > self.env().block_timestamp(),
+                "unlock time must be in the future"
+            );
+            let (_, operator, token0, token1, fee, _, _, liquidity, _, _, _, _) =
+                PositionManagerRef::positions(&self.nonfungible_position_manager, id.clone());
+            // the position manager clears the operator on every transfer, an operator here could pull the liquidity
+            // during the lock, so the invariant is checked rather than assumed
+            assert!(operator == ADDRESS0.into(), "position has an operator");
+            let pool = FactoryRef::get_pool(&self.factory, fee, token0, token1);
+            self.locks.insert(
+                &id,
+                &Lock {
+                    owner: from,
+                    pool,
+                    unlock_time,
+                },
+            );
+            self.env().emit_event(Locked {
+                token_id: id,
+                owner: from,
+                pool,
+                liquidity,
+                unlock_time,
+            });
+            Ok(())
+        }
+    }
+
+    impl LiquidityLockerContract {
+        #[ink(constructor)]
+        pub fn new(factory: Address, nonfungible_position_manager: Address) -> Self {
+            ink_lang::utils::initialize_contract(|instance: &mut Self| {
+                instance.factory = factory;
+                instance.nonfungible_position_manager = nonfungible_position_manager;
+            })
+        }
+
+        /// @notice Collects the fees of a locked position to the recipient
+        /// @return amount0 The amount of fees collected in token0
+        /// @return amount1 The amount of fees collected in token1
+        #[ink(message)]
+        pub fn collect(&mut self, token_id: Id, recipient: Address) -> (U256, U256) {
+            let lock = self.locks.get(&token_id).expect("lock not exist");
+            assert!(lock.owner == self.env().caller(), "only owner can collect");
+            assert!(recipient != self.env().account_id(), "cannot collect to locker");
+            // the position manager keeps fees collected to ADDRESS0 for itself
+            assert!(recipient != ADDRESS0.into(), "invalid recipient");
+            PositionManagerRef::collect(
+                &self.nonfungible_position_manager,
+                token_id,
+                recipient,
+                u128::MAX,
+                u128::MAX,
+            )
+        }
+
+        /// @notice Moves the unlock time of a locked position later
+        #[ink(message)]
+        pub fn extend(&mut self, token_id: Id, unlock_time: u64) {
+            let mut lock = self.locks.get(&token_id).expect("lock not exist");
+            assert!(lock.owner == self.env().caller(), "only owner can extend lock");
+            assert!(unlock_time > lock.unlock_time, "cannot shorten lock");
+            lock.unlock_time = unlock_time;
+            self.locks.insert(&token_id, &lock);
+            self.env().emit_event(LockExtended {
+                token_id: token_id.clone(),
+                owner: lock.owner,
+                pool: lock.pool,
+                liquidity: self._liquidity(token_id),
+                unlock_time,
+            });
+        }
+
+        /// @notice Sends a position NFT whose lock has expired to the given account
+        #[ink(message)]
+        pub fn withdraw(&mut self, token_id: Id, to: Address, data: Vec<u8>) {
+            let lock = self.locks.get(&token_id).expect("lock not exist");
+            assert!(lock.owner == self.env().caller(), "only owner can withdraw");
+            assert!(
+                self.env().block_timestamp() >= lock.unlock_time,
+                "position still locked"
+            );
+            assert!(to != self.env().account_id(), "cannot withdraw to locker");
+            self.locks.remove(&token_id);
+            self.env().emit_event(Unlocked {
+                token_id: token_id.clone(),
+                owner: lock.owner,
+                to,
+            });
+            PSP34Ref::transfer(&self.nonfungible_position_manager, to, token_id, data)
+                .expect("nft transfer error!");
+        }
+
+        #[ink(message)]
+        pub fn get_lock(&self, token_id: Id) -> Option<Lock> {
+            self.locks.get(&token_id)
+        }
+
+        /// @notice Whether the position NFT is held by the locker and its unlock time has not passed
+        #[ink(message)]
+        pub fn is_locked(&self, token_id: Id) -> bool {
+            match self.locks.get(&token_id) {
+                Some(lock) => self.env().block_timestamp() < lock.unlock_time,
+                None => false,
+            }
+        }
+
+        fn _liquidity(&self, token_id: Id) -> u128 {
+            let (_, _, _, _, _, _, _, liquidity, _, _, _, _) =
+                PositionManagerRef::positions(&self.nonfungible_position_manager, token_id);
+            liquidity
+        }
+    }
+}
@@ -7,15 +7,20 @@ pub mod position_manager {
     use openbrush::contracts::psp34::PSP34Data;
     // use crabswap::impls::periphery::position_manager::*;
     use openbrush::contracts::psp34::extensions::burnable::*;
+    use openbrush::contracts::psp34::extensions::enumerable::*;
     use openbrush::contracts::psp34::extensions::mintable::*;
+    use openbrush::contracts::psp22::*;
     use openbrush::modifiers;
     use crabswap::impls::erc721_permit::*;
     use crabswap::impls::periphery::PeripheryPayments::*;
     use crabswap::impls::pool_initialize::*;
     use crabswap::impls::psp34_base::*;
     use crabswap::traits::core::pool_action::PoolActionRef;
+    use crabswap::traits::core::pool_state::PoolStateRef;
+    use crabswap::traits::core::access_policy::AccessPolicyRef;
     use crabswap::traits::periphery::position_manager::*;
     use crabswap::traits::periphery::LiquidityManagement::*;
+    use crabswap::traits::periphery::swap_callback::*;
     use crabswap::traits::periphery::periphery_immutable_state::*;
 
     use ink_lang::codegen::EmitEvent;
@@ -41,13 +46,16 @@ pub mod position_manager {
     use libs::core::TickMath;
     use libs::periphery::LiquidityAmounts;
     use libs::PoolKey;
-    use primitives::{Address, U256};
+    use primitives::{Address, Int256, U160, U256};
     use scale::{Decode, Encode};
 
     use crabswap::impls::periphery_immutable_state::{ImmutableStateStorage,ImmutableStateData};
     use crabswap::traits::core::pool_action::*;
     use ink_env::CallFlags;
 
+    // 1e18, the whole position in split
+    const FRACTION_ONE: u128 = 1_000_000_000_000_000_000;
+
     #[derive(Default, Debug, Encode, Decode, SpreadAllocate, SpreadLayout, PackedLayout)]
     #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, StorageLayout))]
     pub struct Position {
@@ -88,7 +96,7 @@ pub mod position_manager {
         #[ImmutableStateField]
         immutable_state: ImmutableStateData,
         #[PSP34StorageField]
-        psp34: PSP34Data,
+        psp34: PSP34Data<EnumerableBalances>,
         #[ERC721PermitStorageField]
         erc721_permit: ERC721PermitData,
         #[PSP34BaseStorageField]
@@ -115,12 +123,18 @@ pub mod position_manager {
 
         /// @dev The address of the token descriptor contract, which handles generating token URIs for position tokens
         _tokenDescriptor: Address,
+
+        /// @dev Scoped permissions by (token ID, permission epoch of the token, grantee)
+        _permissions: Mapping<(Id, u32, Address), u8>,
+        /// @dev Bumped on every transfer of a token, so the permissions granted before lapse for good
+        _permissionEpochs: Mapping<Id, u32>,
     }
 
     impl Initializer for PositionMangerContract {}
     impl PSP34 for PositionMangerContract {}
     impl PSP34Mintable for PositionMangerContract {}
     impl PSP34Burnable for PositionMangerContract {}
+    impl PSP34Enumerable for PositionMangerContract {}
     impl PSP34Base for PositionMangerContract {}
     impl PeripheryImmutableState for PositionMangerContract {}
         
@@ -141,84 +155,14 @@ pub mod position_manager {
         // returns (uint128 liquidity,uint256 amount0,uint256 amount1,IUniswapV3Pool pool)
         #[ink(message)]
         fn addLiquidity(&mut self, params: AddLiquidityParams) -> (u128, U256, U256, Address) {
-            // PoolAddress.PoolKey memory poolKey =
-            //         PoolAddress.PoolKey({token0: params.token0, token1: params.token1, fee: params.fee});
-            let poolKey: PoolKey = PoolKey {
-                token0: params.token0,
-                token1: params.token1,
-                fee: params.fee,
-            };
-
-            // pool = IUniswapV3Pool(PoolAddress.computeAddress(factory, poolKey));
-            let factory = self.immutable_state.factory;
-            // let poolAddress = PoolAddress::computeAddress(factory, poolKey.clone());
-            let poolAddress =
-                FactoryRef::get_pool(&factory, params.fee, params.token0, params.token1);
-
-            //         // compute the liquidity amount
-            //         {
-            //             // (uint160 sqrtPriceX96, , , , , , ) = poolRef::slot0(poolAddress);
-            let slot0: Slot0 = PoolActionRef::getSlot0(&poolAddress);
-            let sqrtPriceX96 = slot0.sqrtPriceX96.value;
-            //             // uint160 sqrtRatioAX96 = TickMath.getSqrtRatioAtTick(params.tickLower);
-            let sqrtRatioAX96 = TickMath::getSqrtRatioAtTick(params.tickLower);
-            //             // uint160 sqrtRatioBX96 = TickMath.getSqrtRatioAtTick(params.tickUpper);
-            let sqrtRatioBX96 = TickMath::getSqrtRatioAtTick(params.tickUpper);
-
-            //             liquidity = LiquidityAmounts::getLiquidityForAmounts(
-            //                 sqrtPriceX96,
-            //                 sqrtRatioAX96,
-            //                 sqrtRatioBX96,
-            //                 params.amount0Desired,
-            //                 params.amount1Desired
-            //             );
-            //         }
-            ink_env::debug_println!(
-                "sqrtPriceX96 is:{},sqrtRatioAX96 is:{},sqrtRatioBX96 is:{}",
-                sqrtPriceX96,
-                sqrtRatioAX96,
-                sqrtRatioBX96
-            );
-            let liquidity = LiquidityAmounts::getLiquidityForAmounts(
-                sqrtPriceX96,
-                sqrtRatioAX96,
-                sqrtRatioBX96,
-                params.amount0Desired.value,
-                params.amount1Desired.value,
-            );
-
-            //         (amount0, amount1) = pool.mint(
-            //             params.recipient,
-            //             params.tickLower,
-            //             params.tickUpper,
-            //             liquidity,
-            //             abi.encode(MintCallbackData({poolKey: poolKey, payer: msg.sender}))
-            //         );
             let msg_sender = ink_env::caller::<DefaultEnvironment>();
-            let mint_callback_data = MintCallbackData {
-                poolKey: poolKey,
-                payer: msg_sender,
+            // liquidity added to the manager's own position is not owned by any token, the payer provides it
+            let owner = if params.recipient == ink_env::account_id::<DefaultEnvironment>() {
+                msg_sender
+            } else {
+                params.recipient
             };
-
-            let callback_data = scale::Encode::encode(&mint_callback_data);
-            let (amount0, amount1) = PoolActionRef::mint_builder(
-                &poolAddress,
-                params.recipient,
-                params.tickLower,
-                params.tickUpper,
-                liquidity,
-                callback_data.clone(),
-            )
-            .call_flags(CallFlags::default().set_allow_reentry(true))
-            .fire()
-            .unwrap();
-            // self.uniswapV3MintCallback(amount0, amount1, callback_data);
-            //         require(amount0 >= params.amount0Min && amount1 >= params.amount1Min, 'Price slippage check');
-            assert!(
-                amount0 >= params.amount0Min.value && amount1 >= params.amount1Min.value,
-                "Price slippage check"
-            );
-            return (liquidity, amount0, amount1, poolAddress);
+            self._addLiquidity(params, msg_sender, owner)
         }
 
         #[ink(message)]
@@ -261,6 +205,39 @@ pub mod position_manager {
         }
     }
 
+    impl SwapCallback for PositionMangerContract {
+        /// @notice Pays the pool for a swap made by the manager, from the manager's own tokens
+        #[ink(message)]
+        fn swapCallback(&mut self, amount0Delta: Int256, amount1Delta: Int256, data: Vec<u8>) {
+            let poolKey: PoolKey =
+                scale::Decode::decode(&mut data.as_ref()).expect("call back data parse error!");
+            let factory = self.immutable_state.factory;
+            let pool = FactoryRef::get_pool(&factory, poolKey.fee, poolKey.token0, poolKey.token1);
+            let msg_sender = ink_env::caller::<DefaultEnvironment>();
+            assert!(msg_sender == pool, "call back is not pool");
+            let address_this = ink_env::account_id::<DefaultEnvironment>();
+            if amount0Delta > 0 {
+                self.pay(poolKey.token0, address_this, msg_sender, U256::from(amount0Delta));
+            }
+            if amount1Delta > 0 {
+                self.pay(poolKey.token1, address_this, msg_sender, U256::from(amount1Delta));
+            }
+        }
+    }
+
+    impl PSP22Receiver for PositionMangerContract {
+        #[ink(message)]
+        fn before_received(
+            &mut self,
+            _operator: AccountId,
+            _from: AccountId,
+            _value: Balance,
+            _data: Vec<u8>,
+        ) -> Result<(), PSP22ReceiverError> {
+            Ok(())
+        }
+    }
+
     impl PeripheryPaymentsTrait for PositionMangerContract {}
 
     // modifier isAuthorizedForToken(uint256 tokenId) {
@@ -333,6 +310,23 @@ pub mod position_manager {
             })
         }
 
+        /// @notice Returns a page of the positions held by the owner, in the order of owners_token_by_index
+        /// @param start The index of the first position of the page
+        /// @param limit The maximum number of positions returned
+        #[ink(message)]
+        pub fn positions_of(&self, owner: Address, start: u128, limit: u128) -> Vec<(Id, Position)> {
+            let end = (self.balance_of(owner) as u128).min(start.saturating_add(limit));
+            let mut result = Vec::new();
+            for index in start..end {
+                let tokenId = self
+                    .owners_token_by_index(owner, index)
+                    .expect("index out of bounds");
+                let position = self._positions.get(tokenId.clone()).expect("tokenId not exist!");
+                result.push((tokenId, position));
+            }
+            result
+        }
+
         /// @dev Caches a pool key
         fn cachePoolKey(&mut self, pool: Address, poolKey: PoolAddress::PoolKey) -> u128 {
             let mut poolId = match self._poolIds.get(&pool) {
@@ -348,88 +342,377 @@ pub mod position_manager {
             poolId
         }
 
-        // #[ink(message)]
-        // pub fn testEvent(&mut self) -> u128 {
-        //     self.env().emit_event(TestEvent { tokenId: 1 });
-        //     0
-        // }
-    }
-
-    // impl PositionManager for PositionMangerContract{}
-    impl PositionManager for PositionMangerContract {
-        #[ink(message, payable)]
-        #[modifiers(checkDeadline(deadline))]
-        fn increaseLiquidity(
+        /// @dev Collects up to the maximum amounts owed to the position to the recipient, without authorization check
+        /// @return poolKey The key of the pool of the position
+        /// @return amount0 The amount of token0 collected
+        /// @return amount1 The amount of token1 collected
+        fn _collect(
             &mut self,
             tokenId: Id,
-            amount0Desired: U256,
-            amount1Desired: U256,
-            amount0Min: U256,
-            amount1Min: U256,
-            deadline: u64,
-        ) -> (
-            u128, //liquidity
-            U256, //amount0
-            U256, //amount1
-        ) {
-            // Position storage position = _positions[params.tokenId];
-            let params = IncreaseLiquidityParams {
-                tokenId,
-                amount0Desired,
-                amount1Desired,
-                amount0Min,
-                amount1Min,
-                deadline,
-            };
-            let mut position: Position = self
-                ._positions
-                .get(params.tokenId.clone())
-                .expect("token not in _positions!");
+            recipient: Address,
+            amount0Max: u128,
+            amount1Max: u128,
+        ) -> (PoolAddress::PoolKey, U256, U256) {
+            // Position storage position = _positions[tokenId];
+            let mut position:Position = self._positions.get(tokenId.clone()).expect("tokenId not exist!");
             // PoolAddress.PoolKey memory poolKey = _poolIdToPoolKey[position.poolId];
-            let poolKey: PoolAddress::PoolKey = self
-                ._poolIdToPoolKey
-                .get(position.poolId)
-                .expect("token not in _poolIdToPoolKey!");
-            // IUniswapV3Pool pool;
+            let poolKey:PoolAddress::PoolKey = self._poolIdToPoolKey.get(position.poolId).expect("pooId not exist!");
+            // IUniswapV3Pool pool = IUniswapV3Pool(PoolAddress.computeAddress(factory, poolKey));
+            let factoryAddress = self.immutable_state.factory;
+            let pool: Address =
+                FactoryRef::get_pool(&factoryAddress, poolKey.fee, poolKey.token0, poolKey.token1);
+            // (uint128 tokensOwed0, uint128 tokensOwed1) = (position.tokensOwed0, position.tokensOwed1);
+            let (mut tokensOwed0,mut tokensOwed1) = (position.tokensOwed0, position.tokensOwed1);
+            // // trigger an update of the position fees owed and fee growth snapshots if it has any liquidity
+            // if (position.liquidity > 0) {
+            //     pool.burn(position.tickLower, position.tickUpper, 0);
+            //     (, uint256 feeGrowthInside0LastX128, uint256 feeGrowthInside1LastX128, , ) =
+            //         pool.positions(PositionKey.compute(address(this), position.tickLower, position.tickUpper));
+            let address_of_this = ink_env::account_id::<DefaultEnvironment>();
+            if position.liquidity > 0 {
+                PoolActionRef::burn(&pool,position.tickLower, position.tickUpper, 0, None);
+                let position_info =
+                    PoolActionRef::positions(&pool,address_of_this, position.tickLower, position.tickUpper, None);
+                let feeGrowthInside0LastX128 = position_info.feeGrowthInside0LastX128;
+                let feeGrowthInside1LastX128 = position_info.feeGrowthInside1LastX128;
+                //     tokensOwed0 += uint128(
+                //         FullMath.mulDiv(
+                //             feeGrowthInside0LastX128 - position.feeGrowthInside0LastX128,
+                //             position.liquidity,
+                //             FixedPoint128.Q128
+                //         )
+                //     );
+                tokensOwed0+=FullMath::mulDiv(
+                                feeGrowthInside0LastX128.value - position.feeGrowthInside0LastX128.value,
+                                U256::from(position.liquidity),
+                                U256::from(FixedPoint128::Q128)
+                            ).as_u128();
+                //     tokensOwed1 += uint128(
+                //         FullMath.mulDiv(
+                //             feeGrowthInside1LastX128 - position.feeGrowthInside1LastX128,
+                //             position.liquidity,
+                //             FixedPoint128.Q128
+                //         )
+                //     );
+                tokensOwed1+=FullMath::mulDiv(
+                    feeGrowthInside1LastX128.value - position.feeGrowthInside1LastX128.value,
+                    U256::from(position.liquidity),
+                    U256::from(FixedPoint128::Q128)
+                ).as_u128();
+                //     position.feeGrowthInside0LastX128 = feeGrowthInside0LastX128;
+                //     position.feeGrowthInside1LastX128 = feeGrowthInside1LastX128;
+                position.feeGrowthInside0LastX128 = feeGrowthInside0LastX128;
+                position.feeGrowthInside1LastX128 = feeGrowthInside1LastX128;
+            }
 
-            // (liquidity, amount0, amount1, pool) = addLiquidity(
-            //     AddLiquidityParams({
-            //         token0: poolKey.token0,
-            //         token1: poolKey.token1,
-            //         fee: poolKey.fee,
-            //         tickLower: position.tickLower,
-            //         tickUpper: position.tickUpper,
-            //         amount0Desired: params.amount0Desired,
-            //         amount1Desired: params.amount1Desired,
-            //         amount0Min: params.amount0Min,
-            //         amount1Min: params.amount1Min,
-            //         recipient: address(this)
-            //     })
+            // // compute the arguments to give to the pool#collect method
+            // (uint128 amount0Collect, uint128 amount1Collect) =
+            //     (
+            //         amount0Max > tokensOwed0 ? tokensOwed0 : amount0Max,
+            //         amount1Max > tokensOwed1 ? tokensOwed1 : amount1Max
+            //     );
+            let (amount0Collect, amount1Collect) = (
+                    if amount0Max > tokensOwed0{
+                        tokensOwed0
+                    }else{
+                        amount0Max
+                    },
+                    if amount1Max > tokensOwed1 {
+                        tokensOwed1
+                    }else{
+                        amount1Max
+                    }
+                );
+            // // the actual amounts collected are returned
+            // (amount0, amount1) = pool.collect(
+            //     recipient,
+            //     position.tickLower,
+            //     position.tickUpper,
+            //     amount0Collect,
+            //     amount1Collect
             // );
-            let address_this = ink_env::account_id::<DefaultEnvironment>();
-            let (liquidity, amount0, amount1, pool) = self.addLiquidity(AddLiquidityParams {
-                token0: poolKey.token0,
-                token1: poolKey.token1,
-                fee: poolKey.fee,
-                tickLower: position.tickLower,
-                tickUpper: position.tickUpper,
-                amount0Desired: Uint256::new_with_u256(params.amount0Desired),
-                amount1Desired: Uint256::new_with_u256(params.amount1Desired),
-                amount0Min: Uint256::new_with_u256(params.amount0Min),
-                amount1Min: Uint256::new_with_u256(params.amount1Min),
-                recipient: address_this,
-            });
-
-            // bytes32 positionKey = PositionKey.compute(address(this), position.tickLower, position.tickUpper);
-            // let positionKey = PositionKey::compute(address_this, position.tickLower, position.tickUpper);
+            // the recipient may be this contract, which the tokens reenter through PSP22Receiver
+            let (amount0, amount1) = PoolActionRef::collect_builder(&pool,recipient,
+                    position.tickLower,
+                    position.tickUpper,
+                    amount0Collect,
+                    amount1Collect,
+                    None)
+                .call_flags(CallFlags::default().set_allow_reentry(true))
+                .fire()
+                .unwrap();
+            let amount0 = U256::from(amount0);
+            let amount1 = U256::from(amount1);
+            // // sometimes there will be a few less wei than expected due to rounding down in core, but we just subtract the full amount expected
+            // // instead of the actual amount so we can burn the token
+            // (position.tokensOwed0, position.tokensOwed1) = (tokensOwed0 - amount0Collect, tokensOwed1 - amount1Collect);
+            position.tokensOwed0 = tokensOwed0 - amount0Collect;
+            position.tokensOwed1 = tokensOwed1 - amount1Collect;
+            self._positions.insert(tokenId.clone(),&position);
+            (poolKey, amount0, amount1)
+        }
 
-            // // this is now updated to the current transaction
+        /// @dev Mints a position NFT to the recipient for new liquidity, paid by the payer in uniswapV3MintCallback
+        fn _mintPosition(
+            &mut self,
+            params: AddLiquidityParams,
+            recipient: Address,
+            payer: Address,
+        ) -> (Id, u128, U256, U256) {
+            let address_this = ink_env::account_id::<DefaultEnvironment>();
+            let (token0, token1, fee) = (params.token0, params.token1, params.fee);
+            let (tickLower, tickUpper) = (params.tickLower, params.tickUpper);
+            let (liquidity, amount0, amount1, pool) = self._addLiquidity(params, payer, recipient);
+            self._nextId = self._nextId + 1;
+            let tokenId = Id::U128(self._nextId);
+            self._mint_to(recipient, tokenId.clone()).expect("mint error!");
+            // _mint(params.recipient, (tokenId = _nextId++));
+            // bytes32 positionKey = PositionKey.compute(address(this), params.tickLower, params.tickUpper);
+            // let positionKey = PositionKey::compute(address_of_this,params.tickLower, params.tickUpper);
+            // (, uint256 feeGrowthInside0LastX128, uint256 feeGrowthInside1LastX128, , ) = pool.positions(positionKey);
+            let position_info =
+                PoolActionRef::positions(&pool, address_this, tickLower, tickUpper, None);
+            let feeGrowthInside0LastX128 = position_info.feeGrowthInside0LastX128;
+            let feeGrowthInside1LastX128 = position_info.feeGrowthInside1LastX128;
+
+            // // idempotent set
+            // uint80 poolId =
+            //     cachePoolKey(
+            //         address(pool),
+            //         PoolAddress.PoolKey({token0: params.token0, token1: params.token1, fee: params.fee})
+            //     );
+            let pool_key = PoolAddress::PoolKey {
+                token0: token0,
+                token1: token1,
+                fee: fee,
+            };
+            let poolId = self.cachePoolKey(pool, pool_key);
+
+            // _positions[tokenId] = Position({
+            //     nonce: 0,
+            //     operator: address(0),
+            //     poolId: poolId,
+            //     tickLower: params.tickLower,
+            //     tickUpper: params.tickUpper,
+            //     liquidity: liquidity,
+            //     feeGrowthInside0LastX128: feeGrowthInside0LastX128,
+            //     feeGrowthInside1LastX128: feeGrowthInside1LastX128,
+            //     tokensOwed0: 0,
+            //     tokensOwed1: 0
+            // });
+            let position = Position {
+                nonce: 0,
+                operator: ADDRESS0.into(),
+                poolId: poolId,
+                tickLower: tickLower,
+                tickUpper: tickUpper,
+                liquidity: liquidity,
+                feeGrowthInside0LastX128: feeGrowthInside0LastX128,
+                feeGrowthInside1LastX128: feeGrowthInside1LastX128,
+                tokensOwed0: 0,
+                tokensOwed1: 0,
+            };
+            self._positions.insert(tokenId.clone(), &position);
+            self.env().emit_event(IncreaseLiquidity {
+                tokenId:tokenId.clone(),
+                liquidity,
+                amount0,
+                amount1,
+            });
+            // ink_lang::codegen::EmitEvent::<PositionMangerContract>::emit_event(self.env(), IncreaseLiquidity {
+            //         tokenId,
+            //         liquidity,
+            //         amount0,
+            //         amount1,
+            //     });
+            // emit IncreaseLiquidity(tokenId, liquidity, amount0, amount1);
+            (tokenId.clone(), liquidity, amount0, amount1)
+        }
+
+        /// @dev Adds liquidity to the position of the recipient, paid by the payer in uniswapV3MintCallback. The owner is
+        /// the account the liquidity is added for, which the access policy of the pool is checked against
+        fn _addLiquidity(
+            &mut self,
+            params: AddLiquidityParams,
+            payer: Address,
+            owner: Address,
+        ) -> (u128, U256, U256, Address) {
+            // PoolAddress.PoolKey memory poolKey =
+            //         PoolAddress.PoolKey({token0: params.token0, token1: params.token1, fee: params.fee});
+            let poolKey: PoolKey = PoolKey {
+                token0: params.token0,
+                token1: params.token1,
+                fee: params.fee,
+            };
+
+            // pool = IUniswapV3Pool(PoolAddress.computeAddress(factory, poolKey));
+            let factory = self.immutable_state.factory;
+            // let poolAddress = PoolAddress::computeAddress(factory, poolKey.clone());
+            let poolAddress =
+                FactoryRef::get_pool(&factory, params.fee, params.token0, params.token1);
+            self._checkLiquidityProvider(poolAddress, owner);
+
+            //         // compute the liquidity amount
+            //         {
+            //             // (uint160 sqrtPriceX96, , , , , , ) = poolRef::slot0(poolAddress);
+            let slot0: Slot0 = PoolActionRef::getSlot0(&poolAddress);
+            let sqrtPriceX96 = slot0.sqrtPriceX96.value;
+            //             // uint160 sqrtRatioAX96 = TickMath.getSqrtRatioAtTick(params.tickLower);
+            let sqrtRatioAX96 = TickMath::getSqrtRatioAtTick(params.tickLower);
+            //             // uint160 sqrtRatioBX96 = TickMath.getSqrtRatioAtTick(params.tickUpper);
+            let sqrtRatioBX96 = TickMath::getSqrtRatioAtTick(params.tickUpper);
+
+            //             liquidity = LiquidityAmounts::getLiquidityForAmounts(
+            //                 sqrtPriceX96,
+            //                 sqrtRatioAX96,
+            //                 sqrtRatioBX96,
+            //                 params.amount0Desired,
+            //                 params.amount1Desired
+            //             );
+            //         }
+            let liquidity = LiquidityAmounts::getLiquidityForAmounts(
+                sqrtPriceX96,
+                sqrtRatioAX96,
+                sqrtRatioBX96,
+                params.amount0Desired.value,
+                params.amount1Desired.value,
+            );
+
+            //         (amount0, amount1) = pool.mint(
+            //             params.recipient,
+            //             params.tickLower,
+            //             params.tickUpper,
+            //             liquidity,
+            //             abi.encode(MintCallbackData({poolKey: poolKey, payer: msg.sender}))
+            //         );
+            let mint_callback_data = MintCallbackData {
+                poolKey: poolKey,
+                payer,
+            };
+
+            let callback_data = scale::Encode::encode(&mint_callback_data);
+            let (amount0, amount1) = PoolActionRef::mint_builder(
+                &poolAddress,
+                params.recipient,
+                params.tickLower,
+                params.tickUpper,
+                liquidity,
+                callback_data.clone(),
+                None,
+            )
+            .call_flags(CallFlags::default().set_allow_reentry(true))
+            .fire()
+            .unwrap();
+            // self.uniswapV3MintCallback(amount0, amount1, callback_data);
+            //         require(amount0 >= params.amount0Min && amount1 >= params.amount1Min, 'Price slippage check');
+            assert!(
+                amount0 >= params.amount0Min.value && amount1 >= params.amount1Min.value,
+                "Price slippage check"
+            );
+            return (liquidity, amount0, amount1, poolAddress);
+        }
+
+        /// @dev The pool only sees the manager as the recipient of the liquidity, so the access policy of a
+        /// permissioned pool is applied to the owner of the position here
+        fn _checkLiquidityProvider(&self, pool: Address, owner: Address) {
+            let policy = PoolStateRef::accessPolicy(&pool);
+            if policy != ADDRESS0.into() {
+                assert!(AccessPolicyRef::canProvideLiquidity(&policy, owner), "liquidity not allowed");
+            }
+        }
+
+        /// @dev The key of the permissions of a grantee on a token, under the current permission epoch of the token
+        fn _permissionKey(&self, tokenId: Id, grantee: Address) -> (Id, u32, Address) {
+            let epoch = self._permissionEpochs.get(tokenId.clone()).unwrap_or(0);
+            (tokenId, epoch, grantee)
+        }
+
+        // #[ink(message)]
+        // pub fn testEvent(&mut self) -> u128 {
+        //     self.env().emit_event(TestEvent { tokenId: 1 });
+        //     0
+        // }
+    }
+
+    // impl PositionManager for PositionMangerContract{}
+    impl PositionManager for PositionMangerContract {
+        #[ink(message, payable)]
+        #[modifiers(checkDeadline(deadline))]
+        #[modifiers(isAuthorizedForToken(tokenId, PERMISSION_INCREASE))]
+        fn increaseLiquidity(
+            &mut self,
+            tokenId: Id,
+            amount0Desired: U256,
+            amount1Desired: U256,
+            amount0Min: U256,
+            amount1Min: U256,
+            deadline: u64,
+        ) -> (
+            u128, //liquidity
+            U256, //amount0
+            U256, //amount1
+        ) {
+            // Position storage position = _positions[params.tokenId];
+            let params = IncreaseLiquidityParams {
+                tokenId,
+                amount0Desired,
+                amount1Desired,
+                amount0Min,
+                amount1Min,
+                deadline,
+            };
+            let mut position: Position = self
+                ._positions
+                .get(params.tokenId.clone())
+                .expect("token not in _positions!");
+            // PoolAddress.PoolKey memory poolKey = _poolIdToPoolKey[position.poolId];
+            let poolKey: PoolAddress::PoolKey = self
+                ._poolIdToPoolKey
+                .get(position.poolId)
+                .expect("token not in _poolIdToPoolKey!");
+            // IUniswapV3Pool pool;
+
+            // (liquidity, amount0, amount1, pool) = addLiquidity(
+            //     AddLiquidityParams({
+            //         token0: poolKey.token0,
+            //         token1: poolKey.token1,
+            //         fee: poolKey.fee,
+            //         tickLower: position.tickLower,
+            //         tickUpper: position.tickUpper,
+            //         amount0Desired: params.amount0Desired,
+            //         amount1Desired: params.amount1Desired,
+            //         amount0Min: params.amount0Min,
+            //         amount1Min: params.amount1Min,
+            //         recipient: address(this)
+            //     })
+            // );
+            let address_this = ink_env::account_id::<DefaultEnvironment>();
+            let msg_sender = ink_env::caller::<DefaultEnvironment>();
+            let owner: Address = self.owner_of(params.tokenId.clone()).expect("tokenId not exist!");
+            let (liquidity, amount0, amount1, pool) = self._addLiquidity(AddLiquidityParams {
+                token0: poolKey.token0,
+                token1: poolKey.token1,
+                fee: poolKey.fee,
+                tickLower: position.tickLower,
+                tickUpper: position.tickUpper,
+                amount0Desired: Uint256::new_with_u256(params.amount0Desired),
+                amount1Desired: Uint256::new_with_u256(params.amount1Desired),
+                amount0Min: Uint256::new_with_u256(params.amount0Min),
+                amount1Min: Uint256::new_with_u256(params.amount1Min),
+                recipient: address_this,
+            }, msg_sender, owner);
+
+            // bytes32 positionKey = PositionKey.compute(address(this), position.tickLower, position.tickUpper);
+            // let positionKey = PositionKey::compute(address_this, position.tickLower, position.tickUpper);
+
+            // // this is now updated to the current transaction
             // (, uint256 feeGrowthInside0LastX128, uint256 feeGrowthInside1LastX128, , ) = pool.positions(positionKey);
             let positionInfo: Info = PoolActionRef::positions(
                 &pool,
                 address_this,
                 position.tickLower,
                 position.tickUpper,
+                None,
             );
             let feeGrowthInside0LastX128 = positionInfo.feeGrowthInside0LastX128;
             let feeGrowthInside1LastX128 = positionInfo.feeGrowthInside1LastX128;
@@ -487,7 +770,7 @@ pub mod position_manager {
         // checkDeadline(params.deadline)
         #[ink(message, payable)]
         #[modifiers(checkDeadline(deadline))]
-        #[modifiers(isAuthorizedForToken(tokenId))]
+        #[modifiers(isAuthorizedForToken(tokenId, PERMISSION_DECREASE))]
         fn decreaseLiquidity(
             &mut self,
             tokenId: Id,
@@ -534,6 +817,7 @@ pub mod position_manager {
                 position.tickLower,
                 position.tickUpper,
                 params.liquidity,
+                None,
             );
             // require(amount0 >= params.amount0Min && amount1 >= params.amount1Min, 'Price slippage check');
             assert!(
@@ -550,6 +834,7 @@ pub mod position_manager {
                 address_this,
                 position.tickLower,
                 position.tickUpper,
+                None,
             );
             let feeGrowthInside0LastX128 = pool_info.feeGrowthInside0LastX128;
             let feeGrowthInside1LastX128 = pool_info.feeGrowthInside1LastX128;
@@ -627,7 +912,71 @@ pub mod position_manager {
                 "ERC721: operator query for nonexistent token"
             );
             let owner: Address = self.owner_of(tokenId.clone()).unwrap();
-            spender == owner || self._allowance(&owner, &spender, &Some(&tokenId))
+            // the approved operator is kept in the position, see _approve_for
+            spender == owner
+                || self.getApproved(tokenId) == spender
+                || self.psp34.operator_approvals.get((owner, spender, Option::<Id>::None)).is_some()
+        }
+
+        fn _hasPermission(&self, spender: Address, tokenId: Id, permission: u8) -> bool {
+            match self.owner_of(tokenId.clone()) {
+                Some(_) => {
+                    let granted = self._permissions.get(self._permissionKey(tokenId, spender)).unwrap_or(0);
+                    permission != 0 && granted & permission == permission
+                }
+                None => false,
+            }
+        }
+
+        #[ink(message)]
+        fn grantPermissions(&mut self, tokenId: Id, grantee: Address, permissions: u8) {
+            let msg_sender = ink_env::caller::<DefaultEnvironment>();
+            assert!(self._isApprovedOrOwner(msg_sender, tokenId.clone()), "Not approved");
+            assert!(
+                permissions != 0 && permissions & !PERMISSION_ALL == 0,
+                "invalid permissions"
+            );
+            let owner: Address = self.owner_of(tokenId.clone()).unwrap();
+            let key = self._permissionKey(tokenId.clone(), grantee);
+            let granted = self._permissions.get(&key).unwrap_or(0) | permissions;
+            self._permissions.insert(&key, &granted);
+            self.env().emit_event(PermissionsGranted {
+                tokenId,
+                owner,
+                grantee,
+                permissions: granted,
+            });
+        }
+
+        #[ink(message)]
+        fn revokePermissions(&mut self, tokenId: Id, grantee: Address, permissions: u8) {
+            let msg_sender = ink_env::caller::<DefaultEnvironment>();
+            assert!(
+                msg_sender == grantee || self._isApprovedOrOwner(msg_sender, tokenId.clone()),
+                "Not approved"
+            );
+            let owner: Address = self.owner_of(tokenId.clone()).expect("tokenId not exist!");
+            let key = self._permissionKey(tokenId.clone(), grantee);
+            let granted = self._permissions.get(&key).unwrap_or(0) & !permissions;
+            if granted == 0 {
+                self._permissions.remove(&key);
+            } else {
+                self._permissions.insert(&key, &granted);
+            }
+            self.env().emit_event(PermissionsRevoked {
+                tokenId,
+                owner,
+                grantee,
+                permissions: granted,
+            });
+        }
+
+        #[ink(message)]
+        fn getPermissions(&self, tokenId: Id, grantee: Address) -> u8 {
+            match self.owner_of(tokenId.clone()) {
+                Some(_) => self._permissions.get(self._permissionKey(tokenId, grantee)).unwrap_or(0),
+                None => 0,
+            }
         }
 
         #[ink(message)]
@@ -734,81 +1083,12 @@ pub mod position_manager {
             // let amount0: U256;
             // let amount1: U256;
 
-            let (liquidity, amount0, amount1, pool) = self.addLiquidity(addLiquidityParams);
-            ink_env::debug_println!(
-                "liquidity:{:?}, amount0:{:?}, amount1:{:?}",
-                liquidity,
-                amount0,
-                amount1
-            );
-            self._nextId = self._nextId + 1;
-            let tokenId = Id::U128(self._nextId);
-            self._mint_to(recipient, tokenId.clone()).expect("mint error!");
-            // _mint(params.recipient, (tokenId = _nextId++));
-            // bytes32 positionKey = PositionKey.compute(address(this), params.tickLower, params.tickUpper);
-            // let positionKey = PositionKey::compute(address_of_this,params.tickLower, params.tickUpper);
-            // (, uint256 feeGrowthInside0LastX128, uint256 feeGrowthInside1LastX128, , ) = pool.positions(positionKey);
-            let position_info =
-                PoolActionRef::positions(&pool, position_manager_address, tickLower, tickUpper);
-            let feeGrowthInside0LastX128 = position_info.feeGrowthInside0LastX128;
-            let feeGrowthInside1LastX128 = position_info.feeGrowthInside1LastX128;
-
-            // // idempotent set
-            // uint80 poolId =
-            //     cachePoolKey(
-            //         address(pool),
-            //         PoolAddress.PoolKey({token0: params.token0, token1: params.token1, fee: params.fee})
-            //     );
-            let pool_key = PoolAddress::PoolKey {
-                token0: token0,
-                token1: token1,
-                fee: fee,
-            };
-            let poolId = self.cachePoolKey(position_manager_address, pool_key);
-
-            // _positions[tokenId] = Position({
-            //     nonce: 0,
-            //     operator: address(0),
-            //     poolId: poolId,
-            //     tickLower: params.tickLower,
-            //     tickUpper: params.tickUpper,
-            //     liquidity: liquidity,
-            //     feeGrowthInside0LastX128: feeGrowthInside0LastX128,
-            //     feeGrowthInside1LastX128: feeGrowthInside1LastX128,
-            //     tokensOwed0: 0,
-            //     tokensOwed1: 0
-            // });
-            let position = Position {
-                nonce: 0,
-                operator: ADDRESS0.into(),
-                poolId: poolId,
-                tickLower: tickLower,
-                tickUpper: tickUpper,
-                liquidity: liquidity,
-                feeGrowthInside0LastX128: feeGrowthInside0LastX128,
-                feeGrowthInside1LastX128: feeGrowthInside1LastX128,
-                tokensOwed0: 0,
-                tokensOwed1: 0,
-            };
-            self._positions.insert(tokenId.clone(), &position);
-            self.env().emit_event(IncreaseLiquidity {
-                tokenId:tokenId.clone(),
-                liquidity,
-                amount0,
-                amount1,
-            });
-            // ink_lang::codegen::EmitEvent::<PositionMangerContract>::emit_event(self.env(), IncreaseLiquidity {
-            //         tokenId,
-            //         liquidity,
-            //         amount0,
-            //         amount1,
-            //     });
-            // emit IncreaseLiquidity(tokenId, liquidity, amount0, amount1);
-            (tokenId.clone(), liquidity, amount0, amount1)
-        }
+            let msg_sender = ink_env::caller::<DefaultEnvironment>();
+            self._mintPosition(addLiquidityParams, recipient, msg_sender)
+        }
 
         #[ink(message, payable)]
-        #[modifiers(isAuthorizedForToken(tokenId))]
+        #[modifiers(isAuthorizedForToken(tokenId, PERMISSION_COLLECT))]
         fn collect(
             &mut self,
             tokenId: Id,
@@ -816,152 +1096,73 @@ pub mod position_manager {
             amount0Max: u128,
             amount1Max: u128,
         ) -> (U256, U256){
-            let params = CollectParams{
-                tokenId,
-                recipient,
-                amount0Max,
-                amount1Max,
-            };
             // require(params.amount0Max > 0 || params.amount1Max > 0);
-            ink_env::debug_println!("$$$$$$$$$$$$$$$$$1");
-            assert!(params.amount0Max > 0 || params.amount1Max > 0,"input must be position!");
+            assert!(amount0Max > 0 || amount1Max > 0,"input must be position!");
             // // allow collecting to the nft position manager address with address 0
             // address recipient = params.recipient == address(0) ? address(this) : params.recipient;
-            ink_env::debug_println!("$$$$$$$$$$$$$$$$$2");
-            let recipient:Address = if params.recipient == ADDRESS0.into(){
+            let recipient:Address = if recipient == ADDRESS0.into(){
                 ink_env::account_id::<DefaultEnvironment>()
             }else{
-                params.recipient
+                recipient
             };
-            ink_env::debug_println!("$$$$$$$$$$$$$$$$$3");
-            // Position storage position = _positions[params.tokenId];
-            let mut position:Position = self._positions.get(params.tokenId.clone()).expect("tokenId not exist!");
-            ink_env::debug_println!("$$$$$$$$$$$$$$$$$4");
-            // PoolAddress.PoolKey memory poolKey = _poolIdToPoolKey[position.poolId];
-            let poolKey:PoolAddress::PoolKey = self._poolIdToPoolKey.get(position.poolId).expect("pooId not exist!");
-            ink_env::debug_println!("$$$$$$$$$$$$$$$$$5");
-            // IUniswapV3Pool pool = IUniswapV3Pool(PoolAddress.computeAddress(factory, poolKey));
-            let factoryAddress = self.immutable_state.factory;
-            ink_env::debug_println!("$$$$$$$$$$$$$$$$$6");
-            let pool: Address =
-                FactoryRef::get_pool(&factoryAddress, poolKey.fee, poolKey.token0, poolKey.token1);
-            ink_env::debug_println!("$$$$$$$$$$$$$$$$$7");
-            // (uint128 tokensOwed0, uint128 tokensOwed1) = (position.tokensOwed0, position.tokensOwed1);
-            let (mut tokensOwed0,mut tokensOwed1) = (position.tokensOwed0, position.tokensOwed1);
-            ink_env::debug_println!("$$$$$$$$$$$$$$$$$8");
-            // // trigger an update of the position fees owed and fee growth snapshots if it has any liquidity
-            // if (position.liquidity > 0) {
-            //     pool.burn(position.tickLower, position.tickUpper, 0);
-            //     (, uint256 feeGrowthInside0LastX128, uint256 feeGrowthInside1LastX128, , ) =
-            //         pool.positions(PositionKey.compute(address(this), position.tickLower, position.tickUpper));
-            let address_of_this = ink_env::account_id::<DefaultEnvironment>();
-            ink_env::debug_println!("$$$$$$$$$$$$$$$$$9");
-            if position.liquidity > 0 {
-                ink_env::debug_println!("$$$$$$$$$$$$$$$$$10");
-                PoolActionRef::burn(&pool,position.tickLower, position.tickUpper, 0);
-                ink_env::debug_println!("$$$$$$$$$$$$$$$$$11");
-                let position_info =
-                    PoolActionRef::positions(&pool,address_of_this, position.tickLower, position.tickUpper);
-                ink_env::debug_println!("$$$$$$$$$$$$$$$$$11");
-                let feeGrowthInside0LastX128 = position_info.feeGrowthInside0LastX128;
-                ink_env::debug_println!("$$$$$$$$$$$$$$$$$12");
-                let feeGrowthInside1LastX128 = position_info.feeGrowthInside1LastX128;
-                ink_env::debug_println!("$$$$$$$$$$$$$$$$$13");
-                //     tokensOwed0 += uint128(
-                //         FullMath.mulDiv(
-                //             feeGrowthInside0LastX128 - position.feeGrowthInside0LastX128,
-                //             position.liquidity,
-                //             FixedPoint128.Q128
-                //         )
-                //     );
-                tokensOwed0+=FullMath::mulDiv(
-                                feeGrowthInside0LastX128.value - position.feeGrowthInside0LastX128.value,
-                                U256::from(position.liquidity),
-                                U256::from(FixedPoint128::Q128)
-                            ).as_u128();
-                            ink_env::debug_println!("$$$$$$$$$$$$$$$$$14");
-                //     tokensOwed1 += uint128(
-                //         FullMath.mulDiv(
-                //             feeGrowthInside1LastX128 - position.feeGrowthInside1LastX128,
-                //             position.liquidity,
-                //             FixedPoint128.Q128
-                //         )
-                //     );
-                tokensOwed1+=FullMath::mulDiv(
-                    feeGrowthInside1LastX128.value - position.feeGrowthInside1LastX128.value,
-                    U256::from(position.liquidity),
-                    U256::from(FixedPoint128::Q128)
-                ).as_u128();
-                ink_env::debug_println!("$$$$$$$$$$$$$$$$$15");
-                //     position.feeGrowthInside0LastX128 = feeGrowthInside0LastX128;
-                //     position.feeGrowthInside1LastX128 = feeGrowthInside1LastX128;
-                position.feeGrowthInside0LastX128 = feeGrowthInside0LastX128;
-                position.feeGrowthInside1LastX128 = feeGrowthInside1LastX128;
-                ink_env::debug_println!("$$$$$$$$$$$$$$$$$16");
-            }
-
-            // // compute the arguments to give to the pool#collect method
-            // (uint128 amount0Collect, uint128 amount1Collect) =
-            //     (
-            //         params.amount0Max > tokensOwed0 ? tokensOwed0 : params.amount0Max,
-            //         params.amount1Max > tokensOwed1 ? tokensOwed1 : params.amount1Max
-            //     );
-            let (amount0Collect, amount1Collect) = (
-                    if params.amount0Max > tokensOwed0{
-                        tokensOwed0
-                    }else{
-                        params.amount0Max
-                    },
-                    if params.amount1Max > tokensOwed1 {
-                        tokensOwed1
-                    }else{
-                        params.amount1Max
-                    }
-                );
-                ink_env::debug_println!("$$$$$$$$$$$$$$$$$17");
-            // // the actual amounts collected are returned
-            // (amount0, amount1) = pool.collect(
-            //     recipient,
-            //     position.tickLower,
-            //     position.tickUpper,
-            //     amount0Collect,
-            //     amount1Collect
-            // );
-            let (amount0, amount1) = PoolActionRef::collect(&pool,recipient,
-                    position.tickLower,
-                    position.tickUpper,
-                    amount0Collect,
-                    amount1Collect);
-                    ink_env::debug_println!("$$$$$$$$$$$$$$$$$18");
-            let amount0 = U256::from(amount0);
-            let amount1 = U256::from(amount1);
-            // // sometimes there will be a few less wei than expected due to rounding down in core, but we just subtract the full amount expected
-            // // instead of the actual amount so we can burn the token
-            // (position.tokensOwed0, position.tokensOwed1) = (tokensOwed0 - amount0Collect, tokensOwed1 - amount1Collect);
-            position.tokensOwed0 = tokensOwed0 - amount0Collect;
-            position.tokensOwed1 = tokensOwed1 - amount1Collect;
-            ink_env::debug_println!("$$$$$$$$$$$$$$$$$19");
-            self._positions.insert(params.tokenId.clone(),&position);
-            ink_env::debug_println!("$$$$$$$$$$$$$$$$$20");
+            let (_, amount0, amount1) = self._collect(tokenId.clone(), recipient, amount0Max, amount1Max);
             // emit Collect(params.tokenId, recipient, amount0Collect, amount1Collect);
             self.env().emit_event(Collect {
-                tokenId:params.tokenId.clone(),
+                tokenId,
                 recipient,
                 amount0,
                 amount1,
             });
-            // ink_lang::codegen::EmitEvent::<PositionMangerContract>::emit_event(self.env(), Collect {
-            //     tokenId:params.tokenId,
-            //     recipient,
-            //     amount0,
-            //     amount1,
-            // });
-            ink_env::debug_println!("$$$$$$$$$$$$$$$$$21");
             (amount0,amount1)
         }
 
         #[ink(message, payable)]
-        #[modifiers(isAuthorizedForToken(tokenId))]
+        fn collectMany(&mut self, collects: Vec<(Id, u128, u128)>, recipient: Address) -> Vec<(Address, U256)> {
+            let msg_sender = ink_env::caller::<DefaultEnvironment>();
+            let address_this = ink_env::account_id::<DefaultEnvironment>();
+            // allow collecting to the nft position manager address with address 0
+            let recipient: Address = if recipient == ADDRESS0.into() {
+                address_this
+            } else {
+                recipient
+            };
+            let mut amounts: Vec<(Address, U256)> = Vec::new();
+            for (tokenId, amount0Max, amount1Max) in collects.into_iter() {
+                assert!(
+                    self._isApprovedOrOwner(msg_sender, tokenId.clone())
+                        || self._hasPermission(msg_sender, tokenId.clone(), PERMISSION_COLLECT),
+                    "Not approved"
+                );
+                assert!(amount0Max > 0 || amount1Max > 0, "input must be position!");
+                // everything is collected to this contract and paid out once per token below
+                let (poolKey, amount0, amount1) =
+                    self._collect(tokenId.clone(), address_this, amount0Max, amount1Max);
+                for (token, amount) in [(poolKey.token0, amount0), (poolKey.token1, amount1)] {
+                    match amounts.iter_mut().find(|(t, _)| *t == token) {
+                        Some(total) => total.1 += amount,
+                        None => amounts.push((token, amount)),
+                    }
+                }
+                self.env().emit_event(Collect {
+                    tokenId,
+                    recipient,
+                    amount0,
+                    amount1,
+                });
+            }
+            if recipient != address_this {
+                for (token, amount) in amounts.iter() {
+                    if !amount.is_zero() {
+                        PSP22Ref::transfer(token, recipient, amount.as_u128(), Vec::new())
+                            .expect("token transfer error!");
+                    }
+                }
+            }
+            amounts
+        }
+
+        #[ink(message, payable)]
+        #[modifiers(isAuthorizedForToken(tokenId, PERMISSION_DECREASE))]
         fn burn(&mut self,tokenId:Id){
             // Position storage position = _positions[tokenId];
             let position:Position = self._positions.get(tokenId.clone()).expect("tokenId not exist!");
@@ -971,8 +1172,348 @@ pub mod position_manager {
             // delete _positions[tokenId];
             self._positions.remove(tokenId.clone());
             // _burn(tokenId);
-            let caller = ink_env::caller::<DefaultEnvironment>();
-            self._burn_from(caller,tokenId.clone()).expect("burn token failed");
+            // burn from the owner, the caller may be an approved operator
+            let owner = self.owner_of(tokenId.clone()).unwrap();
+            self._burn_from(owner,tokenId.clone()).expect("burn token failed");
+        }
+
+        #[ink(message, payable)]
+        #[modifiers(checkDeadline(deadline))]
+        #[modifiers(isAuthorizedForToken(tokenId, PERMISSION_ALL))]
+        fn rebalance(
+            &mut self,
+            tokenId: Id,
+            tickLower: Int24,
+            tickUpper: Int24,
+            swapSqrtPriceLimitX96: Option<U160>,
+            amount0Min: U256,
+            amount1Min: U256,
+            deadline: u64,
+        ) -> (Id, u128, U256, U256) {
+            assert!(tickLower < tickUpper, "TLU");
+            let position: Position = self._positions.get(tokenId.clone()).expect("tokenId not exist!");
+            let poolKey: PoolAddress::PoolKey =
+                self._poolIdToPoolKey.get(position.poolId).expect("pooId not exist!");
+            let factoryAddress = self.immutable_state.factory;
+            let pool: Address =
+                FactoryRef::get_pool(&factoryAddress, poolKey.fee, poolKey.token0, poolKey.token1);
+            let owner: Address = self.owner_of(tokenId.clone()).unwrap();
+            let address_this = ink_env::account_id::<DefaultEnvironment>();
+
+            // withdraw the liquidity and everything owed to the position into this contract
+            if position.liquidity > 0 {
+                self.decreaseLiquidity(
+                    tokenId.clone(),
+                    position.liquidity,
+                    U256::zero(),
+                    U256::zero(),
+                    deadline,
+                );
+            }
+            let (_, collected0, collected1) =
+                self._collect(tokenId.clone(), address_this, u128::MAX, u128::MAX);
+            self.env().emit_event(Collect {
+                tokenId: tokenId.clone(),
+                recipient: address_this,
+                amount0: collected0,
+                amount1: collected1,
+            });
+            let (mut amount0, mut amount1) = (collected0.as_u128(), collected1.as_u128());
+            self._positions.remove(tokenId.clone());
+            self._burn_from(owner, tokenId.clone()).expect("burn token failed");
+
+            // swap the withdrawn tokens to the ratio of the new range
+            if let Some(sqrtPriceLimitX96) = swapSqrtPriceLimitX96 {
+                let sqrtPriceX96 = PoolActionRef::getSlot0(&pool).sqrtPriceX96.value;
+                let (zeroForOne, amountIn) = LiquidityAmounts::getSwapAmountForRatio(
+                    sqrtPriceX96,
+                    TickMath::getSqrtRatioAtTick(tickLower),
+                    TickMath::getSqrtRatioAtTick(tickUpper),
+                    U256::from(amount0),
+                    U256::from(amount1),
+                );
+                if !amountIn.is_zero() {
+                    // the pool calls back swapCallback, which pays from this contract
+                    let (amount0Delta, amount1Delta) = PoolActionRef::swap_builder(
+                        &pool,
+                        address_this,
+                        zeroForOne,
+                        amountIn.as_u128() as Int256,
+                        sqrtPriceLimitX96,
+                        scale::Encode::encode(&poolKey),
+                    )
+                    .call_flags(CallFlags::default().set_allow_reentry(true))
+                    .fire()
+                    .unwrap();
+                    amount0 = (amount0 as Int256 - amount0Delta) as u128;
+                    amount1 = (amount1 as Int256 - amount1Delta) as u128;
+                }
+            }
+
+            let (newTokenId, liquidity, used0, used1) = self._mintPosition(
+                AddLiquidityParams {
+                    token0: poolKey.token0,
+                    token1: poolKey.token1,
+                    fee: poolKey.fee,
+                    recipient: address_this,
+                    tickLower,
+                    tickUpper,
+                    amount0Desired: Uint256::new_with_u256(U256::from(amount0)),
+                    amount1Desired: Uint256::new_with_u256(U256::from(amount1)),
+                    amount0Min: Uint256::new_with_u256(amount0Min),
+                    amount1Min: Uint256::new_with_u256(amount1Min),
+                },
+                owner,
+                address_this,
+            );
+            // return what the new position could not use
+            if U256::from(amount0) > used0 {
+                PSP22Ref::transfer(&poolKey.token0, owner, amount0 - used0.as_u128(), Vec::new())
+                    .expect("token0 transfer error!");
+            }
+            if U256::from(amount1) > used1 {
+                PSP22Ref::transfer(&poolKey.token1, owner, amount1 - used1.as_u128(), Vec::new())
+                    .expect("token1 transfer error!");
+            }
+            self.env().emit_event(Rebalance {
+                tokenId,
+                newTokenId: newTokenId.clone(),
+                liquidity,
+                amount0: used0,
+                amount1: used1,
+            });
+            (newTokenId, liquidity, used0, used1)
+        }
+
+        #[ink(message, payable)]
+        #[modifiers(checkDeadline(deadline))]
+        fn zap(
+            &mut self,
+            tokenIn: Address,
+            token0: Address,
+            token1: Address,
+            fee: Uint24,
+            tickLower: Int24,
+            tickUpper: Int24,
+            amountIn: U256,
+            swapSqrtPriceLimitX96: U160,
+            amount0Min: U256,
+            amount1Min: U256,
+            recipient: Address,
+            deadline: u64,
+        ) -> (Id, u128, U256, U256) {
+            assert!(tokenIn == token0 || tokenIn == token1, "tokenIn not in pool");
+            assert!(tickLower < tickUpper, "TLU");
+            assert!(amountIn > U256::zero(), "amountIn must big than 0");
+            let factoryAddress = self.immutable_state.factory;
+            let pool: Address = FactoryRef::get_pool(&factoryAddress, fee, token0, token1);
+            assert!(pool != ADDRESS0.into(), "pool not exist");
+            let msg_sender = ink_env::caller::<DefaultEnvironment>();
+            let address_this = ink_env::account_id::<DefaultEnvironment>();
+            self.pay(tokenIn, msg_sender, address_this, amountIn);
+
+            let (mut amount0, mut amount1) = if tokenIn == token0 {
+                (amountIn.as_u128(), 0)
+            } else {
+                (0, amountIn.as_u128())
+            };
+            let sqrtPriceX96 = PoolActionRef::getSlot0(&pool).sqrtPriceX96.value;
+            let (zeroForOne, swapAmountIn) = LiquidityAmounts::getSwapAmountForRatio(
+                sqrtPriceX96,
+                TickMath::getSqrtRatioAtTick(tickLower),
+                TickMath::getSqrtRatioAtTick(tickUpper),
+                U256::from(amount0),
+                U256::from(amount1),
+            );
+            if !swapAmountIn.is_zero() {
+                let poolKey = PoolKey {
+                    token0,
+                    token1,
+                    fee,
+                };
+                // the pool calls back swapCallback, which pays from this contract
+                let (amount0Delta, amount1Delta) = PoolActionRef::swap_builder(
+                    &pool,
+                    address_this,
+                    zeroForOne,
+                    swapAmountIn.as_u128() as Int256,
+                    swapSqrtPriceLimitX96,
+                    scale::Encode::encode(&poolKey),
+                )
+                .call_flags(CallFlags::default().set_allow_reentry(true))
+                .fire()
+                .unwrap();
+                amount0 = (amount0 as Int256 - amount0Delta) as u128;
+                amount1 = (amount1 as Int256 - amount1Delta) as u128;
+            }
+
+            let (tokenId, liquidity, used0, used1) = self._mintPosition(
+                AddLiquidityParams {
+                    token0,
+                    token1,
+                    fee,
+                    recipient: address_this,
+                    tickLower,
+                    tickUpper,
+                    amount0Desired: Uint256::new_with_u256(U256::from(amount0)),
+                    amount1Desired: Uint256::new_with_u256(U256::from(amount1)),
+                    amount0Min: Uint256::new_with_u256(amount0Min),
+                    amount1Min: Uint256::new_with_u256(amount1Min),
+                },
+                recipient,
+                address_this,
+            );
+            // refund the dust the position could not use
+            if U256::from(amount0) > used0 {
+                PSP22Ref::transfer(&token0, msg_sender, amount0 - used0.as_u128(), Vec::new())
+                    .expect("token0 transfer error!");
+            }
+            if U256::from(amount1) > used1 {
+                PSP22Ref::transfer(&token1, msg_sender, amount1 - used1.as_u128(), Vec::new())
+                    .expect("token1 transfer error!");
+            }
+            (tokenId, liquidity, used0, used1)
+        }
+
+        #[ink(message)]
+        #[modifiers(isAuthorizedForToken(tokenId, PERMISSION_TRANSFER))]
+        fn split(&mut self, tokenId: Id, liquidityFraction: U256, recipient: Address) -> (Id, u128) {
+            assert!(
+                liquidityFraction > U256::zero() && liquidityFraction < U256::from(FRACTION_ONE),
+                "fraction out of range"
+            );
+            let mut position: Position = self._positions.get(tokenId.clone()).expect("tokenId not exist!");
+            // both positions keep the fee growth snapshots, so the fees not yet accounted divide with the liquidity
+            let liquidity = FullMath::mulDiv(
+                U256::from(position.liquidity),
+                liquidityFraction,
+                U256::from(FRACTION_ONE),
+            )
+            .as_u128();
+            let tokensOwed0 = FullMath::mulDiv(
+                U256::from(position.tokensOwed0),
+                liquidityFraction,
+                U256::from(FRACTION_ONE),
+            )
+            .as_u128();
+            let tokensOwed1 = FullMath::mulDiv(
+                U256::from(position.tokensOwed1),
+                liquidityFraction,
+                U256::from(FRACTION_ONE),
+            )
+            .as_u128();
+            assert!(
+                liquidity > 0 || tokensOwed0 > 0 || tokensOwed1 > 0,
+                "nothing to split"
+            );
+            position.liquidity -= liquidity;
+            position.tokensOwed0 -= tokensOwed0;
+            position.tokensOwed1 -= tokensOwed1;
+            self._positions.insert(tokenId.clone(), &position);
+
+            self._nextId = self._nextId + 1;
+            let newTokenId = Id::U128(self._nextId);
+            self._mint_to(recipient, newTokenId.clone()).expect("mint error!");
+            self._positions.insert(
+                newTokenId.clone(),
+                &Position {
+                    nonce: 0,
+                    operator: ADDRESS0.into(),
+                    poolId: position.poolId,
+                    tickLower: position.tickLower,
+                    tickUpper: position.tickUpper,
+                    liquidity,
+                    feeGrowthInside0LastX128: position.feeGrowthInside0LastX128,
+                    feeGrowthInside1LastX128: position.feeGrowthInside1LastX128,
+                    tokensOwed0,
+                    tokensOwed1,
+                },
+            );
+            self.env().emit_event(Split {
+                tokenId,
+                newTokenId: newTokenId.clone(),
+                liquidity,
+                tokensOwed0,
+                tokensOwed1,
+            });
+            (newTokenId, liquidity)
+        }
+
+        #[ink(message)]
+        fn merge(&mut self, tokenIds: Vec<Id>) -> u128 {
+            assert!(tokenIds.len() > 1, "nothing to merge");
+            let msg_sender = ink_env::caller::<DefaultEnvironment>();
+            let tokenId = tokenIds[0].clone();
+            let owner: Address = self.owner_of(tokenId.clone()).expect("tokenId not exist!");
+            let mut positions: Vec<Position> = Vec::new();
+            for (i, id) in tokenIds.iter().enumerate() {
+                assert!(!tokenIds[..i].contains(id), "duplicate tokenId");
+                assert!(
+                    self._isApprovedOrOwner(msg_sender, id.clone())
+                        || self._hasPermission(msg_sender, id.clone(), PERMISSION_TRANSFER),
+                    "Not approved"
+                );
+                assert!(self.owner_of(id.clone()).unwrap() == owner, "owners differ");
+                let position: Position = self._positions.get(id.clone()).expect("tokenId not exist!");
+                if i > 0 {
+                    assert!(
+                        position.poolId == positions[0].poolId
+                            && position.tickLower == positions[0].tickLower
+                            && position.tickUpper == positions[0].tickUpper,
+                        "positions differ"
+                    );
+                }
+                positions.push(position);
+            }
+
+            let (tickLower, tickUpper) = (positions[0].tickLower, positions[0].tickUpper);
+            let poolKey: PoolAddress::PoolKey =
+                self._poolIdToPoolKey.get(positions[0].poolId).expect("pooId not exist!");
+            let factoryAddress = self.immutable_state.factory;
+            let pool: Address =
+                FactoryRef::get_pool(&factoryAddress, poolKey.fee, poolKey.token0, poolKey.token1);
+            let address_this = ink_env::account_id::<DefaultEnvironment>();
+            // bring the fees of the positions up to date, so that the merged position starts from one snapshot
+            let liquidity: u128 = positions.iter().map(|position| position.liquidity).sum();
+            if liquidity > 0 {
+                PoolActionRef::burn(&pool, tickLower, tickUpper, 0, None);
+            }
+            let position_info = PoolActionRef::positions(&pool, address_this, tickLower, tickUpper, None);
+            let (mut tokensOwed0, mut tokensOwed1) = (0u128, 0u128);
+            for position in positions.iter() {
+                tokensOwed0 += position.tokensOwed0
+                    + FullMath::mulDiv(
+                        position_info.feeGrowthInside0LastX128.value - position.feeGrowthInside0LastX128.value,
+                        U256::from(position.liquidity),
+                        U256::from(FixedPoint128::Q128),
+                    )
+                    .as_u128();
+                tokensOwed1 += position.tokensOwed1
+                    + FullMath::mulDiv(
+                        position_info.feeGrowthInside1LastX128.value - position.feeGrowthInside1LastX128.value,
+                        U256::from(position.liquidity),
+                        U256::from(FixedPoint128::Q128),
+                    )
+                    .as_u128();
+            }
+
+            let mut merged: Position = positions.swap_remove(0);
+            merged.liquidity = liquidity;
+            merged.feeGrowthInside0LastX128 = position_info.feeGrowthInside0LastX128;
+            merged.feeGrowthInside1LastX128 = position_info.feeGrowthInside1LastX128;
+            merged.tokensOwed0 = tokensOwed0;
+            merged.tokensOwed1 = tokensOwed1;
+            self._positions.insert(tokenId.clone(), &merged);
+            for id in tokenIds[1..].iter() {
+                self._positions.remove(id.clone());
+                self._burn_from(owner, id.clone()).expect("burn token failed");
+            }
+            self.env().emit_event(Merge {
+                tokenId,
+                mergedTokenIds: tokenIds[1..].to_vec(),
+                liquidity,
+            });
+            liquidity
         }
 
         #[ink(message)]
@@ -1054,6 +1595,78 @@ pub mod position_manager {
         amount1: U256,
     }
 
+    /// @notice Emitted when a position NFT is moved to a new range
+    /// @param tokenId The ID of the token that was burned
+    /// @param newTokenId The ID of the token minted for the new range
+    /// @param liquidity The liquidity of the new position
+    /// @param amount0 The amount of token0 in the new position
+    /// @param amount1 The amount of token1 in the new position
+    #[ink(event)]
+    pub struct Rebalance {
+        #[ink(topic)]
+        tokenId: Id,
+        #[ink(topic)]
+        newTokenId: Id,
+        liquidity: u128,
+        amount0: U256,
+        amount1: U256,
+    }
+
+    /// @notice Emitted when part of a position NFT is moved to a new one
+    /// @param tokenId The ID of the token that was split
+    /// @param newTokenId The ID of the token minted for the part
+    /// @param liquidity The liquidity moved to the new position
+    /// @param tokensOwed0 The token0 owed moved to the new position
+    /// @param tokensOwed1 The token1 owed moved to the new position
+    #[ink(event)]
+    pub struct Split {
+        #[ink(topic)]
+        tokenId: Id,
+        #[ink(topic)]
+        newTokenId: Id,
+        liquidity: u128,
+        tokensOwed0: u128,
+        tokensOwed1: u128,
+    }
+
+    /// @notice Emitted when position NFTs are merged into one
+    /// @param tokenId The ID of the token that keeps the merged position
+    /// @param mergedTokenIds The IDs of the tokens merged into it, which were burned
+    /// @param liquidity The liquidity of the merged position
+    #[ink(event)]
+    pub struct Merge {
+        #[ink(topic)]
+        tokenId: Id,
+        mergedTokenIds: Vec<Id>,
+        liquidity: u128,
+    }
+
+    /// @notice Emitted when scoped permissions on a position NFT are granted
+    /// @param permissions The permissions the grantee has after the grant
+    #[ink(event)]
+    pub struct PermissionsGranted {
+        #[ink(topic)]
+        tokenId: Id,
+        #[ink(topic)]
+        owner: Address,
+        #[ink(topic)]
+        grantee: Address,
+        permissions: u8,
+    }
+
+    /// @notice Emitted when scoped permissions on a position NFT are revoked
+    /// @param permissions The permissions the grantee has left after the revocation
+    #[ink(event)]
+    pub struct PermissionsRevoked {
+        #[ink(topic)]
+        tokenId: Id,
+        #[ink(topic)]
+        owner: Address,
+        #[ink(topic)]
+        grantee: Address,
+        permissions: u8,
+    }
+
     #[ink(event)]
     pub struct TestEvent {
         #[ink(topic)]
@@ -1092,11 +1705,66 @@ pub mod position_manager {
                     self._positions.insert(id.clone(),&position);
                 }
                 self._emit_approval_event(caller, to, Some(id.clone()), approved);
+            } else {
+                if approved && caller == to {
+                    return Err(PSP34Error::SelfApprove)
+                }
+                if approved {
+                    self.psp34.operator_approvals.insert((caller, to, Option::<Id>::None), &());
+                } else {
+                    self.psp34.operator_approvals.remove((caller, to, Option::<Id>::None));
+                }
+                self._emit_approval_event(caller, to, None, approved);
             }
             
             Ok(())
         }
 
+        /// @dev The approved operator and the permissions of a position do not carry over to its next owner, who must be
+        /// allowed to provide liquidity if the pool of the position is permissioned
+        fn _before_token_transfer(
+            &mut self,
+            from: Option<&AccountId>,
+            to: Option<&AccountId>,
+            id: &Id,
+        ) -> Result<(), PSP34Error> {
+            if from.is_some() {
+                let epoch = self._permissionEpochs.get(id.clone()).unwrap_or(0);
+                self._permissionEpochs.insert(id.clone(), &epoch.wrapping_add(1));
+                if let Some(mut position) = self._positions.get(id.clone()) {
+                    if position.operator != ADDRESS0.into() {
+                        position.operator = ADDRESS0.into();
+                        self._positions.insert(id.clone(), &position);
+                    }
+                    if let Some(to) = to {
+                        let poolKey = self._poolIdToPoolKey.get(position.poolId).expect("pool key not exist");
+                        let pool = FactoryRef::get_pool(
+                            &self.immutable_state.factory,
+                            poolKey.fee,
+                            poolKey.token0,
+                            poolKey.token1,
+                        );
+                        self._checkLiquidityProvider(pool, *to);
+                    }
+                }
+            }
+            Ok(())
+        }
+
+        /// @dev An account may transfer a token it is the approved operator of, or was granted the transfer permission on
+        fn _allowance(&self, owner: &AccountId, operator: &AccountId, id: &Option<&Id>) -> bool {
+            if self.psp34.operator_approvals.get((*owner, *operator, Option::<Id>::None)).is_some() {
+                return true
+            }
+            match id {
+                Some(id) => {
+                    self._positions.get((*id).clone()).map_or(false, |position| position.operator == *operator)
+                        || self._hasPermission(*operator, (*id).clone(), PERMISSION_TRANSFER)
+                }
+                None => false,
+            }
+        }
+
         fn _emit_transfer_event(&self, from: Option<AccountId>, to: Option<AccountId>, id: Id) {
             self.env().emit_event(Transfer { from, to, id });
         }
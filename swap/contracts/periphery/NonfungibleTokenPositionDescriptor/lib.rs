@@ -14,33 +14,101 @@ pub mod PositionDescriptor {
     use crabswap::traits::core::factory::FactoryRef;
     use libs::periphery::{TokenRatioSortOrder, NFTDescriptor};
     use crabswap::traits::core::pool_action::{PoolActionRef, Slot0};
+    use ink_lang::codegen::EmitEvent;
+    use ink_lang::codegen::Env;
     use ink_prelude::vec::Vec;
     use ink_prelude::vec;
+    use ink_prelude::format;
+    use ink_primitives::KeyPtr;
+    use ink_storage::traits::{PackedLayout, SpreadLayout};
+    use ink_storage::Mapping;
+    use openbrush::contracts::ownable::*;
+    use openbrush::modifiers;
 
-    // TODO decode the hex to accountId
-    static DAI:&'static str = "e4678b676433e1f6f3f6a77730f4ede1737ad4b828b229def437e3e2ab46eb01";
-    static USDC:&'static str = "e4678b676433e1f6f3f6a77730f4ede1737ad4b828b229def437e3e2ab46eb01";
-    static USDT:&'static str = "e4678b676433e1f6f3f6a77730f4ede1737ad4b828b229def437e3e2ab46eb01";
-    static TBTC:&'static str = "e4678b676433e1f6f3f6a77730f4ede1737ad4b828b229def437e3e2ab46eb01";
-    static WBTC:&'static str = "e4678b676433e1f6f3f6a77730f4ede1737ad4b828b229def437e3e2ab46eb01";
-    static CHAIN_ID:u8 = 1;
+    // address private constant DAI = 0x6B175474E89094C44Da98b954EedeAC495271d0F;
     // address private constant USDC = 0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48;
     // address private constant USDT = 0xdAC17F958D2ee523a2206206994597C13D831ec7;
     // address private constant TBTC = 0x8dAEBADE922dF735c38C80C7eBD708Af50815fAa;
     // address private constant WBTC = 0x2260FAC5E5542a773Aa44fBCfeDf7C193bc2C599;
+    // The mainnet addresses above are replaced by a registry the owner fills in for the chain the descriptor is
+    // deployed on, with priorities from TokenRatioSortOrder.
+
+    /// @notice How tokenURI describes a position
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode, SpreadLayout, PackedLayout)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout))]
+    pub enum DescriptorMode {
+        /// @dev A data URI with the name, description and the on-chain SVG image of the position
+        Svg,
+        /// @dev The base URI followed by the token ID, for metadata served off-chain
+        BaseUri,
+        /// @dev A data URI with compact JSON attributes of the position and no image
+        Json,
+    }
+
+    impl Default for DescriptorMode {
+        fn default() -> Self {
+            DescriptorMode::Svg
+        }
+    }
+
+    impl SpreadAllocate for DescriptorMode {
+        fn allocate_spread(ptr: &mut KeyPtr) -> Self {
+            ptr.advance_by(<Self as SpreadLayout>::FOOTPRINT);
+            Self::default()
+        }
+    }
 
     #[ink(storage)]
-    #[derive(SpreadAllocate)]
+    #[derive(SpreadAllocate, OwnableStorage)]
     pub struct PositionDescriptor {
+        #[OwnableStorageField]
+        ownable: OwnableData,
         pub WETH9:Address,
         /// @dev A null-terminated string
-        pub nativeCurrencyLabelBytes:Vec<u8>
+        pub nativeCurrencyLabelBytes:Vec<u8>,
+        /// @dev The priority of a token to be the quote token of the displayed price, see TokenRatioSortOrder
+        tokenRatioPriorities: Mapping<Address, Int256>,
+        mode: DescriptorMode,
+        baseURI: String,
+    }
+
+    /// @notice Emitted when the owner sets the ratio priority of a token
+    #[ink(event)]
+    pub struct TokenRatioPrioritySet {
+        #[ink(topic)]
+        token: Address,
+        priority: Int256,
+    }
+
+    /// @notice Emitted when the owner removes a token from the ratio priority registry
+    #[ink(event)]
+    pub struct TokenRatioPriorityRemoved {
+        #[ink(topic)]
+        token: Address,
     }
 
+    /// @notice Emitted when the owner changes how positions are described
+    #[ink(event)]
+    pub struct DescriptorModeSet {
+        mode: DescriptorMode,
+    }
+
+    /// @notice Emitted when the owner changes the base URI of the BaseUri mode
+    #[ink(event)]
+    pub struct BaseURISet {
+        baseURI: String,
+    }
+
+    impl Ownable for PositionDescriptor {}
+
     impl Descriptor for PositionDescriptor {
         //positionManager is NonfungiblePositionManager address
         #[ink(message)]
         fn tokenURI(&self, positionManager: Address, tokenId: u128) -> String {
+            if self.mode == DescriptorMode::BaseUri {
+                return format!("{}{}", self.baseURI, tokenId);
+            }
+
             // (, , address token0, address token1, uint24 fee, int24 tickLower, int24 tickUpper, , , , , ) =
             //     positionManager.positions(tokenId);
             let (_, _, token0, token1, fee, tickLower, tickUpper, _, _, _, _, _) =
@@ -57,7 +125,7 @@ pub mod PositionDescriptor {
             let pool:Address = FactoryRef::get_pool(&factory_address,fee,token0,token1);
 
             // bool _flipRatio = flipRatio(token0, token1, ChainId.get());
-            let _flipRatio:bool = self.flipRatio(token0, token1, CHAIN_ID);
+            let _flipRatio:bool = self.flipRatio(token0, token1);
             // address quoteTokenAddress = !_flipRatio ? token1 : token0;
             let quoteTokenAddress:Address = if !_flipRatio{
                 token1
@@ -74,6 +142,21 @@ pub mod PositionDescriptor {
             let slot0:Slot0 = PoolActionRef::getSlot0(&pool);
             let tick:Int24 = slot0.tick;
 
+            if self.mode == DescriptorMode::Json {
+                return NFTDescriptor::constructTokenMetadata(
+                    NFTDescriptor::ConstructTokenMetadataParams{
+                        tokenId: U256::from(tokenId),
+                        quoteTokenSymbol: self.tokenSymbol(quoteTokenAddress),
+                        baseTokenSymbol: self.tokenSymbol(baseTokenAddress),
+                        tickLower: tickLower,
+                        tickUpper: tickUpper,
+                        tickCurrent: tick,
+                        fee: fee,
+                        poolAddress: pool
+                    }
+                );
+            }
+
             // return
             //     NFTDescriptor.constructTokenURI(
             //         NFTDescriptor.ConstructTokenURIParams({
@@ -102,16 +185,8 @@ pub mod PositionDescriptor {
                         tokenId: U256::from(tokenId),
                         quoteTokenAddress: quoteTokenAddress,
                         baseTokenAddress: baseTokenAddress,
-                        quoteTokenSymbol: if quoteTokenAddress == self.WETH9{
-                            self.nativeCurrencyLabel()
-                        }else{
-                            PSP22MetadataRef::token_symbol(&quoteTokenAddress).expect("option errro!")
-                        },
-                        baseTokenSymbol: if baseTokenAddress == self.WETH9{
-                            self.nativeCurrencyLabel()
-                        }else{
-                            PSP22MetadataRef::token_symbol(&baseTokenAddress).expect("option errro!")
-                        },
+                        quoteTokenSymbol: self.tokenSymbol(quoteTokenAddress),
+                        baseTokenSymbol: self.tokenSymbol(baseTokenAddress),
                         quoteTokenDecimals: PSP22MetadataRef::token_decimals(&quoteTokenAddress),
                         baseTokenDecimals: PSP22MetadataRef::token_decimals(&baseTokenAddress),
                         flipRatio: _flipRatio,
@@ -129,21 +204,93 @@ pub mod PositionDescriptor {
     impl PositionDescriptor {
         #[ink(constructor)]
         // constructor(address _WETH9, bytes32 _nativeCurrencyLabelBytes) {
-        pub fn new(_WETH9: AccountId, mut _nativeCurrencyLabelBytes: String) -> Self {
+        /// @param tokenRatioPriorities The initial (token, priority) entries of the ratio priority registry
+        pub fn new(_WETH9: AccountId, mut _nativeCurrencyLabelBytes: String, tokenRatioPriorities: Vec<(Address, Int256)>) -> Self {
             ink_lang::codegen::initialize_contract(|instance: &mut PositionDescriptor| {
+                let caller = instance.env().caller();
+                instance._init_with_owner(caller);
                 instance.WETH9 = _WETH9;
                 unsafe{
                 instance.nativeCurrencyLabelBytes= _nativeCurrencyLabelBytes.as_mut_vec().clone();
             }
+                for (token, priority) in tokenRatioPriorities {
+                    instance.tokenRatioPriorities.insert(token, &priority);
+                    instance.env().emit_event(TokenRatioPrioritySet { token, priority });
+                }
             })
         }
 
+        /// @notice Sets the priority of a token to be the quote token of the displayed price
+        /// @dev Overrides the default priority of WETH9
+        /// @param priority The priority, e.g. TokenRatioSortOrder::NUMERATOR_MOST for the main stablecoin
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn setTokenRatioPriority(&mut self, token: Address, priority: Int256) -> Result<(), OwnableError> {
+            self.tokenRatioPriorities.insert(token, &priority);
+            self.env().emit_event(TokenRatioPrioritySet { token, priority });
+            Ok(())
+        }
+
+        /// @notice Removes a token from the ratio priority registry, which gives it the default priority again
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn removeTokenRatioPriority(&mut self, token: Address) -> Result<(), OwnableError> {
+            assert!(self.tokenRatioPriorities.get(token).is_some(), "token not registered");
+            self.tokenRatioPriorities.remove(token);
+            self.env().emit_event(TokenRatioPriorityRemoved { token });
+            Ok(())
+        }
+
+        /// @notice Sets how tokenURI describes positions
+        /// @dev The BaseUri mode needs a base URI to be set first
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn setMode(&mut self, mode: DescriptorMode) -> Result<(), OwnableError> {
+            assert!(
+                mode != DescriptorMode::BaseUri || !self.baseURI.is_empty(),
+                "base URI not set"
+            );
+            self.mode = mode;
+            self.env().emit_event(DescriptorModeSet { mode });
+            Ok(())
+        }
+
+        /// @notice Sets the base URI the token ID is appended to in the BaseUri mode
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn setBaseURI(&mut self, baseURI: String) -> Result<(), OwnableError> {
+            assert!(
+                !baseURI.is_empty() || self.mode != DescriptorMode::BaseUri,
+                "base URI in use"
+            );
+            self.baseURI = baseURI.clone();
+            self.env().emit_event(BaseURISet { baseURI });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn getMode(&self) -> DescriptorMode {
+            self.mode
+        }
+
+        #[ink(message)]
+        pub fn getBaseURI(&self) -> String {
+            self.baseURI.clone()
+        }
+
+        fn tokenSymbol(&self, token: Address) -> String {
+            if token == self.WETH9 {
+                self.nativeCurrencyLabel()
+            } else {
+                PSP22MetadataRef::token_symbol(&token).expect("option errro!")
+            }
+        }
+
         fn flipRatio(&self,
              token0:Address,
-             token1:Address,
-             chainId:u8
+             token1:Address
         ) -> bool {
-            return self.tokenRatioPriority(token0, chainId) > self.tokenRatioPriority(token1, chainId);
+            return self.tokenRatioPriority(token0) > self.tokenRatioPriority(token1);
         }
 
         /// @notice Returns the native currency label as a string
@@ -159,26 +306,183 @@ pub mod PositionDescriptor {
             return String::from_utf8(b).expect("error!");
         }
 
-        pub fn tokenRatioPriority(&self,token:Address, chainId:u8) -> Int256 {
+        /// @notice Returns the priority of a token to be the quote token of the displayed price
+        /// @dev Tokens not in the registry have priority 0, except WETH9 which defaults to DENOMINATOR
+        #[ink(message)]
+        pub fn tokenRatioPriority(&self, token: Address) -> Int256 {
+            if let Some(priority) = self.tokenRatioPriorities.get(token) {
+                return priority;
+            }
             if token == self.WETH9 {
                 return TokenRatioSortOrder::DENOMINATOR;
             }
-            if chainId == 1 {
-                if token == TokenRatioSortOrder::trans_hex_2_account(USDC) {
-                    return TokenRatioSortOrder::NUMERATOR_MOST;
-                } else if token == TokenRatioSortOrder::trans_hex_2_account(USDT) {
-                    return TokenRatioSortOrder::NUMERATOR_MORE;
-                } else if token == TokenRatioSortOrder::trans_hex_2_account(DAI) {
-                    return TokenRatioSortOrder::NUMERATOR;
-                } else if token == TokenRatioSortOrder::trans_hex_2_account(TBTC) {
-                    return TokenRatioSortOrder::DENOMINATOR_MORE;
-                } else if token == TokenRatioSortOrder::trans_hex_2_account(WBTC) {
-                    return TokenRatioSortOrder::DENOMINATOR_MOST;
-                } else {
-                    return 0;
-                }
-            }
             return 0;
         }
     }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use ink_env::DefaultEnvironment;
+        use ink_lang as ink;
+
+        fn default_accounts() -> ink_env::test::DefaultAccounts<DefaultEnvironment> {
+            ink_env::test::default_accounts::<DefaultEnvironment>()
+        }
+
+        fn set_next_caller(caller: AccountId) {
+            ink_env::test::set_caller::<DefaultEnvironment>(caller);
+        }
+
+        fn priority_events() -> Vec<(Address, Int256)> {
+            ink_env::test::recorded_events()
+                .filter_map(|event| {
+                    match <Event as scale::Decode>::decode(&mut &event.data[..]).expect("invalid event") {
+                        Event::TokenRatioPrioritySet(set) => Some((set.token, set.priority)),
+                        _ => None,
+                    }
+                })
+                .collect()
+        }
+
+        /// @dev A descriptor owned by alice, with django as WETH9 and no registered tokens
+        fn setup() -> PositionDescriptor {
+            let accounts = default_accounts();
+            set_next_caller(accounts.alice);
+            PositionDescriptor::new(accounts.django, String::from("ETH"), Vec::new())
+        }
+
+        #[ink::test]
+        fn constructor_seeds_the_registry_and_emits_the_entries() {
+            let accounts = default_accounts();
+            set_next_caller(accounts.alice);
+            let seeded = vec![
+                (accounts.bob, TokenRatioSortOrder::NUMERATOR_MOST),
+                (accounts.charlie, TokenRatioSortOrder::DENOMINATOR_MORE),
+            ];
+            let descriptor = PositionDescriptor::new(accounts.django, String::from("ETH"), seeded.clone());
+            assert_eq!(descriptor.tokenRatioPriority(accounts.bob), TokenRatioSortOrder::NUMERATOR_MOST);
+            assert_eq!(descriptor.tokenRatioPriority(accounts.charlie), TokenRatioSortOrder::DENOMINATOR_MORE);
+            assert_eq!(priority_events(), seeded);
+        }
+
+        #[ink::test]
+        fn weth9_defaults_to_the_denominator() {
+            let accounts = default_accounts();
+            let descriptor = setup();
+            assert_eq!(descriptor.tokenRatioPriority(accounts.django), TokenRatioSortOrder::DENOMINATOR);
+            assert_eq!(descriptor.tokenRatioPriority(accounts.bob), 0);
+        }
+
+        #[ink::test]
+        fn owner_sets_and_removes_priorities() {
+            let accounts = default_accounts();
+            let mut descriptor = setup();
+            assert_eq!(descriptor.setTokenRatioPriority(accounts.django, TokenRatioSortOrder::NUMERATOR), Ok(()));
+            assert_eq!(descriptor.tokenRatioPriority(accounts.django), TokenRatioSortOrder::NUMERATOR);
+            assert_eq!(priority_events(), vec![(accounts.django, TokenRatioSortOrder::NUMERATOR)]);
+
+            // removing WETH9 gives it its default priority again
+            assert_eq!(descriptor.removeTokenRatioPriority(accounts.django), Ok(()));
+            assert_eq!(descriptor.tokenRatioPriority(accounts.django), TokenRatioSortOrder::DENOMINATOR);
+        }
+
+        #[ink::test]
+        fn only_owner_sets_and_removes_priorities() {
+            let accounts = default_accounts();
+            let mut descriptor = setup();
+            assert_eq!(descriptor.setTokenRatioPriority(accounts.bob, TokenRatioSortOrder::NUMERATOR), Ok(()));
+            set_next_caller(accounts.bob);
+            assert_eq!(
+                descriptor.setTokenRatioPriority(accounts.bob, TokenRatioSortOrder::NUMERATOR_MOST),
+                Err(OwnableError::CallerIsNotOwner)
+            );
+            assert_eq!(descriptor.removeTokenRatioPriority(accounts.bob), Err(OwnableError::CallerIsNotOwner));
+            assert_eq!(descriptor.tokenRatioPriority(accounts.bob), TokenRatioSortOrder::NUMERATOR);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "token not registered")]
+        fn remove_rejects_an_unregistered_token() {
+            let accounts = default_accounts();
+            let mut descriptor = setup();
+            let _ = descriptor.removeTokenRatioPriority(accounts.bob);
+        }
+
+        #[ink::test]
+        fn base_uri_mode_appends_the_token_id() {
+            let accounts = default_accounts();
+            let mut descriptor = setup();
+            assert_eq!(descriptor.getMode(), DescriptorMode::Svg);
+            assert_eq!(descriptor.setBaseURI(String::from("https://positions.example/")), Ok(()));
+            assert_eq!(descriptor.setMode(DescriptorMode::BaseUri), Ok(()));
+            assert_eq!(descriptor.getMode(), DescriptorMode::BaseUri);
+            assert_eq!(descriptor.getBaseURI(), String::from("https://positions.example/"));
+            // no call to the position manager is made in this mode
+            assert_eq!(descriptor.tokenURI(accounts.eve, 7), String::from("https://positions.example/7"));
+
+            assert_eq!(descriptor.setBaseURI(String::from("ipfs://positions/")), Ok(()));
+            assert_eq!(descriptor.tokenURI(accounts.eve, 42), String::from("ipfs://positions/42"));
+            // the mode and base URI events
+            assert_eq!(ink_env::test::recorded_events().count(), 3);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "base URI not set")]
+        fn base_uri_mode_needs_a_base_uri() {
+            let mut descriptor = setup();
+            let _ = descriptor.setMode(DescriptorMode::BaseUri);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "base URI in use")]
+        fn base_uri_can_not_be_cleared_in_base_uri_mode() {
+            let mut descriptor = setup();
+            assert_eq!(descriptor.setBaseURI(String::from("https://positions.example/")), Ok(()));
+            assert_eq!(descriptor.setMode(DescriptorMode::BaseUri), Ok(()));
+            let _ = descriptor.setBaseURI(String::new());
+        }
+
+        #[ink::test]
+        fn base_uri_can_be_cleared_in_other_modes() {
+            let mut descriptor = setup();
+            assert_eq!(descriptor.setBaseURI(String::from("https://positions.example/")), Ok(()));
+            assert_eq!(descriptor.setMode(DescriptorMode::Json), Ok(()));
+            assert_eq!(descriptor.setBaseURI(String::new()), Ok(()));
+            assert_eq!(descriptor.getBaseURI(), String::new());
+        }
+
+        #[ink::test]
+        fn only_owner_sets_the_mode_and_base_uri() {
+            let accounts = default_accounts();
+            let mut descriptor = setup();
+            set_next_caller(accounts.bob);
+            assert_eq!(
+                descriptor.setBaseURI(String::from("https://positions.example/")),
+                Err(OwnableError::CallerIsNotOwner)
+            );
+            assert_eq!(descriptor.setMode(DescriptorMode::Json), Err(OwnableError::CallerIsNotOwner));
+            assert_eq!(descriptor.getMode(), DescriptorMode::Svg);
+            assert_eq!(descriptor.getBaseURI(), String::new());
+        }
+
+        #[ink::test]
+        fn flip_ratio_quotes_the_token_with_the_higher_priority() {
+            let accounts = default_accounts();
+            let mut descriptor = setup();
+            // an unregistered token is the quote against WETH9 on either side
+            assert!(descriptor.flipRatio(accounts.bob, accounts.django));
+            assert!(!descriptor.flipRatio(accounts.django, accounts.bob));
+
+            // a stablecoin is the quote against WETH9 on either side
+            assert_eq!(descriptor.setTokenRatioPriority(accounts.charlie, TokenRatioSortOrder::NUMERATOR_MOST), Ok(()));
+            assert!(descriptor.flipRatio(accounts.charlie, accounts.django));
+            assert!(!descriptor.flipRatio(accounts.django, accounts.charlie));
+
+            // equal priorities keep token1 as the quote
+            assert_eq!(descriptor.setTokenRatioPriority(accounts.bob, TokenRatioSortOrder::NUMERATOR_MOST), Ok(()));
+            assert!(!descriptor.flipRatio(accounts.bob, accounts.charlie));
+            assert!(!descriptor.flipRatio(accounts.charlie, accounts.bob));
+        }
+    }
 }
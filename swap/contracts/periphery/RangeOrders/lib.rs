@@ -0,0 +1,430 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#![feature(min_specialization)]
+
+/// @title Range orders on position NFTs
+/// @notice A range order is a one-sided position one tick spacing wide. Once the price has moved through the whole
+/// range the position holds only the other token, which makes it a filled limit order. The contract holds the
+/// position NFT until then, so any keeper can close the filled position and deliver the proceeds to the order owner,
+/// keeping a tip for the work. The owner can cancel the order as long as it has not been filled.
+#[openbrush::contract]
+pub mod range_orders {
+    use crabswap::traits::core::factory::FactoryRef;
+    use crabswap::traits::core::pool_action::PoolActionRef;
+    use crabswap::traits::periphery::position_manager::PositionManagerRef;
+    use ink_env::CallFlags;
+    use ink_lang::codegen::EmitEvent;
+    use ink_lang::codegen::Env;
+    use ink_prelude::vec;
+    use ink_prelude::vec::Vec;
+    use ink_storage::traits::{PackedLayout, SpreadAllocate, SpreadLayout};
+    use ink_storage::Mapping;
+    use openbrush::contracts::psp22::*;
+    use openbrush::contracts::psp34::*;
+    use primitives::{Address, Int24, Uint24, ADDRESS0, U256};
+    use scale::{Decode, Encode};
+
+    // the largest keeper tip, 10%
+    pub const MAX_KEEPER_TIP_BPS: u16 = 1000;
+
+    #[derive(Debug, Default, Clone, PartialEq, Eq, Encode, Decode, SpreadLayout, PackedLayout)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout))]
+    pub struct Order {
+        pub owner: Address,
+        pub pool: Address,
+        pub token0: Address,
+        pub token1: Address,
+        // true when selling token0 for token1, the range is then above the price at placement
+        pub zero_for_one: bool,
+        pub tick_lower: Int24,
+        pub tick_upper: Int24,
+        pub liquidity: u128,
+    }
+
+    #[ink(storage)]
+    #[derive(SpreadAllocate)]
+    pub struct RangeOrdersContract {
+        factory: Address,
+        nonfungible_position_manager: Address,
+        // the share of the proceeds paid to the keeper executing an order, in basis points
+        keeper_tip_bps: u16,
+        // by position token id
+        orders: Mapping<Id, Order>,
+    }
+
+    /// @notice Emitted when an order is placed
+    /// @param amount_in The amount of the sold token deposited into the position
+    #[ink(event)]
+    pub struct OrderPlaced {
+        #[ink(topic)]
+        token_id: Id,
+        #[ink(topic)]
+        owner: Address,
+        #[ink(topic)]
+        pool: Address,
+        zero_for_one: bool,
+        tick_lower: Int24,
+        tick_upper: Int24,
+        amount_in: u128,
+    }
+
+    /// @notice Emitted when a keeper closes a filled order
+    /// @param amount0 The token0 delivered to the owner
+    /// @param amount1 The token1 delivered to the owner
+    /// @param tip0 The token0 kept by the keeper
+    /// @param tip1 The token1 kept by the keeper
+    #[ink(event)]
+    pub struct OrderExecuted {
+        #[ink(topic)]
+        token_id: Id,
+        #[ink(topic)]
+        owner: Address,
+        #[ink(topic)]
+        keeper: Address,
+        amount0: u128,
+        amount1: u128,
+        tip0: u128,
+        tip1: u128,
+    }
+
+    /// @notice Emitted when the owner cancels an order that was not filled
+    #[ink(event)]
+    pub struct OrderCancelled {
+        #[ink(topic)]
+        token_id: Id,
+        #[ink(topic)]
+        owner: Address,
+        amount0: u128,
+        amount1: u128,
+    }
+
+    impl PSP22Receiver for RangeOrdersContract {
+        #[ink(message)]
+        fn before_received(
+            &mut self,
+            _operator: AccountId,
+            _from: AccountId,
+            _value: Balance,
+            _data: Vec<u8>,
+        ) -> Result<(), PSP22ReceiverError> {
+            Ok(())
+        }
+    }
+
+    impl PSP34Receiver for RangeOrdersContract {
+        #[ink(message)]
+        fn before_received(
+            &mut self,
+            _operator: AccountId,
+            _from: AccountId,
+            _id: Id,
+            _data: Vec<u8>,
+        ) -> Result<(), PSP34ReceiverError> {
+            assert!(
+                self.env().caller() == self.nonfungible_position_manager,
+                "not a position nft"
+            );
+            Ok(())
+        }
+    }
+
+    impl RangeOrdersContract {
+        /// @param keeper_tip_bps The share of the proceeds paid to keepers, at most MAX_KEEPER_TIP_BPS
+        #[ink(constructor)]
+        pub fn new(factory: Address, nonfungible_position_manager: Address, keeper_tip_bps: u16) -> Self {
+            ink_lang::utils::initialize_contract(|instance: &mut Self| {
+                assert!(keeper_tip_bps <= MAX_KEEPER_TIP_BPS, "keeper tip too large");
+                instance.factory = factory;
+                instance.nonfungible_position_manager = nonfungible_position_manager;
+                instance.keeper_tip_bps = keeper_tip_bps;
+            })
+        }
+
+        /// @notice Places a range order one tick spacing wide starting at tick_lower
+        /// @dev The caller must have approved the contract to spend amount_in of the sold token. The part of
+        /// amount_in the position could not use is returned
+        /// @param zero_for_one Whether token0 is sold for token1, the range must then be above the current tick,
+        /// otherwise it must be below it
+        /// @param amount_in The amount of the sold token
+        /// @return token_id The id of the position NFT, which identifies the order
+        #[ink(message)]
+        pub fn place(
+            &mut self,
+            token0: Address,
+            token1: Address,
+            fee: Uint24,
+            tick_lower: Int24,
+            zero_for_one: bool,
+            amount_in: u128,
+            deadline: u64,
+        ) -> Id {
+            assert!(token0 < token1, "token0 must less than token1");
+            assert!(amount_in > 0, "amount must be positive");
+            let pool = FactoryRef::get_pool(&self.factory, fee, token0, token1);
+            assert!(pool != ADDRESS0.into(), "pool not exist");
+            let tick_spacing = PoolActionRef::get_tickspacing(&pool);
+            assert!(tick_lower % tick_spacing == 0, "tick not aligned to tick spacing");
+            let tick_upper = tick_lower + tick_spacing;
+            let tick = PoolActionRef::getSlot0(&pool).tick;
+            ensure_one_sided(zero_for_one, tick, tick_lower, tick_upper);
+
+            let owner = self.env().caller();
+            let address_this = self.env().account_id();
+            let token_in = if zero_for_one { token0 } else { token1 };
+            PSP22Ref::transfer_from(&token_in, owner, address_this, amount_in, vec![0u8])
+                .expect("token transfer error!");
+            PSP22Ref::approve(&token_in, self.nonfungible_position_manager, amount_in)
+                .expect("token approve error!");
+
+            let (amount0_desired, amount1_desired) = if zero_for_one {
+                (U256::from(amount_in), U256::zero())
+            } else {
+                (U256::zero(), U256::from(amount_in))
+            };
+            // the manager mints the NFT to this contract, which reenters it through PSP34Receiver
+            let (token_id, liquidity, amount0, amount1) = PositionManagerRef::mint_builder(
+                &self.nonfungible_position_manager,
+                token0,
+                token1,
+                fee,
+                tick_lower,
+                tick_upper,
+                amount0_desired,
+                amount1_desired,
+                U256::zero(),
+                U256::zero(),
+                address_this,
+                U256::from(deadline),
+            )
+            .call_flags(CallFlags::default().set_allow_reentry(true))
+            .fire()
+            .unwrap();
+            assert!(liquidity > 0, "amount too small");
+
+            let used = (if zero_for_one { amount0 } else { amount1 }).as_u128();
+            self._send(token_in, owner, amount_in.saturating_sub(used));
+
+            self.orders.insert(
+                &token_id,
+                &Order {
+                    owner,
+                    pool,
+                    token0,
+                    token1,
+                    zero_for_one,
+                    tick_lower,
+                    tick_upper,
+                    liquidity,
+                },
+            );
+            self.env().emit_event(OrderPlaced {
+                token_id: token_id.clone(),
+                owner,
+                pool,
+                zero_for_one,
+                tick_lower,
+                tick_upper,
+                amount_in: used,
+            });
+            token_id
+        }
+
+        /// @notice Closes a filled order and sends the proceeds to its owner, minus the keeper tip sent to the caller
+        /// @return amount0 The token0 sent to the owner
+        /// @return amount1 The token1 sent to the owner
+        #[ink(message)]
+        pub fn execute(&mut self, token_id: Id) -> (u128, u128) {
+            let order = self.orders.get(&token_id).expect("order not exist");
+            assert!(self.is_filled(token_id.clone()), "order not filled");
+            self.orders.remove(&token_id);
+
+            let address_this = self.env().account_id();
+            let (amount0, amount1) = self._close(token_id.clone(), order.liquidity, address_this);
+            let tip0 = keeper_tip(amount0, self.keeper_tip_bps);
+            let tip1 = keeper_tip(amount1, self.keeper_tip_bps);
+            let keeper = self.env().caller();
+            self._send(order.token0, order.owner, amount0 - tip0);
+            self._send(order.token1, order.owner, amount1 - tip1);
+            self._send(order.token0, keeper, tip0);
+            self._send(order.token1, keeper, tip1);
+
+            self.env().emit_event(OrderExecuted {
+                token_id,
+                owner: order.owner,
+                keeper,
+                amount0: amount0 - tip0,
+                amount1: amount1 - tip1,
+                tip0,
+                tip1,
+            });
+            (amount0 - tip0, amount1 - tip1)
+        }
+
+        /// @notice Cancels an order that has not been filled, sending the position's tokens back to the owner
+        #[ink(message)]
+        pub fn cancel(&mut self, token_id: Id) -> (u128, u128) {
+            let order = self.orders.get(&token_id).expect("order not exist");
+            assert!(order.owner == self.env().caller(), "only owner can cancel order");
+            assert!(!self.is_filled(token_id.clone()), "order already filled");
+            self.orders.remove(&token_id);
+
+            let (amount0, amount1) = self._close(token_id.clone(), order.liquidity, order.owner);
+            self.env().emit_event(OrderCancelled {
+                token_id,
+                owner: order.owner,
+                amount0,
+                amount1,
+            });
+            (amount0, amount1)
+        }
+
+        /// @notice Whether the price has moved through the whole range of the order
+        #[ink(message)]
+        pub fn is_filled(&self, token_id: Id) -> bool {
+            let order = self.orders.get(&token_id).expect("order not exist");
+            let tick = PoolActionRef::getSlot0(&order.pool).tick;
+            range_crossed(order.zero_for_one, tick, order.tick_lower, order.tick_upper)
+        }
+
+        #[ink(message)]
+        pub fn get_order(&self, token_id: Id) -> Option<Order> {
+            self.orders.get(&token_id)
+        }
+
+        #[ink(message)]
+        pub fn get_keeper_tip_bps(&self) -> u16 {
+            self.keeper_tip_bps
+        }
+
+        /// @dev Removes all liquidity of the position, collects everything it holds to the recipient and burns it
+        fn _close(&mut self, token_id: Id, liquidity: u128, recipient: Address) -> (u128, u128) {
+            PositionManagerRef::decreaseLiquidity(
+                &self.nonfungible_position_manager,
+                token_id.clone(),
+                liquidity,
+                U256::zero(),
+                U256::zero(),
+                self.env().block_timestamp(),
+            );
+            // the tokens may be delivered to this contract, which reenters it through PSP22Receiver
+            let (amount0, amount1) = PositionManagerRef::collect_builder(
+                &self.nonfungible_position_manager,
+                token_id.clone(),
+                recipient,
+                u128::MAX,
+                u128::MAX,
+            )
+            .call_flags(CallFlags::default().set_allow_reentry(true))
+            .fire()
+            .unwrap();
+            PositionManagerRef::burn(&self.nonfungible_position_manager, token_id);
+            (amount0.as_u128(), amount1.as_u128())
+        }
+
+        fn _send(&self, token: Address, to: Address, amount: u128) {
+            if amount > 0 {
+                PSP22Ref::transfer(&token, to, amount, vec![0u8]).expect("token transfer error!");
+            }
+        }
+    }
+    /// @dev The order must hold only the sold token when placed, so the range must be entirely on one side of the
+    /// current tick. A range containing the current tick would take both tokens
+    fn ensure_one_sided(zero_for_one: bool, tick: Int24, tick_lower: Int24, tick_upper: Int24) {
+        if zero_for_one {
+            assert!(tick < tick_lower, "range must be above the current tick");
+        } else {
+            assert!(tick >= tick_upper, "range must be below the current tick");
+        }
+    }
+
+    /// @dev Whether the price has moved through the whole range, so the position holds only the bought token. The
+    /// pool's liquidity is active in [tick_lower, tick_upper), so tick_upper is crossed when the tick reaches it
+    fn range_crossed(zero_for_one: bool, tick: Int24, tick_lower: Int24, tick_upper: Int24) -> bool {
+        if zero_for_one {
+            tick >= tick_upper
+        } else {
+            tick < tick_lower
+        }
+    }
+
+    /// @dev The keeper's share of an amount, rounded down in favour of the owner
+    fn keeper_tip(amount: u128, keeper_tip_bps: u16) -> u128 {
+        (U256::from(amount) * U256::from(keeper_tip_bps) / U256::from(10000)).as_u128()
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn one_sided_above_current_tick() {
+            ensure_one_sided(true, -1, 0, 10);
+            ensure_one_sided(true, -100, 0, 10);
+        }
+
+        #[test]
+        #[should_panic(expected = "range must be above the current tick")]
+        fn selling_token0_rejects_range_at_current_tick() {
+            ensure_one_sided(true, 0, 0, 10);
+        }
+
+        #[test]
+        #[should_panic(expected = "range must be above the current tick")]
+        fn selling_token0_rejects_range_containing_current_tick() {
+            ensure_one_sided(true, 5, 0, 10);
+        }
+
+        #[test]
+        fn one_sided_below_current_tick() {
+            ensure_one_sided(false, 10, 0, 10);
+            ensure_one_sided(false, 100, 0, 10);
+        }
+
+        #[test]
+        #[should_panic(expected = "range must be below the current tick")]
+        fn selling_token1_rejects_range_containing_current_tick() {
+            ensure_one_sided(false, 9, 0, 10);
+        }
+
+        #[test]
+        fn zero_for_one_is_filled_from_tick_upper() {
+            assert!(!range_crossed(true, 9, 0, 10));
+            assert!(range_crossed(true, 10, 0, 10));
+            assert!(range_crossed(true, 11, 0, 10));
+        }
+
+        #[test]
+        fn one_for_zero_is_filled_below_tick_lower() {
+            assert!(!range_crossed(false, 0, 0, 10));
+            assert!(range_crossed(false, -1, 0, 10));
+        }
+
+        #[test]
+        fn range_is_not_filled_when_placed() {
+            // a range that passes ensure_one_sided is not filled yet
+            for tick in [-20, -11, -1] {
+                ensure_one_sided(true, tick, 0, 10);
+                assert!(!range_crossed(true, tick, 0, 10));
+            }
+            for tick in [10, 11, 20] {
+                ensure_one_sided(false, tick, 0, 10);
+                assert!(!range_crossed(false, tick, 0, 10));
+            }
+        }
+
+        #[test]
+        fn keeper_tip_splits_proceeds() {
+            assert_eq!(keeper_tip(10000, 30), 30);
+            assert_eq!(keeper_tip(10000, 0), 0);
+            assert_eq!(keeper_tip(10000, MAX_KEEPER_TIP_BPS), 1000);
+            // rounded down, the owner keeps the remainder
+            assert_eq!(keeper_tip(333, 30), 0);
+            assert_eq!(keeper_tip(3334, 30), 10);
+        }
+
+        #[test]
+        fn keeper_tip_does_not_overflow() {
+            assert_eq!(keeper_tip(u128::MAX, 10000), u128::MAX);
+            assert_eq!(keeper_tip(u128::MAX, MAX_KEEPER_TIP_BPS), u128::MAX / 10);
+        }
+    }
+}
@@ -0,0 +1,553 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#![feature(min_specialization)]
+#![allow(non_snake_case)]
+
+/// @title Fungible liquidity for a managed price range
+/// @notice The vault owns one pool position on the range set by its manager and issues PSP22 shares of it.
+/// Depositors add liquidity in the current ratio of the range and get shares in proportion to the value added, against
+/// the value of the position, its owed fees and the vault's idle tokens at the pool price.
+/// Every deposit and withdrawal first collects the position's fees and adds them to its liquidity, so the fees accrue
+/// to the value of the shares. The manager, the owner of the vault, can move the whole position to a new range.
+/// @dev Fees that do not fit the ratio of the range stay in the vault and are paid out pro rata on withdrawal, until a
+/// later compounding or a rebalance can use them. The first deposit locks MINIMUM_SHARES in the vault itself, so the
+/// supply can not be made so small that tokens sent to the vault inflate a share beyond what later deposits can buy
+#[openbrush::contract]
+pub mod range_vault {
+    use crabswap::traits::core::factory::FactoryRef;
+    use crabswap::traits::core::pool_action::PoolActionRef;
+    use crabswap::traits::periphery::LiquidityManagement::{
+        liquiditymanagementtrait_external, AddLiquidityParams, LiquidityManagementTrait,
+    };
+    use crabswap::traits::periphery::swap_callback::{swapcallback_external, SwapCallback};
+    use ink_env::CallFlags;
+    use ink_lang::codegen::EmitEvent;
+    use ink_lang::codegen::Env;
+    use ink_prelude::string::String;
+    use ink_prelude::vec;
+    use ink_prelude::vec::Vec;
+    use ink_storage::traits::SpreadAllocate;
+    use libs::core::TickMath;
+    use libs::periphery::LiquidityAmounts;
+    use libs::swap::FullMath;
+    use openbrush::contracts::ownable::*;
+    use openbrush::contracts::psp22::extensions::metadata::*;
+    use openbrush::modifiers;
+    use primitives::{Address, Int24, Int256, Uint24, ADDRESS0, U160, U256};
+    use scale::Decode;
+
+    // the shares of the first deposit locked in the vault forever, see UniswapV2Pair#MINIMUM_LIQUIDITY
+    pub const MINIMUM_SHARES: u128 = 1000;
+
+    #[ink(storage)]
+    #[derive(Default, SpreadAllocate, PSP22Storage, PSP22MetadataStorage, OwnableStorage)]
+    pub struct RangeVaultContract {
+        #[PSP22StorageField]
+        psp22: PSP22Data,
+        #[PSP22MetadataStorageField]
+        metadata: PSP22MetadataData,
+        #[OwnableStorageField]
+        ownable: OwnableData,
+        pool: Address,
+        token0: Address,
+        token1: Address,
+        tick_spacing: Int24,
+        // the range of the vault's position
+        tick_lower: Int24,
+        tick_upper: Int24,
+    }
+
+    /// @notice Emitted when liquidity is added to the vault's position for shares
+    #[ink(event)]
+    pub struct Deposited {
+        #[ink(topic)]
+        sender: Address,
+        #[ink(topic)]
+        recipient: Address,
+        liquidity: u128,
+        shares: u128,
+        amount0: u128,
+        amount1: u128,
+    }
+
+    /// @notice Emitted when shares are redeemed for the tokens they represent
+    #[ink(event)]
+    pub struct Withdrawn {
+        #[ink(topic)]
+        sender: Address,
+        #[ink(topic)]
+        recipient: Address,
+        shares: u128,
+        amount0: u128,
+        amount1: u128,
+    }
+
+    /// @notice Emitted when the fees of the position are collected and added to its liquidity
+    /// @param fees0 The token0 fees collected
+    /// @param fees1 The token1 fees collected
+    /// @param liquidity The liquidity added from the vault's tokens
+    #[ink(event)]
+    pub struct Compounded {
+        fees0: u128,
+        fees1: u128,
+        liquidity: u128,
+    }
+
+    /// @notice Emitted when the manager moves the vault's liquidity to a new range
+    #[ink(event)]
+    pub struct Rebalanced {
+        tick_lower: Int24,
+        tick_upper: Int24,
+        liquidity: u128,
+    }
+
+    impl PSP22 for RangeVaultContract {}
+
+    impl PSP22Metadata for RangeVaultContract {}
+
+    impl Ownable for RangeVaultContract {}
+
+    impl PSP22Receiver for RangeVaultContract {
+        #[ink(message)]
+        fn before_received(
+            &mut self,
+            _operator: AccountId,
+            _from: AccountId,
+            _value: Balance,
+            _data: Vec<u8>,
+        ) -> Result<(), PSP22ReceiverError> {
+            Ok(())
+        }
+    }
+
+    impl LiquidityManagementTrait for RangeVaultContract {
+        /// @dev The vault only adds liquidity to its own position, through deposit
+        #[ink(message)]
+        fn addLiquidity(&mut self, _params: AddLiquidityParams) -> (u128, U256, U256, Address) {
+            panic!("use deposit")
+        }
+
+        /// @notice Pays the pool for liquidity minted by the vault
+        /// @dev data is the encoded payer, the vault itself when compounding
+        #[ink(message)]
+        fn uniswapV3MintCallback(&mut self, amount0Owed: U256, amount1Owed: U256, data: Vec<u8>) {
+            assert!(self.env().caller() == self.pool, "call back is not pool");
+            let payer: Address = Decode::decode(&mut data.as_ref()).expect("call back data parse error!");
+            if amount0Owed > U256::zero() {
+                self._pay(self.token0, payer, amount0Owed.as_u128());
+            }
+            if amount1Owed > U256::zero() {
+                self._pay(self.token1, payer, amount1Owed.as_u128());
+            }
+        }
+    }
+
+    impl SwapCallback for RangeVaultContract {
+        /// @notice Pays the pool for the swap made by rebalance
+        #[ink(message)]
+        fn swapCallback(&mut self, amount0Delta: Int256, amount1Delta: Int256, _data: Vec<u8>) {
+            assert!(self.env().caller() == self.pool, "call back is not pool");
+            let address_this = self.env().account_id();
+            if amount0Delta > 0 {
+                self._pay(self.token0, address_this, amount0Delta as u128);
+            }
+            if amount1Delta > 0 {
+                self._pay(self.token1, address_this, amount1Delta as u128);
+            }
+        }
+    }
+
+    impl RangeVaultContract {
+        /// @notice Creates a vault for the pool of the tokens and fee, managed by the caller
+        /// @param tick_lower The lower tick of the initial range
+        /// @param tick_upper The upper tick of the initial range
+        #[ink(constructor)]
+        pub fn new(
+            factory: Address,
+            token0: Address,
+            token1: Address,
+            fee: Uint24,
+            tick_lower: Int24,
+            tick_upper: Int24,
+            name: Option<String>,
+            symbol: Option<String>,
+        ) -> Self {
+            ink_lang::utils::initialize_contract(|instance: &mut Self| {
+                assert!(token0 < token1, "token0 must less than token1");
+                let pool = FactoryRef::get_pool(&factory, fee, token0, token1);
+                assert!(pool != ADDRESS0.into(), "pool not exist");
+                let caller = instance.env().caller();
+                instance._init_with_owner(caller);
+                instance.metadata.name = name;
+                instance.metadata.symbol = symbol;
+                instance.metadata.decimals = 18;
+                instance.pool = pool;
+                instance.token0 = token0;
+                instance.token1 = token1;
+                instance.tick_spacing = PoolActionRef::get_tickspacing(&pool);
+                instance._check_range(tick_lower, tick_upper);
+                instance.tick_lower = tick_lower;
+                instance.tick_upper = tick_upper;
+            })
+        }
+
+        /// @notice Adds liquidity to the vault's position and issues shares for it
+        /// @dev The caller must have approved the vault to spend the amounts. Only the amounts matching the current
+        /// ratio of the range are taken
+        /// @param amount0_desired The most token0 to add
+        /// @param amount1_desired The most token1 to add
+        /// @param amount0_min The least token0 to add, as slippage check
+        /// @param amount1_min The least token1 to add, as slippage check
+        /// @param recipient The account receiving the shares
+        /// @return shares The shares issued
+        /// @return amount0 The token0 added
+        /// @return amount1 The token1 added
+        #[ink(message)]
+        pub fn deposit(
+            &mut self,
+            amount0_desired: u128,
+            amount1_desired: u128,
+            amount0_min: u128,
+            amount1_min: u128,
+            recipient: Address,
+        ) -> (u128, u128, u128) {
+            self._compound();
+            let (sqrtPriceX96, sqrtRatioAX96, sqrtRatioBX96) = self._sqrt_ratios();
+            let liquidity = LiquidityAmounts::getLiquidityForAmounts(
+                sqrtPriceX96,
+                sqrtRatioAX96,
+                sqrtRatioBX96,
+                U256::from(amount0_desired),
+                U256::from(amount1_desired),
+            );
+            assert!(liquidity > 0, "amount too small");
+
+            // shares are issued against the value held before this deposit
+            let total_supply = self.total_supply();
+            let (held0, held1) = self.get_underlying_balances();
+            let value_before = LiquidityAmounts::valueInToken1(sqrtPriceX96, U256::from(held0)) + held1;
+
+            let sender = self.env().caller();
+            let (amount0, amount1) = self._mint_liquidity(liquidity, sender);
+            assert!(
+                amount0 >= amount0_min && amount1 >= amount1_min,
+                "Price slippage check"
+            );
+            let shares = if total_supply == 0 {
+                assert!(liquidity > MINIMUM_SHARES, "amount too small");
+                let address_this = self.env().account_id();
+                self._mint(address_this, MINIMUM_SHARES).expect("shares mint error!");
+                liquidity - MINIMUM_SHARES
+            } else {
+                assert!(!value_before.is_zero(), "vault has no value");
+                // minting does not move the price, so both values are taken at the same price
+                let value = LiquidityAmounts::valueInToken1(sqrtPriceX96, U256::from(amount0)) + amount1;
+                FullMath::mulDiv(value, U256::from(total_supply), value_before).as_u128()
+            };
+            assert!(shares > 0, "amount too small");
+            self._mint(recipient, shares).expect("shares mint error!");
+            self.env().emit_event(Deposited {
+                sender,
+                recipient,
+                liquidity,
+                shares,
+                amount0,
+                amount1,
+            });
+            (shares, amount0, amount1)
+        }
+
+        /// @notice Redeems shares of the caller for their part of the position and of the tokens held by the vault
+        /// @dev The fees are compounded first, so every part of the value deposit prices shares against is paid pro rata
+        /// @param amount0_min The least token0 to receive, as slippage check
+        /// @param amount1_min The least token1 to receive, as slippage check
+        /// @return amount0 The token0 sent to the recipient
+        /// @return amount1 The token1 sent to the recipient
+        #[ink(message)]
+        pub fn withdraw(
+            &mut self,
+            shares: u128,
+            amount0_min: u128,
+            amount1_min: u128,
+            recipient: Address,
+        ) -> (u128, u128) {
+            assert!(shares > 0, "shares must be positive");
+            self._compound();
+            let sender = self.env().caller();
+            let address_this = self.env().account_id();
+            let total_supply = U256::from(self.total_supply());
+
+            let idle0 = FullMath::mulDiv(
+                U256::from(PSP22Ref::balance_of(&self.token0, address_this)),
+                U256::from(shares),
+                total_supply,
+            )
+            .as_u128();
+            let idle1 = FullMath::mulDiv(
+                U256::from(PSP22Ref::balance_of(&self.token1, address_this)),
+                U256::from(shares),
+                total_supply,
+            )
+            .as_u128();
+            let liquidity =
+                FullMath::mulDiv(U256::from(self._liquidity()), U256::from(shares), total_supply).as_u128();
+            self._burn_from(sender, shares).expect("shares burn error!");
+
+            let (mut amount0, mut amount1) = (idle0, idle1);
+            if liquidity > 0 {
+                let (burned0, burned1) =
+                    PoolActionRef::burn(&self.pool, self.tick_lower, self.tick_upper, liquidity, None);
+                // the fees were collected by the compounding, only the burned amounts are owed
+                let (collected0, collected1) = PoolActionRef::collect(
+                    &self.pool,
+                    recipient,
+                    self.tick_lower,
+                    self.tick_upper,
+                    burned0.as_u128(),
+                    burned1.as_u128(),
+                    None,
+                );
+                amount0 += collected0;
+                amount1 += collected1;
+            }
+            self._send(self.token0, recipient, idle0);
+            self._send(self.token1, recipient, idle1);
+            assert!(
+                amount0 >= amount0_min && amount1 >= amount1_min,
+                "Price slippage check"
+            );
+
+            self.env().emit_event(Withdrawn {
+                sender,
+                recipient,
+                shares,
+                amount0,
+                amount1,
+            });
+            (amount0, amount1)
+        }
+
+        /// @notice Collects the fees of the position and adds them to its liquidity
+        /// @dev Also done by every deposit and withdrawal
+        #[ink(message)]
+        pub fn compound(&mut self) {
+            self._compound();
+        }
+
+        /// @notice Moves all of the vault's liquidity to a new range
+        /// @dev The vault's tokens are swapped through the pool to the ratio of the new range before being added
+        /// @param min_liquidity The least liquidity of the new position, protecting the swap against price moves
+        /// @return liquidity The liquidity of the new position
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn rebalance(
+            &mut self,
+            tick_lower: Int24,
+            tick_upper: Int24,
+            min_liquidity: u128,
+        ) -> Result<u128, OwnableError> {
+            self._check_range(tick_lower, tick_upper);
+            let liquidity = self._liquidity();
+            if liquidity > 0 {
+                PoolActionRef::burn(&self.pool, self.tick_lower, self.tick_upper, liquidity, None);
+            }
+            self._collect_all();
+            self.tick_lower = tick_lower;
+            self.tick_upper = tick_upper;
+
+            let address_this = self.env().account_id();
+            let (sqrtPriceX96, sqrtRatioAX96, sqrtRatioBX96) = self._sqrt_ratios();
+            let (zeroForOne, amountIn) = LiquidityAmounts::getSwapAmountForRatio(
+                sqrtPriceX96,
+                sqrtRatioAX96,
+                sqrtRatioBX96,
+                U256::from(PSP22Ref::balance_of(&self.token0, address_this)),
+                U256::from(PSP22Ref::balance_of(&self.token1, address_this)),
+            );
+            if !amountIn.is_zero() {
+                // the pool calls back swapCallback to be paid
+                PoolActionRef::swap_builder(
+                    &self.pool,
+                    address_this,
+                    zeroForOne,
+                    amountIn.as_u128() as Int256,
+                    if zeroForOne {
+                        U160::from_dec_str(TickMath::MIN_SQRT_RATIO).unwrap() + 1
+                    } else {
+                        U160::from_dec_str(TickMath::MAX_SQRT_RATIO).unwrap() - 1
+                    },
+                    vec![],
+                )
+                .call_flags(CallFlags::default().set_allow_reentry(true))
+                .fire()
+                .unwrap();
+            }
+
+            let liquidity = self._add_balances();
+            assert!(liquidity >= min_liquidity, "Price slippage check");
+            self.env().emit_event(Rebalanced {
+                tick_lower,
+                tick_upper,
+                liquidity,
+            });
+            Ok(liquidity)
+        }
+
+        #[ink(message)]
+        pub fn get_pool(&self) -> Address {
+            self.pool
+        }
+
+        #[ink(message)]
+        pub fn get_range(&self) -> (Int24, Int24) {
+            (self.tick_lower, self.tick_upper)
+        }
+
+        #[ink(message)]
+        pub fn get_liquidity(&self) -> u128 {
+            self._liquidity()
+        }
+
+        /// @notice The tokens all shares represent at the current price
+        /// @dev Fees accrued since the last action on the position are not included
+        #[ink(message)]
+        pub fn get_underlying_balances(&self) -> (u128, u128) {
+            let address_this = self.env().account_id();
+            let info = PoolActionRef::positions(
+                &self.pool,
+                address_this,
+                self.tick_lower,
+                self.tick_upper,
+                None,
+            );
+            let (sqrtPriceX96, sqrtRatioAX96, sqrtRatioBX96) = self._sqrt_ratios();
+            let (amount0, amount1) = LiquidityAmounts::getAmountsForLiquidity(
+                sqrtPriceX96,
+                sqrtRatioAX96,
+                sqrtRatioBX96,
+                info.liquidity,
+            );
+            (
+                amount0.as_u128() + info.tokensOwed0 + PSP22Ref::balance_of(&self.token0, address_this),
+                amount1.as_u128() + info.tokensOwed1 + PSP22Ref::balance_of(&self.token1, address_this),
+            )
+        }
+
+        /// @dev Collects the fees of the position and adds as much of the vault's tokens as the ratio allows to it
+        fn _compound(&mut self) {
+            let (fees0, fees1) = if self._liquidity() > 0 {
+                // burning zero liquidity updates the fees owed to the position
+                PoolActionRef::burn(&self.pool, self.tick_lower, self.tick_upper, 0, None);
+                self._collect_all()
+            } else {
+                (0, 0)
+            };
+            let liquidity = self._add_balances();
+            if fees0 > 0 || fees1 > 0 || liquidity > 0 {
+                self.env().emit_event(Compounded {
+                    fees0,
+                    fees1,
+                    liquidity,
+                });
+            }
+        }
+
+        /// @dev Adds the vault's tokens to the liquidity of the position, as far as the ratio allows
+        fn _add_balances(&mut self) -> u128 {
+            let address_this = self.env().account_id();
+            let (sqrtPriceX96, sqrtRatioAX96, sqrtRatioBX96) = self._sqrt_ratios();
+            let liquidity = LiquidityAmounts::getLiquidityForAmounts(
+                sqrtPriceX96,
+                sqrtRatioAX96,
+                sqrtRatioBX96,
+                U256::from(PSP22Ref::balance_of(&self.token0, address_this)),
+                U256::from(PSP22Ref::balance_of(&self.token1, address_this)),
+            );
+            if liquidity > 0 {
+                self._mint_liquidity(liquidity, address_this);
+            }
+            liquidity
+        }
+
+        /// @dev Mints liquidity to the vault's position, paid by the payer in uniswapV3MintCallback
+        fn _mint_liquidity(&mut self, liquidity: u128, payer: Address) -> (u128, u128) {
+            let address_this = self.env().account_id();
+            // the pool calls back uniswapV3MintCallback to be paid
+            let (amount0, amount1) = PoolActionRef::mint_builder(
+                &self.pool,
+                address_this,
+                self.tick_lower,
+                self.tick_upper,
+                liquidity,
+                scale::Encode::encode(&payer),
+                None,
+            )
+            .call_flags(CallFlags::default().set_allow_reentry(true))
+            .fire()
+            .unwrap();
+            (amount0.as_u128(), amount1.as_u128())
+        }
+
+        /// @dev Collects everything owed to the position to the vault
+        fn _collect_all(&mut self) -> (u128, u128) {
+            let address_this = self.env().account_id();
+            // the tokens are delivered to this contract, which reenters it through PSP22Receiver
+            PoolActionRef::collect_builder(
+                &self.pool,
+                address_this,
+                self.tick_lower,
+                self.tick_upper,
+                u128::MAX,
+                u128::MAX,
+                None,
+            )
+            .call_flags(CallFlags::default().set_allow_reentry(true))
+            .fire()
+            .unwrap()
+        }
+
+        fn _liquidity(&self) -> u128 {
+            PoolActionRef::positions(
+                &self.pool,
+                self.env().account_id(),
+                self.tick_lower,
+                self.tick_upper,
+                None,
+            )
+            .liquidity
+        }
+
+        fn _sqrt_ratios(&self) -> (U160, U160, U160) {
+            (
+                PoolActionRef::getSlot0(&self.pool).sqrtPriceX96.value,
+                TickMath::getSqrtRatioAtTick(self.tick_lower),
+                TickMath::getSqrtRatioAtTick(self.tick_upper),
+            )
+        }
+
+        fn _check_range(&self, tick_lower: Int24, tick_upper: Int24) {
+            assert!(tick_lower < tick_upper, "TLU");
+            assert!(tick_lower >= TickMath::MIN_TICK, "TLM");
+            assert!(tick_upper <= TickMath::MAX_TICK, "TUM");
+            assert!(
+                tick_lower % self.tick_spacing == 0 && tick_upper % self.tick_spacing == 0,
+                "tick not aligned to tick spacing"
+            );
+        }
+
+        /// @dev Pays the pool from the payer, which approved the vault, or from the vault itself
+        fn _pay(&self, token: Address, payer: Address, amount: u128) {
+            if payer == self.env().account_id() {
+                PSP22Ref::transfer(&token, self.pool, amount, vec![0u8]).expect("token transfer error!");
+            } else {
+                PSP22Ref::transfer_from(&token, payer, self.pool, amount, vec![0u8])
+                    .expect("token transfer error!");
+            }
+        }
+
+        fn _send(&self, token: Address, to: Address, amount: u128) {
+            if amount > 0 {
+                PSP22Ref::transfer(&token, to, amount, vec![0u8]).expect("token transfer error!");
+            }
+        }
+    }
+}
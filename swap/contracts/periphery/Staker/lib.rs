@@ -0,0 +1,533 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#![feature(min_specialization)]
+
+/// @title Staking contract for liquidity that stays in range
+/// @notice Anyone can create an incentive paying a reward token to the positions of a pool, over a period of time.
+/// Position NFTs deposited here can be staked into any number of incentives of their pool. Rewards accrue with the
+/// seconds the position's range was active, weighted by its share of the in-range liquidity, as tracked by the pool's
+/// seconds-per-liquidity accumulators.
+#[openbrush::contract]
+pub mod staker {
+    use crabswap::traits::core::factory::FactoryRef;
+    use crabswap::traits::core::pool_derived_state::PoolDerivedStateRef;
+    use crabswap::traits::periphery::position_manager::PositionManagerRef;
+    use ink_env::hash::{HashOutput, Sha2x256};
+    use ink_lang::codegen::EmitEvent;
+    use ink_lang::codegen::Env;
+    use ink_prelude::vec;
+    use ink_prelude::vec::Vec;
+    use ink_storage::traits::{PackedLayout, SpreadAllocate, SpreadLayout};
+    use ink_storage::Mapping;
+    use libs::periphery::RewardMath;
+    use openbrush::contracts::psp22::*;
+    use openbrush::contracts::psp34::*;
+    use primitives::{Address, Int24, Uint160, ADDRESS0, U160};
+    use scale::{Decode, Encode};
+
+    /// @notice Identifies an incentive, its hash is the incentive id
+    #[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct IncentiveKey {
+        // the token being distributed as a reward
+        pub reward_token: Address,
+        // the pool whose positions earn the reward
+        pub pool: Address,
+        // the block timestamps the rewards start and stop dripping at
+        pub start_time: u64,
+        pub end_time: u64,
+        // the account which receives the unclaimed rewards when the incentive is ended
+        pub refundee: Address,
+    }
+
+    /// @notice Represents a staking incentive
+    #[derive(Debug, Default, Clone, PartialEq, Eq, Encode, Decode, SpreadLayout, PackedLayout)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout))]
+    pub struct Incentive {
+        pub total_reward_unclaimed: u128,
+        pub total_seconds_claimed_x128: Uint160,
+        pub number_of_stakes: u32,
+    }
+
+    /// @notice Represents the deposit of a liquidity NFT
+    #[derive(Debug, Default, Clone, PartialEq, Eq, Encode, Decode, SpreadLayout, PackedLayout)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout))]
+    pub struct Deposit {
+        pub owner: Address,
+        pub number_of_stakes: u32,
+        pub tick_lower: Int24,
+        pub tick_upper: Int24,
+    }
+
+    /// @notice Represents a staked liquidity NFT
+    #[derive(Debug, Default, Clone, PartialEq, Eq, Encode, Decode, SpreadLayout, PackedLayout)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout))]
+    pub struct Stake {
+        pub seconds_per_liquidity_inside_initial_x128: Uint160,
+        pub liquidity: u128,
+    }
+
+    #[ink(storage)]
+    #[derive(SpreadAllocate)]
+    pub struct StakerContract {
+        factory: Address,
+        nonfungible_position_manager: Address,
+        // the longest time before its start an incentive can be created, in milliseconds
+        max_incentive_start_lead_time: u64,
+        // the longest duration of an incentive, in milliseconds
+        max_incentive_duration: u64,
+        // by incentive id
+        incentives: Mapping<[u8; 32], Incentive>,
+        // by token id
+        deposits: Mapping<Id, Deposit>,
+        // by (token id, incentive id)
+        stakes: Mapping<(Id, [u8; 32]), Stake>,
+        // the rewards owed, by (reward token, owner)
+        rewards: Mapping<(Address, Address), u128>,
+    }
+
+    /// @notice Emitted when a liquidity mining incentive has been created
+    /// @param reward The amount of reward tokens to be distributed
+    #[ink(event)]
+    pub struct IncentiveCreated {
+        #[ink(topic)]
+        reward_token: Address,
+        #[ink(topic)]
+        pool: Address,
+        start_time: u64,
+        end_time: u64,
+        refundee: Address,
+        reward: u128,
+    }
+
+    /// @notice Emitted when a liquidity mining incentive has ended
+    /// @param refund The amount of reward tokens refunded
+    #[ink(event)]
+    pub struct IncentiveEnded {
+        #[ink(topic)]
+        incentive_id: [u8; 32],
+        refund: u128,
+    }
+
+    /// @notice Emitted when ownership of a deposit changes
+    /// @param old_owner The owner before the deposit was transferred, ADDRESS0 for a new deposit
+    /// @param new_owner The owner after the deposit was transferred, ADDRESS0 when it is withdrawn
+    #[ink(event)]
+    pub struct DepositTransferred {
+        #[ink(topic)]
+        token_id: Id,
+        #[ink(topic)]
+        old_owner: Address,
+        #[ink(topic)]
+        new_owner: Address,
+    }
+
+    /// @notice Emitted when a token is staked into an incentive
+    #[ink(event)]
+    pub struct TokenStaked {
+        #[ink(topic)]
+        token_id: Id,
+        #[ink(topic)]
+        incentive_id: [u8; 32],
+        liquidity: u128,
+    }
+
+    /// @notice Emitted when a token is unstaked from an incentive
+    #[ink(event)]
+    pub struct TokenUnstaked {
+        #[ink(topic)]
+        token_id: Id,
+        #[ink(topic)]
+        incentive_id: [u8; 32],
+    }
+
+    /// @notice Emitted when reward tokens are claimed
+    #[ink(event)]
+    pub struct RewardClaimed {
+        #[ink(topic)]
+        to: Address,
+        reward: u128,
+    }
+
+    impl PSP22Receiver for StakerContract {
+        #[ink(message)]
+        fn before_received(
+            &mut self,
+            _operator: AccountId,
+            _from: AccountId,
+            _value: Balance,
+            _data: Vec<u8>,
+        ) -> Result<(), PSP22ReceiverError> {
+            Ok(())
+        }
+    }
+
+    impl PSP34Receiver for StakerContract {
+        /// @notice Records the deposit of a position NFT sent by the position manager
+        /// @dev data may hold an encoded Vec<IncentiveKey> to stake the token into right away
+        #[ink(message)]
+        fn before_received(
+            &mut self,
+            _operator: AccountId,
+            from: AccountId,
+            id: Id,
+            data: Vec<u8>,
+        ) -> Result<(), PSP34ReceiverError> {
+            assert!(
+                self.env().caller() == self.nonfungible_position_manager,
+                "not a position nft"
+            );
+            let (_, _, _, _, _, tick_lower, tick_upper, _, _, _, _, _) =
+                PositionManagerRef::positions(&self.nonfungible_position_manager, id.clone());
+            self.deposits.insert(
+                &id,
+                &Deposit {
+                    owner: from,
+                    number_of_stakes: 0,
+                    tick_lower,
+                    tick_upper,
+                },
+            );
+            self.env().emit_event(DepositTransferred {
+                token_id: id.clone(),
+                old_owner: ADDRESS0.into(),
+                new_owner: from,
+            });
+
+            if !data.is_empty() {
+                let keys = Vec::<IncentiveKey>::decode(&mut &data[..]).expect("invalid incentive keys");
+                for key in keys {
+                    self._stake_token(key, id.clone());
+                }
+            }
+            Ok(())
+        }
+    }
+
+    impl StakerContract {
+        /// @param factory The factory the incentivized pools were created by
+        /// @param nonfungible_position_manager The position manager whose NFTs can be staked
+        /// @param max_incentive_start_lead_time The longest time before its start an incentive can be created
+        /// @param max_incentive_duration The longest duration of an incentive
+        #[ink(constructor)]
+        pub fn new(
+            factory: Address,
+            nonfungible_position_manager: Address,
+            max_incentive_start_lead_time: u64,
+            max_incentive_duration: u64,
+        ) -> Self {
+            ink_lang::utils::initialize_contract(|instance: &mut Self| {
+                instance.factory = factory;
+                instance.nonfungible_position_manager = nonfungible_position_manager;
+                instance.max_incentive_start_lead_time = max_incentive_start_lead_time;
+                instance.max_incentive_duration = max_incentive_duration;
+            })
+        }
+
+        /// @notice Creates a new liquidity mining incentive program
+        /// @dev The caller must have approved the staker to spend reward of the reward token
+        /// @param key Details of the incentive to create
+        /// @param reward The amount of reward tokens to be distributed
+        #[ink(message)]
+        pub fn create_incentive(&mut self, key: IncentiveKey, reward: u128) {
+            assert!(reward > 0, "reward must be positive");
+            let now = self.env().block_timestamp();
+            assert!(now <= key.start_time, "start time must be now or in the future");
+            assert!(
+                key.start_time - now <= self.max_incentive_start_lead_time,
+                "start time too far into future"
+            );
+            assert!(key.start_time < key.end_time, "start time must be before end time");
+            assert!(
+                key.end_time - key.start_time <= self.max_incentive_duration,
+                "incentive duration is too long"
+            );
+
+            let incentive_id = compute_incentive_id(&key);
+            let mut incentive = self.incentives.get(incentive_id).unwrap_or_default();
+            incentive.total_reward_unclaimed += reward;
+            self.incentives.insert(incentive_id, &incentive);
+
+            let caller = self.env().caller();
+            let address_this = self.env().account_id();
+            PSP22Ref::transfer_from(&key.reward_token, caller, address_this, reward, vec![0u8])
+                .expect("reward transfer error!");
+
+            self.env().emit_event(IncentiveCreated {
+                reward_token: key.reward_token,
+                pool: key.pool,
+                start_time: key.start_time,
+                end_time: key.end_time,
+                refundee: key.refundee,
+                reward,
+            });
+        }
+
+        /// @notice Ends an incentive after the incentive end time has passed and all stakes have been withdrawn
+        /// @param key Details of the incentive to end
+        /// @return refund The remaining reward tokens when the incentive is ended
+        #[ink(message)]
+        pub fn end_incentive(&mut self, key: IncentiveKey) -> u128 {
+            assert!(
+                self.env().block_timestamp() >= key.end_time,
+                "cannot end incentive before end time"
+            );
+            let incentive_id = compute_incentive_id(&key);
+            let mut incentive = self.incentives.get(incentive_id).unwrap_or_default();
+            let refund = incentive.total_reward_unclaimed;
+            assert!(refund > 0, "no refund available");
+            assert!(
+                incentive.number_of_stakes == 0,
+                "cannot end incentive while deposits are staked"
+            );
+
+            // issue the refund
+            incentive.total_reward_unclaimed = 0;
+            self.incentives.insert(incentive_id, &incentive);
+            PSP22Ref::transfer(&key.reward_token, key.refundee, refund, vec![0u8])
+                .expect("refund transfer error!");
+
+            // note we never clear totalSecondsClaimedX128
+            self.env().emit_event(IncentiveEnded { incentive_id, refund });
+            refund
+        }
+
+        /// @notice Transfers ownership of a deposit from the sender to the given recipient
+        #[ink(message)]
+        pub fn transfer_deposit(&mut self, token_id: Id, to: Address) {
+            assert!(to != ADDRESS0.into(), "invalid transfer recipient");
+            let mut deposit = self.deposits.get(&token_id).expect("deposit not exist");
+            let caller = self.env().caller();
+            assert!(deposit.owner == caller, "can only be called by deposit owner");
+            deposit.owner = to;
+            self.deposits.insert(&token_id, &deposit);
+            self.env().emit_event(DepositTransferred {
+                token_id,
+                old_owner: caller,
+                new_owner: to,
+            });
+        }
+
+        /// @notice Withdraws a position NFT that is staked into no incentive
+        /// @param to The address where the NFT will be sent
+        /// @param data Passed to the receiver of the NFT
+        #[ink(message)]
+        pub fn withdraw_token(&mut self, token_id: Id, to: Address, data: Vec<u8>) {
+            assert!(to != self.env().account_id(), "cannot withdraw to staker");
+            let deposit = self.deposits.get(&token_id).expect("deposit not exist");
+            let caller = self.env().caller();
+            assert!(deposit.number_of_stakes == 0, "cannot withdraw token while staked");
+            assert!(deposit.owner == caller, "only owner can withdraw token");
+
+            self.deposits.remove(&token_id);
+            self.env().emit_event(DepositTransferred {
+                token_id: token_id.clone(),
+                old_owner: deposit.owner,
+                new_owner: ADDRESS0.into(),
+            });
+            PSP34Ref::transfer(&self.nonfungible_position_manager, to, token_id, data)
+                .expect("nft transfer error!");
+        }
+
+        /// @notice Stakes a deposited position NFT into an incentive
+        #[ink(message)]
+        pub fn stake_token(&mut self, key: IncentiveKey, token_id: Id) {
+            let deposit = self.deposits.get(&token_id).expect("deposit not exist");
+            assert!(
+                deposit.owner == self.env().caller(),
+                "only owner can stake token"
+            );
+            self._stake_token(key, token_id);
+        }
+
+        /// @notice Unstakes a position NFT from an incentive, crediting its reward to the deposit owner
+        /// @dev Anyone may unstake once the incentive has ended, so it can be ended and refunded
+        #[ink(message)]
+        pub fn unstake_token(&mut self, key: IncentiveKey, token_id: Id) {
+            let mut deposit = self.deposits.get(&token_id).expect("deposit not exist");
+            // anyone can call unstakeToken if the block time is after the end time of the incentive
+            if self.env().block_timestamp() < key.end_time {
+                assert!(
+                    deposit.owner == self.env().caller(),
+                    "only owner can withdraw token before incentive end time"
+                );
+            }
+
+            let incentive_id = compute_incentive_id(&key);
+            let stake = self
+                .stakes
+                .get((&token_id, incentive_id))
+                .expect("stake does not exist");
+
+            deposit.number_of_stakes -= 1;
+            self.deposits.insert(&token_id, &deposit);
+            let mut incentive = self.incentives.get(incentive_id).unwrap_or_default();
+            incentive.number_of_stakes -= 1;
+
+            let (_, seconds_per_liquidity_inside_x128, _) = PoolDerivedStateRef::snapshotCumulativesInside(
+                &key.pool,
+                deposit.tick_lower,
+                deposit.tick_upper,
+            );
+            let (reward, seconds_inside_x128) = RewardMath::computeRewardAmount(
+                incentive.total_reward_unclaimed,
+                incentive.total_seconds_claimed_x128.value,
+                key.start_time,
+                key.end_time,
+                stake.liquidity,
+                stake.seconds_per_liquidity_inside_initial_x128.value,
+                seconds_per_liquidity_inside_x128,
+                self.env().block_timestamp(),
+            );
+
+            // if this overflows, e.g. after 2^32-1 full liquidity seconds have been claimed,
+            // reward rate will fall drastically so it's safe
+            incentive.total_seconds_claimed_x128.value = incentive
+                .total_seconds_claimed_x128
+                .value
+                .overflowing_add(seconds_inside_x128)
+                .0;
+            // reward is never greater than total reward unclaimed
+            incentive.total_reward_unclaimed -= reward;
+            self.incentives.insert(incentive_id, &incentive);
+
+            // this only overflows if a token has a total supply greater than type(uint256).max
+            let owed = self.rewards.get((key.reward_token, deposit.owner)).unwrap_or(0);
+            self.rewards
+                .insert((key.reward_token, deposit.owner), &(owed + reward));
+
+            self.stakes.remove((&token_id, incentive_id));
+            self.env().emit_event(TokenUnstaked {
+                token_id,
+                incentive_id,
+            });
+        }
+
+        /// @notice Transfers reward_requested of the rewards owed to the caller to the recipient
+        /// @param reward_token The token being distributed as a reward
+        /// @param to The address where claimed rewards will be sent to
+        /// @param amount_requested The amount of reward tokens to claim, 0 for the whole balance
+        /// @return reward The amount of reward tokens claimed
+        #[ink(message)]
+        pub fn claim_reward(&mut self, reward_token: Address, to: Address, amount_requested: u128) -> u128 {
+            let caller = self.env().caller();
+            let owed = self.rewards.get((reward_token, caller)).unwrap_or(0);
+            let mut reward = owed;
+            if amount_requested != 0 && amount_requested < reward {
+                reward = amount_requested;
+            }
+            self.rewards.insert((reward_token, caller), &(owed - reward));
+            if reward > 0 {
+                PSP22Ref::transfer(&reward_token, to, reward, vec![0u8])
+                    .expect("reward transfer error!");
+            }
+            self.env().emit_event(RewardClaimed { to, reward });
+            reward
+        }
+
+        /// @notice Calculates the reward amount that will be received for the given stake
+        /// @return reward The reward accrued to the NFT for the given incentive thus far
+        /// @return seconds_inside_x128 The seconds inside the tick range
+        #[ink(message)]
+        pub fn get_reward_info(&self, key: IncentiveKey, token_id: Id) -> (u128, U160) {
+            let incentive_id = compute_incentive_id(&key);
+            let stake = self
+                .stakes
+                .get((&token_id, incentive_id))
+                .expect("stake does not exist");
+            let deposit = self.deposits.get(&token_id).expect("deposit not exist");
+            let incentive = self.incentives.get(incentive_id).unwrap_or_default();
+
+            let (_, seconds_per_liquidity_inside_x128, _) = PoolDerivedStateRef::snapshotCumulativesInside(
+                &key.pool,
+                deposit.tick_lower,
+                deposit.tick_upper,
+            );
+            RewardMath::computeRewardAmount(
+                incentive.total_reward_unclaimed,
+                incentive.total_seconds_claimed_x128.value,
+                key.start_time,
+                key.end_time,
+                stake.liquidity,
+                stake.seconds_per_liquidity_inside_initial_x128.value,
+                seconds_per_liquidity_inside_x128,
+                self.env().block_timestamp(),
+            )
+        }
+
+        #[ink(message)]
+        pub fn get_incentive(&self, incentive_id: [u8; 32]) -> Incentive {
+            self.incentives.get(incentive_id).unwrap_or_default()
+        }
+
+        #[ink(message)]
+        pub fn get_deposit(&self, token_id: Id) -> Option<Deposit> {
+            self.deposits.get(&token_id)
+        }
+
+        #[ink(message)]
+        pub fn get_stake(&self, token_id: Id, incentive_id: [u8; 32]) -> Option<Stake> {
+            self.stakes.get((&token_id, incentive_id))
+        }
+
+        #[ink(message)]
+        pub fn get_rewards(&self, reward_token: Address, owner: Address) -> u128 {
+            self.rewards.get((reward_token, owner)).unwrap_or(0)
+        }
+
+        #[ink(message)]
+        pub fn get_incentive_id(&self, key: IncentiveKey) -> [u8; 32] {
+            compute_incentive_id(&key)
+        }
+
+        /// @dev Stakes a deposited token without doing an ownership check
+        fn _stake_token(&mut self, key: IncentiveKey, token_id: Id) {
+            let now = self.env().block_timestamp();
+            assert!(now >= key.start_time, "incentive not started");
+            assert!(now < key.end_time, "incentive ended");
+
+            let incentive_id = compute_incentive_id(&key);
+            let mut incentive = self.incentives.get(incentive_id).unwrap_or_default();
+            assert!(incentive.total_reward_unclaimed > 0, "non-existent incentive");
+            assert!(
+                self.stakes.get((&token_id, incentive_id)).is_none(),
+                "token already staked"
+            );
+
+            let (_, _, token0, token1, fee, tick_lower, tick_upper, liquidity, _, _, _, _) =
+                PositionManagerRef::positions(&self.nonfungible_position_manager, token_id.clone());
+            let pool = FactoryRef::get_pool(&self.factory, fee, token0, token1);
+            assert!(pool == key.pool, "token pool is not the incentive pool");
+            assert!(liquidity > 0, "cannot stake token with 0 liquidity");
+
+            let mut deposit = self.deposits.get(&token_id).expect("deposit not exist");
+            deposit.number_of_stakes += 1;
+            self.deposits.insert(&token_id, &deposit);
+            incentive.number_of_stakes += 1;
+            self.incentives.insert(incentive_id, &incentive);
+
+            let (_, seconds_per_liquidity_inside_x128, _) =
+                PoolDerivedStateRef::snapshotCumulativesInside(&pool, tick_lower, tick_upper);
+            self.stakes.insert(
+                (&token_id, incentive_id),
+                &Stake {
+                    seconds_per_liquidity_inside_initial_x128: Uint160::new_with_u256(
+                        seconds_per_liquidity_inside_x128,
+                    ),
+                    liquidity,
+                },
+            );
+            self.env().emit_event(TokenStaked {
+                token_id,
+                incentive_id,
+                liquidity,
+            });
+        }
+    }
+
+    /// @notice Calculates the incentive id from an incentive key
+    fn compute_incentive_id(key: &IncentiveKey) -> [u8; 32] {
+        let mut incentive_id = <Sha2x256 as HashOutput>::Type::default();
+        ink_env::hash_encoded::<Sha2x256, _>(key, &mut incentive_id);
+        incentive_id
+    }
+}
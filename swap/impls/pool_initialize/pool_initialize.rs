@@ -36,11 +36,15 @@ impl<T:ImmutableStateStorage<Data = ImmutableStateData>> Initializer for T{
         assert!(token0<token1,"token0 must less than token1");
         let factory_address = self.get().factory;
         let mut pool_address = FactoryRef::get_pool(&factory_address,fee,token0,token1);
+        let transfer_value = ink_env::transferred_value::<DefaultEnvironment>();
+        let mut spent = 0;
         
         if pool_address == ADDRESS0.into() {
-            let transfer_value = ink_env::transferred_value::<DefaultEnvironment>();
+            // forward exactly what the factory asks for, see Factory#get_pool_creation_cost
+            spent = FactoryRef::get_pool_creation_cost(&factory_address);
+            assert!(transfer_value>=spent,"insufficient value for pool creation");
             pool_address = FactoryRef::create_pool_builder(&factory_address,fee,token0,token1)
-                .transferred_value(transfer_value/2)
+                .transferred_value(spent)
                 .fire().unwrap();
             PoolActionRef::initialize(&mut pool_address,sqrt_price_x96);
         }else{
@@ -49,6 +53,10 @@ impl<T:ImmutableStateStorage<Data = ImmutableStateData>> Initializer for T{
                 PoolActionRef::initialize(&mut pool_address,sqrt_price_x96);
             }
         }
+        if transfer_value > spent {
+            ink_env::transfer::<DefaultEnvironment>(ink_env::caller::<DefaultEnvironment>(),transfer_value-spent)
+                .expect("refund failed");
+        }
         pool_address
     }
 }
\ No newline at end of file
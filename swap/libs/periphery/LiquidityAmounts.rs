@@ -99,6 +99,119 @@ fn getLiquidityForAmount1(mut sqrtRatioAX96: U160, mut sqrtRatioBX96: U160, amou
     .as_u128();
 }
 
+/// @notice Computes the amount of token0 for a given amount of liquidity and a price range
+/// @param sqrtRatioAX96 A sqrt price representing the first tick boundary
+/// @param sqrtRatioBX96 A sqrt price representing the second tick boundary
+/// @param liquidity The liquidity being valued
+/// @return amount0 The amount of token0
+pub fn getAmount0ForLiquidity(
+    mut sqrtRatioAX96: U160,
+    mut sqrtRatioBX96: U160,
+    liquidity: u128,
+) -> U256 {
+    // if (sqrtRatioAX96 > sqrtRatioBX96) (sqrtRatioAX96, sqrtRatioBX96) = (sqrtRatioBX96, sqrtRatioAX96);
+    (sqrtRatioAX96, sqrtRatioBX96) = arrangeToken(sqrtRatioAX96, sqrtRatioBX96);
+    // return
+    //     FullMath.mulDiv(
+    //         uint256(liquidity) << FixedPoint96.RESOLUTION,
+    //         sqrtRatioBX96 - sqrtRatioAX96,
+    //         sqrtRatioBX96
+    //     ) / sqrtRatioAX96;
+    // FullMath::mulDiv has no 512 bit intermediate, so the Q96 factor is divided by sqrtRatioBX96 first
+    FullMath::mulDiv(
+        U256::from(liquidity),
+        FullMath::mulDiv(
+            sqrtRatioBX96 - sqrtRatioAX96,
+            U256::from(FixedPoint96::Q96),
+            sqrtRatioBX96,
+        ),
+        sqrtRatioAX96,
+    )
+}
+
+/// @notice Computes the token0 and token1 value for a given amount of liquidity, the current
+/// pool prices and the prices at the tick boundaries
+/// @param sqrtRatioX96 A sqrt price representing the current pool prices
+/// @param sqrtRatioAX96 A sqrt price representing the first tick boundary
+/// @param sqrtRatioBX96 A sqrt price representing the second tick boundary
+/// @param liquidity The liquidity being valued
+/// @return amount0 The amount of token0
+/// @return amount1 The amount of token1
+pub fn getAmountsForLiquidity(
+    sqrtRatioX96: U160,
+    mut sqrtRatioAX96: U160,
+    mut sqrtRatioBX96: U160,
+    liquidity: u128,
+) -> (U256, U256) {
+    // if (sqrtRatioAX96 > sqrtRatioBX96) (sqrtRatioAX96, sqrtRatioBX96) = (sqrtRatioBX96, sqrtRatioAX96);
+    (sqrtRatioAX96, sqrtRatioBX96) = arrangeToken(sqrtRatioAX96, sqrtRatioBX96);
+    // if (sqrtRatioX96 <= sqrtRatioAX96) {
+    //     amount0 = getAmount0ForLiquidity(sqrtRatioAX96, sqrtRatioBX96, liquidity);
+    // } else if (sqrtRatioX96 < sqrtRatioBX96) {
+    //     amount0 = getAmount0ForLiquidity(sqrtRatioX96, sqrtRatioBX96, liquidity);
+    //     amount1 = getAmount1ForLiquidity(sqrtRatioAX96, sqrtRatioX96, liquidity);
+    // } else {
+    //     amount1 = getAmount1ForLiquidity(sqrtRatioAX96, sqrtRatioBX96, liquidity);
+    // }
+    if sqrtRatioX96 <= sqrtRatioAX96 {
+        (
+            getAmount0ForLiquidity(sqrtRatioAX96, sqrtRatioBX96, liquidity),
+            U256::zero(),
+        )
+    } else if sqrtRatioX96 < sqrtRatioBX96 {
+        (
+            getAmount0ForLiquidity(sqrtRatioX96, sqrtRatioBX96, liquidity),
+            getAmount1ForLiquidity(sqrtRatioAX96, sqrtRatioX96, liquidity),
+        )
+    } else {
+        (
+            U256::zero(),
+            getAmount1ForLiquidity(sqrtRatioAX96, sqrtRatioBX96, liquidity),
+        )
+    }
+}
+
+/// @notice Computes the swap that brings token amounts to the ratio of a position at the current price
+/// @dev Values both amounts in token1 at the current price and ignores the swap fee and price impact, so a small
+/// remainder is left over after adding liquidity
+/// @param sqrtRatioX96 A sqrt price representing the current pool prices
+/// @param sqrtRatioAX96 A sqrt price representing the first tick boundary
+/// @param sqrtRatioBX96 A sqrt price representing the second tick boundary
+/// @param amount0 The amount of token0 available
+/// @param amount1 The amount of token1 available
+/// @return zeroForOne Whether token0 should be swapped for token1
+/// @return amountIn The amount of the token to swap, 0 if the amounts already match the ratio
+pub fn getSwapAmountForRatio(
+    sqrtRatioX96: U160,
+    mut sqrtRatioAX96: U160,
+    mut sqrtRatioBX96: U160,
+    amount0: U256,
+    amount1: U256,
+) -> (bool, U256) {
+    (sqrtRatioAX96, sqrtRatioBX96) = arrangeToken(sqrtRatioAX96, sqrtRatioBX96);
+    // the amounts of Q96 liquidity, which only serve as the target ratio
+    let (ratio0, ratio1) =
+        getAmountsForLiquidity(sqrtRatioX96, sqrtRatioAX96, sqrtRatioBX96, FixedPoint96::Q96);
+    let value = valueInToken1(sqrtRatioX96, amount0) + amount1;
+    let ratioValue = valueInToken1(sqrtRatioX96, ratio0) + ratio1;
+
+    let target1 = FullMath::mulDiv(value, ratio1, ratioValue);
+    if amount1 > target1 {
+        return (false, amount1 - target1);
+    }
+    let target0 = FullMath::mulDiv(value, ratio0, ratioValue);
+    (true, amount0.saturating_sub(target0))
+}
+
+/// @dev Values an amount of token0 in token1 at the price sqrtRatioX96
+pub fn valueInToken1(sqrtRatioX96: U160, amount0: U256) -> U256 {
+    FullMath::mulDiv(
+        FullMath::mulDiv(amount0, sqrtRatioX96, U256::from(FixedPoint96::Q96)),
+        sqrtRatioX96,
+        U256::from(FixedPoint96::Q96),
+    )
+}
+
 fn arrangeToken(mut rationA: U160, mut ratioB: U160) -> (U160, U160) {
     if rationA > ratioB {
         let temp = ratioB;
@@ -119,3 +232,83 @@ fn my_temp_test() {
     
     println!("hash is:{:?}",hash);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn q96() -> U256 {
+        U256::from(FixedPoint96::Q96)
+    }
+
+    fn e18(value: u64) -> U256 {
+        U256::from(value) * U256::from(1_000_000_000_000_000_000u128)
+    }
+
+    #[test]
+    fn amount0_for_liquidity_between_price_1_and_4() {
+        // sqrt prices 1 and 2
+        let amount0 = getAmount0ForLiquidity(q96(), q96() * 2, 1_000_000_000_000_000_000);
+        assert_eq!(amount0, e18(1) / 2);
+        // the order of the boundaries does not matter
+        assert_eq!(getAmount0ForLiquidity(q96() * 2, q96(), 1_000_000_000_000_000_000), amount0);
+    }
+
+    #[test]
+    fn amounts_for_liquidity_below_in_and_above_range() {
+        let (lower, upper) = (q96() / 2, q96() * 2);
+        let liquidity = 1_000_000_000_000_000_000;
+        let (amount0, amount1) = getAmountsForLiquidity(q96() / 4, lower, upper, liquidity);
+        assert!(amount0 > U256::zero());
+        assert_eq!(amount1, U256::zero());
+
+        let (amount0, amount1) = getAmountsForLiquidity(q96(), lower, upper, liquidity);
+        assert_eq!(amount0, e18(1) / 2);
+        assert_eq!(amount1, e18(1) / 2);
+
+        let (amount0, amount1) = getAmountsForLiquidity(q96() * 4, lower, upper, liquidity);
+        assert_eq!(amount0, U256::zero());
+        assert!(amount1 > U256::zero());
+    }
+
+    #[test]
+    fn value_in_token1_at_price_1_and_4() {
+        assert_eq!(valueInToken1(q96(), e18(3)), e18(3));
+        assert_eq!(valueInToken1(q96() * 2, e18(3)), e18(12));
+    }
+
+    #[test]
+    fn swap_for_ratio_swaps_half_into_a_balanced_range() {
+        // at price 1 the range [1/4, 4] needs equal amounts of both tokens
+        let (lower, upper) = (q96() / 2, q96() * 2);
+        assert_eq!(
+            getSwapAmountForRatio(q96(), lower, upper, e18(100), U256::zero()),
+            (true, e18(50))
+        );
+        assert_eq!(
+            getSwapAmountForRatio(q96(), lower, upper, U256::zero(), e18(100)),
+            (false, e18(50))
+        );
+    }
+
+    #[test]
+    fn swap_for_ratio_is_zero_when_balanced() {
+        let (lower, upper) = (q96() / 2, q96() * 2);
+        let (_, amountIn) = getSwapAmountForRatio(q96(), lower, upper, e18(10), e18(10));
+        assert_eq!(amountIn, U256::zero());
+    }
+
+    #[test]
+    fn swap_for_ratio_swaps_everything_out_of_range() {
+        // the range is below the price, so the position only holds token1
+        assert_eq!(
+            getSwapAmountForRatio(q96(), q96() / 4, q96() / 2, e18(10), U256::zero()),
+            (true, e18(10))
+        );
+        // the range is above the price, so the position only holds token0
+        assert_eq!(
+            getSwapAmountForRatio(q96(), q96() * 2, q96() * 4, U256::zero(), e18(10)),
+            (false, e18(10))
+        );
+    }
+}
@@ -10,6 +10,7 @@ use ink_prelude::string::ToString;
 use ink_prelude::string::String;
 use ink_prelude::vec::Vec;
 use ink_prelude::vec;
+use ink_prelude::format;
 
 const sqrt10X128: &'static str = "1076067327063303206878105757264492625226";
 
@@ -31,6 +32,19 @@ pub struct ConstructTokenURIParams {
     pub poolAddress: Address,
 }
 
+/// @dev The subset of ConstructTokenURIParams needed for the metadata without image
+#[derive(Clone)]
+pub struct ConstructTokenMetadataParams {
+    pub tokenId: U256,
+    pub quoteTokenSymbol: String,
+    pub baseTokenSymbol: String,
+    pub tickLower: Int24,
+    pub tickUpper: Int24,
+    pub tickCurrent: Int24,
+    pub fee: Uint24,
+    pub poolAddress: Address,
+}
+
 #[derive(Default)]
 struct DecimalStringParams {
     // significant figures of decimal
@@ -237,6 +251,40 @@ fn tokenToColorHex(token: U256, offset: u32) -> String {
     return HexStrings::toHexStringNoPrefix(token >> offset, 3);
 }
 
+/// @notice Returns a data URI with compact JSON metadata of a position, without the SVG image of constructTokenURI
+/// @dev The position details are structured attributes, so they can be filtered on by marketplaces
+pub fn constructTokenMetadata(params: ConstructTokenMetadataParams) -> String {
+    let feeTier: String = feeToPercentString(params.fee);
+    let quoteTokenSymbol: String = escapeQuotes(params.quoteTokenSymbol);
+    let baseTokenSymbol: String = escapeQuotes(params.baseTokenSymbol);
+    // the position only earns fees while tickLower <= tickCurrent < tickUpper
+    let inRange: bool = params.tickLower <= params.tickCurrent && params.tickCurrent < params.tickUpper;
+    let json: String = format!(
+        concat!(
+            r#"{{"name":"Uniswap - {} - {}/{}","tokenId":"{}","attributes":["#,
+            r#"{{"trait_type":"Pool","value":"{}"}},"#,
+            r#"{{"trait_type":"Fee Tier","value":"{}"}},"#,
+            r#"{{"trait_type":"Tick Lower","value":{}}},"#,
+            r#"{{"trait_type":"Tick Upper","value":{}}},"#,
+            r#"{{"trait_type":"In Range","value":{}}},"#,
+            r#"{{"trait_type":"Quote Token","value":"{}"}},"#,
+            r#"{{"trait_type":"Base Token","value":"{}"}}]}}"#
+        ),
+        feeTier,
+        quoteTokenSymbol,
+        baseTokenSymbol,
+        params.tokenId,
+        addressToString(params.poolAddress),
+        feeTier,
+        params.tickLower,
+        params.tickUpper,
+        inRange,
+        quoteTokenSymbol,
+        baseTokenSymbol,
+    );
+    format!("data:application/json;base64,{}", base64::encode(json.as_bytes()))
+}
+
 fn overRange(tickLower: Int24, tickUpper: Int24, tickCurrent: Int24) -> i8 {
     if tickCurrent < tickLower {
         return -1;
@@ -349,11 +397,11 @@ fn escapeQuotes(symbol: String) -> String {
         let mut index: U256 = U256::zero();
         for symbol_byte in symbolBytes {
             if symbol_byte == b'"' {
-                index += U256::one();
                 escapedBytes[index.as_usize()] = b'\\';
+                index += U256::one();
             }
-            index += U256::one();
             escapedBytes[index.as_usize()] = symbol_byte;
+            index += U256::one();
         }
         return String::from_utf8_lossy(&escapedBytes).to_string();
     }
@@ -771,3 +819,85 @@ fn generateDecimalString(mut params: DecimalStringParams) -> String {
     // return string(buffer);
     String::from_utf8_lossy(&buffer).to_string()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(tickCurrent: Int24) -> String {
+        let uri = constructTokenMetadata(ConstructTokenMetadataParams {
+            tokenId: U256::from(7),
+            quoteTokenSymbol: "USDC".to_string(),
+            baseTokenSymbol: "WETH".to_string(),
+            tickLower: -60,
+            tickUpper: 60,
+            tickCurrent,
+            fee: 3000,
+            poolAddress: Address::from([0x11; 32]),
+        });
+        let json = uri.strip_prefix("data:application/json;base64,").unwrap();
+        String::from_utf8(base64::decode(json).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn metadata_has_position_attributes() {
+        let json = metadata(0);
+        assert!(json.contains(r#""tokenId":"7""#));
+        assert!(json.contains(&format!(r#"{{"trait_type":"Pool","value":"{}"}}"#, "11".repeat(32))));
+        assert!(json.contains(r#"{"trait_type":"Tick Lower","value":-60}"#));
+        assert!(json.contains(r#"{"trait_type":"Tick Upper","value":60}"#));
+        assert!(json.contains(r#"{"trait_type":"In Range","value":true}"#));
+        assert!(json.contains(r#"{"trait_type":"Quote Token","value":"USDC"}"#));
+        assert!(json.contains(r#"{"trait_type":"Base Token","value":"WETH"}"#));
+        assert!(!json.contains("image"));
+    }
+
+    /// @dev The decoded payload of the data URI, with the SCALE length prefixes constructTokenURI puts before
+    /// every part
+    fn token_uri_payload(quoteTokenSymbol: &str) -> Vec<u8> {
+        let uri = constructTokenURI(ConstructTokenURIParams {
+            tokenId: U256::from(7),
+            quoteTokenAddress: Address::from([0x22; 32]),
+            baseTokenAddress: Address::from([0x33; 32]),
+            quoteTokenSymbol: quoteTokenSymbol.to_string(),
+            baseTokenSymbol: "WETH".to_string(),
+            quoteTokenDecimals: 18,
+            baseTokenDecimals: 18,
+            flipRatio: false,
+            tickLower: -60,
+            tickUpper: 60,
+            tickCurrent: 0,
+            tickSpacing: 60,
+            fee: 3000,
+            poolAddress: Address::from([0x11; 32]),
+        });
+        let encoded = &uri[uri.find("base64,").unwrap() + "base64,".len()..];
+        // skips the length prefix of the base64 part
+        encoded
+            .char_indices()
+            .take(8)
+            .find_map(|(i, _)| base64::decode(&encoded[i..]).ok())
+            .expect("no base64 payload")
+    }
+
+    fn contains(payload: &[u8], part: &str) -> bool {
+        payload.windows(part.len()).any(|window| window == part.as_bytes())
+    }
+
+    #[test]
+    fn token_uri_escapes_quotes_in_symbols() {
+        let payload = token_uri_payload(r#"US"DC"#);
+        assert!(contains(&payload, r#"{"name":""#));
+        assert!(contains(&payload, r#"US\"DC"#));
+        assert!(!contains(&payload, r#"US"DC"#));
+        // symbols without quotes are kept as they are
+        assert!(contains(&token_uri_payload("USDC"), "USDC"));
+    }
+
+    #[test]
+    fn metadata_in_range_excludes_upper_tick() {
+        assert!(metadata(-60).contains(r#"{"trait_type":"In Range","value":true}"#));
+        assert!(metadata(60).contains(r#"{"trait_type":"In Range","value":false}"#));
+        assert!(metadata(-61).contains(r#"{"trait_type":"In Range","value":false}"#));
+    }
+}
@@ -1,13 +1,45 @@
 #![allow(non_snake_case)]
 
+use ink_env::hash::{HashOutput, Sha2x256};
 use primitives::{Int24, Address};
 
 /// @dev Returns the key of the position in the core library
+/// @param salt Distinguishes independent positions of the same owner on the same range, zero for the default position
 pub fn compute(
     owner:Address,
     tickLower:Int24,
     tickUpper:Int24,
-) ->Vec<u8> {
-    // return keccak256(abi.encodePacked(owner, tickLower, tickUpper));
-    scale::Encode::encode(&(owner,tickLower,tickUpper))
-}
\ No newline at end of file
+    salt:[u8; 32],
+) ->[u8; 32] {
+    // return keccak256(abi.encodePacked(owner, tickLower, tickUpper, salt));
+    let mut key = <Sha2x256 as HashOutput>::Type::default();
+    ink_env::hash_encoded::<Sha2x256, _>(&(owner, tickLower, tickUpper, salt), &mut key);
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compute_is_deterministic() {
+        let owner = Address::from([0x01; 32]);
+        assert_eq!(compute(owner, -60, 60, [0u8; 32]), compute(owner, -60, 60, [0u8; 32]));
+    }
+
+    #[test]
+    fn compute_differs_by_salt() {
+        let owner = Address::from([0x01; 32]);
+        assert_ne!(compute(owner, -60, 60, [0u8; 32]), compute(owner, -60, 60, [1u8; 32]));
+    }
+
+    #[test]
+    fn compute_differs_by_range_and_owner() {
+        let owner = Address::from([0x01; 32]);
+        let other = Address::from([0x02; 32]);
+        let key = compute(owner, -60, 60, [0u8; 32]);
+        assert_ne!(key, compute(owner, -120, 60, [0u8; 32]));
+        assert_ne!(key, compute(owner, -60, 120, [0u8; 32]));
+        assert_ne!(key, compute(other, -60, 60, [0u8; 32]));
+    }
+}
@@ -0,0 +1,103 @@
+#![allow(non_snake_case)]
+
+use primitives::{U160, U256};
+
+use crate::swap::FullMath;
+
+// @title Math for computing rewards
+// @notice Allows computing rewards given some parameters of stakes and incentives
+
+/// @notice Compute the amount of rewards owed given parameters of the incentive and stake
+/// @param totalRewardUnclaimed The total amount of unclaimed rewards left for an incentive
+/// @param totalSecondsClaimedX128 How many full liquidity-seconds have been already claimed for the incentive
+/// @param startTime The block timestamp the incentive rewards began at
+/// @param endTime The block timestamp rewards are no longer being dripped out after
+/// @param liquidity The amount of liquidity, assumed to be constant over the period over which the snapshots are measured
+/// @param secondsPerLiquidityInsideInitialX128 The seconds per liquidity of the liquidity tick range as of the beginning of the period
+/// @param secondsPerLiquidityInsideX128 The seconds per liquidity of the liquidity tick range as of the current block timestamp
+/// @param currentTime The current block timestamp, which must be greater than or equal to the start time
+/// @return reward The amount of rewards owed
+/// @return secondsInsideX128 The total liquidity seconds inside the position's range for the duration of the stake
+pub fn computeRewardAmount(
+    totalRewardUnclaimed: u128,
+    totalSecondsClaimedX128: U160,
+    startTime: u64,
+    endTime: u64,
+    liquidity: u128,
+    secondsPerLiquidityInsideInitialX128: U160,
+    secondsPerLiquidityInsideX128: U160,
+    currentTime: u64,
+) -> (u128, U160) {
+    // this should never be called before the start time
+    // assert(currentTime >= startTime);
+    assert!(currentTime >= startTime, "current time before start time");
+
+    // this operation is safe, as the difference cannot be greater than 1/stake.liquidity
+    // secondsInsideX128 = (secondsPerLiquidityInsideX128 - secondsPerLiquidityInsideInitialX128) * liquidity;
+    let secondsInsideX128 = secondsPerLiquidityInsideX128
+        .overflowing_sub(secondsPerLiquidityInsideInitialX128)
+        .0
+        .overflowing_mul(U256::from(liquidity))
+        .0;
+
+    // uint256 totalSecondsUnclaimedX128 =
+    //     ((Math.max(endTime, currentTime) - startTime) << 128) - totalSecondsClaimedX128;
+    let totalSecondsUnclaimedX128 =
+        (U256::from(endTime.max(currentTime) - startTime) << 128) - totalSecondsClaimedX128;
+
+    // reward = FullMath.mulDiv(totalRewardUnclaimed, secondsInsideX128, totalSecondsUnclaimedX128);
+    let reward = FullMath::mulDiv(
+        U256::from(totalRewardUnclaimed),
+        secondsInsideX128,
+        totalSecondsUnclaimedX128,
+    );
+    (reward.as_u128(), secondsInsideX128)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn x128(value: u64) -> U256 {
+        U256::from(value) << 128
+    }
+
+    #[test]
+    fn full_range_stake_for_the_whole_incentive_gets_everything() {
+        // a single stake holding all the in-range liquidity for the whole 100s incentive
+        let (reward, secondsInsideX128) =
+            computeRewardAmount(1000, U256::zero(), 100, 200, 10, U256::zero(), x128(100) / 10, 200);
+        assert_eq!(reward, 1000);
+        assert_eq!(secondsInsideX128, x128(100));
+    }
+
+    #[test]
+    fn half_the_liquidity_seconds_gets_half_the_reward() {
+        let (reward, secondsInsideX128) =
+            computeRewardAmount(1000, U256::zero(), 100, 200, 5, U256::zero(), x128(100) / 10, 200);
+        assert_eq!(reward, 500);
+        assert_eq!(secondsInsideX128, x128(50));
+    }
+
+    #[test]
+    fn rewards_dilute_after_the_end_time() {
+        // claiming 100s after the end counts the extra time in the denominator
+        let (reward, _) =
+            computeRewardAmount(1000, U256::zero(), 100, 200, 10, U256::zero(), x128(100) / 10, 300);
+        assert_eq!(reward, 500);
+    }
+
+    #[test]
+    fn claimed_seconds_are_excluded() {
+        // half of the incentive was already claimed, the remaining half of the reward is left for 50s
+        let (reward, _) =
+            computeRewardAmount(500, x128(50), 100, 200, 10, x128(50) / 10, x128(100) / 10, 200);
+        assert_eq!(reward, 500);
+    }
+
+    #[test]
+    #[should_panic(expected = "current time before start time")]
+    fn panics_before_the_start_time() {
+        computeRewardAmount(1000, U256::zero(), 100, 200, 10, U256::zero(), U256::zero(), 99);
+    }
+}
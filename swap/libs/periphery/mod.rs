@@ -6,5 +6,5 @@ pub mod NFTDescriptor;
 pub mod NFTSVG;
 pub mod HexStrings;
 // pub mod callback_validation;
-// no use
-// pub mod PositionKey;
\ No newline at end of file
+pub mod PositionKey;
+pub mod RewardMath;
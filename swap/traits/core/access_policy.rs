@@ -0,0 +1,26 @@
+#![allow(non_snake_case)]
+use primitives::Address;
+
+#[openbrush::wrapper]
+pub type AccessPolicyRef = dyn AccessPolicy;
+
+/// @title Access policy of a permissioned pool
+/// @notice Queried by a pool created with an access policy before every mint and swap. Pools created without one
+/// never call it.
+/// @dev Called without allowing reentry, so a policy can not call back into the pool
+#[openbrush::trait_definition]
+pub trait AccessPolicy {
+    /// @notice Whether liquidity may be minted to the account
+    /// @dev A position manager is the recipient of the liquidity of all its positions, so a policy allowing a manager
+    /// relies on it to check the owners of its positions, see NonfungiblePositionManager
+    /// @param account The recipient of the minted position, or the owner of a position the manager mints for
+    #[ink(message)]
+    fn canProvideLiquidity(&self, account: Address) -> bool;
+
+    /// @notice Whether a swap may be made
+    /// @dev Swaps through a router have the router as sender, so the router must be allowed as well
+    /// @param sender The caller of swap
+    /// @param recipient The receiver of the output of the swap
+    #[ink(message)]
+    fn canTrade(&self, sender: Address, recipient: Address) -> bool;
+}
@@ -1,15 +1,32 @@
 use openbrush::{
     traits::{
         AccountId,
+        Balance,
     },
 };
-use primitives::Address;
+use primitives::{Address, Uint24};
+use scale::{Decode, Encode};
 
 
 #[openbrush::wrapper]
 pub type FactoryRef = dyn Factory;
 
-
+/// @notice Optional per-pool settings fixed when the pool is created
+#[derive(Default, Debug, Clone, PartialEq, Eq, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub struct PoolConfig {
+    // the PoolHooks contract called by the pool, ADDRESS0 for none
+    pub hooks: Address,
+    // whether the swap fee of the pool can be changed after creation
+    pub dynamic_fee: bool,
+    // the contract allowed to change the fee besides the factory owner, ADDRESS0 for the owner only
+    pub fee_controller: Address,
+    // the bounds of a dynamic fee in hundredths of a bip, the fee tier of the pool must be within them
+    pub min_fee: Uint24,
+    pub max_fee: Uint24,
+    // the AccessPolicy contract gating mint and swap, ADDRESS0 for a permissionless pool
+    pub access_policy: Address,
+}
 
 #[openbrush::trait_definition]
 pub trait Factory{
@@ -19,4 +36,31 @@ pub trait Factory{
     //此处原有modifier,限制不可以使用delegateCall的方式调用该方法,因为ink!中没有delegate call 调用,所以按时不使用NoDelegateCall
     #[ink(message,payable)]
     fn create_pool(&mut self,fee:u32,token_a:Address,token_b:Address)->AccountId;
+
+    /// @notice Creates a pool like create_pool, with the given per-pool settings
+    /// @dev Only the owner may create a pool with settings other than the default ones
+    /// @param config The settings stored in the pool, e.g. its hooks contract
+    #[ink(message,payable)]
+    fn create_pool_with_config(&mut self,fee:u32,token_a:Address,token_b:Address,config:PoolConfig)->AccountId;
+
+    /// @notice The number of pools created by the factory
+    #[ink(message)]
+    fn get_all_pools_length(&self)->u32;
+
+    /// @notice The pool created at the given index, ADDRESS0 if out of range
+    #[ink(message)]
+    fn get_all_pools(&self,index:u32)->AccountId;
+
+    /// @notice The account allowed to collect protocol fees from every pool besides the owner, e.g. a treasury
+    #[ink(message)]
+    fn get_fee_collector(&self)->Address;
+
+    /// @notice The (feeProtocol0, feeProtocol1) new pools of the fee tier start with, (0, 0) if not set
+    #[ink(message)]
+    fn get_fee_protocol_default(&self,fee:u32)->(u8,u8);
+
+    /// @notice The exact value create_pool and create_pool_with_config must be called with
+    /// @dev The pool creation fee plus the storage deposit endowed to the new pool, any value above it is refunded
+    #[ink(message)]
+    fn get_pool_creation_cost(&self)->Balance;
 }
\ No newline at end of file
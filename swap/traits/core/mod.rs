@@ -3,4 +3,10 @@ pub mod pool_action;
 //由于ink!合约的初始化合约的方式修改了.此处不需要使用pool_deployer的方式来部署,故pool_deployer废弃不用.
 // pub mod pool_deployer;
 pub mod no_delegate_call;
-pub mod pool_owner_action;
\ No newline at end of file
+pub mod pool_owner_action;
+pub mod pool_state;
+pub mod pool_hooks;
+pub mod pool_guardian;
+pub mod pool_upgrade;
+pub mod access_policy;
+pub mod pool_derived_state;
@@ -83,6 +83,7 @@ pub trait PoolAction {
     /// @param tickUpper The upper tick of the position in which to add liquidity
     /// @param amount The amount of liquidity to mint
     /// @param data Any data that should be passed through to the callback
+    /// @param salt Optional salt keeping this position apart from other positions of the recipient on the same range
     /// @return amount0 The amount of token0 that was paid to mint the given amount of liquidity. Matches the value in the callback
     /// @return amount1 The amount of token1 that was paid to mint the given amount of liquidity. Matches the value in the callback
     #[ink(message)]
@@ -93,6 +94,7 @@ pub trait PoolAction {
         tickUpper: Int24,
         amount: u128,
         data: Vec<u8>,
+        salt: Option<[u8; 32]>,
     ) -> (U256, U256);
 
     /// @notice Burn liquidity from the sender and account tokens owed for the liquidity to the position
@@ -101,17 +103,27 @@ pub trait PoolAction {
     /// @param tickLower The lower tick of the position for which to burn liquidity
     /// @param tickUpper The upper tick of the position for which to burn liquidity
     /// @param amount How much liquidity to burn
+    /// @param salt The salt the position was minted with, None for the default position
     /// @return amount0 The amount of token0 sent to the recipient
     /// @return amount1 The amount of token1 sent to the recipient
     #[ink(message)]
-    fn burn(&mut self, tickLower: Int24, tickUpper: Int24, amount: u128) -> (U256, U256);
+    fn burn(
+        &mut self,
+        tickLower: Int24,
+        tickUpper: Int24,
+        amount: u128,
+        salt: Option<[u8; 32]>,
+    ) -> (U256, U256);
 
+    /// @notice Returns the information about a position by the position's owner, range and salt
+    /// @param salt The salt the position was minted with, None for the default position
     #[ink(message)]
     fn positions(
         &self,
         position_address: Address,
         tick_lower: Int24,
         tick_upper: Int24,
+        salt: Option<[u8; 32]>,
     ) -> Position::Info;
 
     /// @notice Collects tokens owed to a position
@@ -124,6 +136,7 @@ pub trait PoolAction {
     /// @param tickUpper The upper tick of the position for which to collect fees
     /// @param amount0Requested How much token0 should be withdrawn from the fees owed
     /// @param amount1Requested How much token1 should be withdrawn from the fees owed
+    /// @param salt The salt the position was minted with, None for the default position
     /// @return amount0 The amount of fees collected in token0
     /// @return amount1 The amount of fees collected in token1
     #[ink(message)]
@@ -134,6 +147,7 @@ pub trait PoolAction {
         tickUpper: Int24,
         amount0Requested: u128,
         amount1Requested: u128,
+        salt: Option<[u8; 32]>,
     ) -> (u128, u128);
 
     /// @notice Swap token0 for token1, or token1 for token0
@@ -169,4 +183,17 @@ pub trait PoolAction {
 
     #[ink(message)]
     fn get_tickspacing(&self)->Int24;
+
+    /// @notice Sends any token0/token1 held above the reserves to the recipient
+    /// @dev Used to recover donations or tokens sent to the pool by mistake
+    /// @param recipient The address which will receive the surplus
+    /// @return amount0 The amount of token0 sent
+    /// @return amount1 The amount of token1 sent
+    #[ink(message)]
+    fn skim(&mut self, recipient: Address) -> (u128, u128);
+
+    /// @notice Sets the reserves to the pool's current token balances
+    /// @dev Used to absorb donations into the pool or to follow a rebasing token
+    #[ink(message)]
+    fn sync(&mut self);
 }
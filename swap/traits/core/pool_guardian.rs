@@ -0,0 +1,40 @@
+#![allow(non_snake_case)]
+use openbrush::modifier_definition;
+
+#[openbrush::wrapper]
+pub type PoolGuardianRef = dyn PoolGuardian;
+
+/// @dev Blocks swap, mint and flash while the pool is paused. burn and collect are never paused so LPs can always exit.
+#[modifier_definition]
+pub fn whenNotPaused<T, F, R>(instance: &mut T, body: F) -> R
+where
+    T: PoolGuardian,
+    F: FnOnce(&mut T) -> R,
+{
+    assert!(!instance.paused(), "PAUSED");
+    body(instance)
+}
+
+// @title Emergency pause of a pool
+// @notice Contains pool methods that may only be called by the factory, on behalf of its owner or guardian
+#[openbrush::trait_definition]
+pub trait PoolGuardian {
+    /// @notice Pause or unpause swap, mint and flash on the pool
+    /// @param paused Whether the pool should be paused
+    #[ink(message)]
+    fn setPaused(&mut self, paused: bool);
+
+    /// @notice Set the circuit breaker of the pool
+    /// @dev The pool pauses itself after a single swap moves the tick by more than maxTickMove ticks
+    /// @param maxTickMove The maximum tick movement of a swap, 0 disables the circuit breaker
+    #[ink(message)]
+    fn setMaxTickMove(&mut self, maxTickMove: u32);
+
+    /// @notice Whether swap, mint and flash are currently blocked
+    #[ink(message)]
+    fn paused(&self) -> bool;
+
+    /// @notice The circuit breaker threshold in ticks, 0 if disabled
+    #[ink(message)]
+    fn maxTickMove(&self) -> u32;
+}
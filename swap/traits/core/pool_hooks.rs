@@ -0,0 +1,148 @@
+#![allow(non_snake_case)]
+use primitives::{Address, Int24, Int256, U160, U256};
+
+use crate::traits::errors::pool::PoolError;
+
+#[openbrush::wrapper]
+pub type PoolHooksRef = dyn PoolHooks;
+
+// the bits of getHookPermissions, each one enables the matching call from the pool
+pub const BEFORE_INITIALIZE_FLAG: u16 = 1 << 0;
+pub const AFTER_INITIALIZE_FLAG: u16 = 1 << 1;
+pub const BEFORE_MINT_FLAG: u16 = 1 << 2;
+pub const AFTER_MINT_FLAG: u16 = 1 << 3;
+pub const BEFORE_BURN_FLAG: u16 = 1 << 4;
+pub const AFTER_BURN_FLAG: u16 = 1 << 5;
+pub const BEFORE_SWAP_FLAG: u16 = 1 << 6;
+pub const AFTER_SWAP_FLAG: u16 = 1 << 7;
+
+/// @title Pool hooks
+/// @notice Extension points called by a pool created with a hooks contract. The pool reads the permission bitmask
+/// once at deployment and only calls the hooks whose bit is set.
+/// @dev Hooks are called while the pool is locked and without allowing reentry, so a hook can never call back into
+/// the pool it is invoked from. Returning an error from any hook but the burn hooks reverts the whole pool action, the
+/// burn hooks can not block liquidity from being withdrawn and their result is ignored.
+#[openbrush::trait_definition]
+pub trait PoolHooks {
+    /// @notice Returns the bitmask of hooks the pool should call, see the *_FLAG constants
+    #[ink(message)]
+    fn getHookPermissions(&self) -> u16;
+
+    /// @notice Called before the pool price is initialized
+    /// @param sender The caller of initialize
+    /// @param sqrtPriceX96 The initial sqrt price of the pool as a Q64.96
+    #[ink(message)]
+    fn beforeInitialize(&mut self, sender: Address, sqrtPriceX96: U160) -> Result<(), PoolError>;
+
+    /// @notice Called after the pool price is initialized
+    /// @param sender The caller of initialize
+    /// @param sqrtPriceX96 The initial sqrt price of the pool as a Q64.96
+    /// @param tick The initial tick of the pool
+    #[ink(message)]
+    fn afterInitialize(
+        &mut self,
+        sender: Address,
+        sqrtPriceX96: U160,
+        tick: Int24,
+    ) -> Result<(), PoolError>;
+
+    /// @notice Called before liquidity is added to a position
+    /// @param sender The caller of mint
+    /// @param recipient The owner of the position
+    /// @param tickLower The lower tick of the position
+    /// @param tickUpper The upper tick of the position
+    /// @param amount The amount of liquidity to mint
+    /// @param salt The salt of the position
+    #[ink(message)]
+    fn beforeMint(
+        &mut self,
+        sender: Address,
+        recipient: Address,
+        tickLower: Int24,
+        tickUpper: Int24,
+        amount: u128,
+        salt: [u8; 32],
+    ) -> Result<(), PoolError>;
+
+    /// @notice Called after liquidity is added to a position and paid for
+    /// @param amount0 The amount of token0 paid for the liquidity
+    /// @param amount1 The amount of token1 paid for the liquidity
+    #[ink(message)]
+    fn afterMint(
+        &mut self,
+        sender: Address,
+        recipient: Address,
+        tickLower: Int24,
+        tickUpper: Int24,
+        amount: u128,
+        salt: [u8; 32],
+        amount0: U256,
+        amount1: U256,
+    ) -> Result<(), PoolError>;
+
+    /// @notice Called before liquidity is removed from a position
+    /// @dev Can not veto the burn, an error or trap is ignored by the pool
+    /// @param owner The owner of the position, i.e. the caller of burn
+    /// @param tickLower The lower tick of the position
+    /// @param tickUpper The upper tick of the position
+    /// @param amount The amount of liquidity to burn
+    /// @param salt The salt of the position
+    #[ink(message)]
+    fn beforeBurn(
+        &mut self,
+        owner: Address,
+        tickLower: Int24,
+        tickUpper: Int24,
+        amount: u128,
+        salt: [u8; 32],
+    ) -> Result<(), PoolError>;
+
+    /// @notice Called after liquidity is removed from a position
+    /// @dev Can not veto the burn, an error or trap is ignored by the pool
+    /// @param amount0 The amount of token0 owed to the position for the burned liquidity
+    /// @param amount1 The amount of token1 owed to the position for the burned liquidity
+    #[ink(message)]
+    fn afterBurn(
+        &mut self,
+        owner: Address,
+        tickLower: Int24,
+        tickUpper: Int24,
+        amount: u128,
+        salt: [u8; 32],
+        amount0: U256,
+        amount1: U256,
+    ) -> Result<(), PoolError>;
+
+    /// @notice Called before a swap is computed
+    /// @param sender The caller of swap
+    /// @param recipient The receiver of the output of the swap
+    /// @param zeroForOne The direction of the swap
+    /// @param amountSpecified The amount of the swap, exact input when positive, exact output when negative
+    /// @param sqrtPriceLimitX96 The sqrt price limit of the swap
+    #[ink(message)]
+    fn beforeSwap(
+        &mut self,
+        sender: Address,
+        recipient: Address,
+        zeroForOne: bool,
+        amountSpecified: Int256,
+        sqrtPriceLimitX96: U160,
+    ) -> Result<(), PoolError>;
+
+    /// @notice Called after a swap is settled
+    /// @param amount0 The delta of the token0 balance of the pool
+    /// @param amount1 The delta of the token1 balance of the pool
+    /// @param sqrtPriceX96 The sqrt price of the pool after the swap
+    /// @param tick The tick of the pool after the swap
+    #[ink(message)]
+    fn afterSwap(
+        &mut self,
+        sender: Address,
+        recipient: Address,
+        zeroForOne: bool,
+        amount0: Int256,
+        amount1: Int256,
+        sqrtPriceX96: U160,
+        tick: Int24,
+    ) -> Result<(), PoolError>;
+}
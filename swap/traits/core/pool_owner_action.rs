@@ -1,4 +1,4 @@
-use primitives::Address;
+use primitives::{Address, Uint24};
 
 
 #[openbrush::wrapper]
@@ -15,6 +15,7 @@ pub trait PoolOwnerActions {
     fn setFeeProtocol(&mut self,feeProtocol0:u8, feeProtocol1:u8);
 
     /// @notice Collect the protocol fee accrued to the pool
+    /// @dev Callable by the factory owner or the fee collector of the factory
     /// @param recipient The address to which collected protocol fees should be sent
     /// @param amount0Requested The maximum amount of token0 to send, can be 0 to collect fees in only token1
     /// @param amount1Requested The maximum amount of token1 to send, can be 0 to collect fees in only token0
@@ -28,6 +29,12 @@ pub trait PoolOwnerActions {
         amount1Requested:u128
     )->(u128 , u128);
 
+    /// @notice Set the swap fee of a dynamic-fee pool, also used for flash loans
+    /// @dev Callable by the fee controller of the pool or the factory owner
+    /// @param fee The new fee in hundredths of a bip, within the bounds fixed at creation
+    #[ink(message)]
+    fn setFee(&mut self, fee:Uint24);
+
     #[ink(message)]
     fn get_factory(&self)->Address;
 }
\ No newline at end of file
@@ -0,0 +1,133 @@
+#![allow(non_snake_case)]
+#[cfg(feature = "std")]
+use ink_storage::traits::StorageLayout;
+use ink_storage::traits::{PackedLayout, SpreadAllocate, SpreadLayout};
+use libs::core::oracle::Observation;
+use libs::core::Tick;
+use primitives::{Address, Int24, Uint24, U256};
+use scale::{Decode, Encode};
+
+use crate::traits::core::pool_action::Slot0;
+
+#[openbrush::wrapper]
+pub type PoolStateRef = dyn PoolState;
+
+// accumulated protocol fees in token0/token1 units
+#[derive(
+    Default, Debug, Clone, PartialEq, Eq, Encode, Decode, SpreadLayout, SpreadAllocate, PackedLayout,
+)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo, StorageLayout))]
+pub struct ProtocolFees {
+    pub token0: u128,
+    pub token1: u128,
+}
+
+/// @notice Every piece of public pool state, read in a single call
+/// @dev Mirrors IUniswapV3PoolImmutables and the scalar part of IUniswapV3PoolState
+#[derive(Default, Debug, Clone, PartialEq, Eq, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub struct PoolStateInfo {
+    pub factory: Address,
+    pub token0: Address,
+    pub token1: Address,
+    pub fee: Uint24,
+    pub feeTier: Uint24,
+    pub tickSpacing: Int24,
+    pub maxLiquidityPerTick: u128,
+    pub slot0: Slot0,
+    pub feeGrowthGlobal0X128: U256,
+    pub feeGrowthGlobal1X128: U256,
+    pub protocolFees: ProtocolFees,
+    pub liquidity: u128,
+    pub reserve0: u128,
+    pub reserve1: u128,
+    pub hooks: Address,
+    pub hookPermissions: u16,
+}
+
+/// @title Pool state that never changes and pool state that can change
+/// @notice These methods compose the pool's state, and can change with any frequency including multiple times
+/// per transaction
+#[openbrush::trait_definition]
+pub trait PoolState {
+    /// @notice The first of the two tokens of the pool, sorted by address
+    #[ink(message)]
+    fn token0(&self) -> Address;
+
+    /// @notice The second of the two tokens of the pool, sorted by address
+    #[ink(message)]
+    fn token1(&self) -> Address;
+
+    /// @notice The pool's fee in hundredths of a bip, i.e. 1e-6
+    /// @dev The current swap fee, which differs from the fee tier once a dynamic-fee pool has been updated
+    #[ink(message)]
+    fn fee(&self) -> Uint24;
+
+    /// @notice The fee the pool was created with, which keys the pool in the factory
+    #[ink(message)]
+    fn feeTier(&self) -> Uint24;
+
+    /// @notice The dynamic fee settings of the pool: enabled, fee controller, min fee and max fee
+    #[ink(message)]
+    fn dynamicFee(&self) -> (bool, Address, Uint24, Uint24);
+
+    /// @notice The maximum amount of position liquidity that can use any tick in the range
+    #[ink(message)]
+    fn maxLiquidityPerTick(&self) -> u128;
+
+    /// @notice The fee growth as a Q128.128 fees of token0 collected per unit of liquidity for the entire life of the pool
+    #[ink(message)]
+    fn feeGrowthGlobal0X128(&self) -> U256;
+
+    /// @notice The fee growth as a Q128.128 fees of token1 collected per unit of liquidity for the entire life of the pool
+    #[ink(message)]
+    fn feeGrowthGlobal1X128(&self) -> U256;
+
+    /// @notice The amounts of token0 and token1 that are owed to the protocol
+    #[ink(message)]
+    fn protocolFees(&self) -> ProtocolFees;
+
+    /// @notice The currently in range liquidity available to the pool
+    #[ink(message)]
+    fn liquidity(&self) -> u128;
+
+    /// @notice The token0 and token1 balances the pool has accounted for
+    /// @dev Tokens sent to the pool outside of a callback are not counted until #sync, and can be taken with #skim
+    #[ink(message)]
+    fn getReserves(&self) -> (u128, u128);
+
+    /// @notice The PoolHooks contract of the pool and the hooks it enabled, ADDRESS0 and 0 for none
+    #[ink(message)]
+    fn hooks(&self) -> (Address, u16);
+
+    /// @notice The AccessPolicy contract gating mint and swap, ADDRESS0 for a permissionless pool
+    #[ink(message)]
+    fn accessPolicy(&self) -> Address;
+
+    /// @notice Look up information about a specific tick in the pool
+    /// @param tick The tick to look up
+    /// @return the tick info, or the default value if the tick is not initialized
+    #[ink(message)]
+    fn ticks(&self, tick: Int24) -> Tick::Info;
+
+    /// @notice Returns 256 packed tick initialized boolean values. See TickBitmap for more information
+    #[ink(message)]
+    fn tickBitmap(&self, wordPosition: i16) -> U256;
+
+    /// @notice Returns data about a specific observation index
+    /// @param index The element of the observations array to fetch
+    #[ink(message)]
+    fn observations(&self, index: u16) -> Observation;
+
+    /// @notice Returns the all-time fee growth inside a tick range, per unit of liquidity
+    /// @param tickLower The lower tick of the range
+    /// @param tickUpper The upper tick of the range
+    /// @return feeGrowthInside0X128 The all-time fee growth in token0 inside the range
+    /// @return feeGrowthInside1X128 The all-time fee growth in token1 inside the range
+    #[ink(message)]
+    fn getFeeGrowthInside(&self, tickLower: Int24, tickUpper: Int24) -> (U256, U256);
+
+    /// @notice Returns every piece of public pool state in one struct
+    #[ink(message)]
+    fn pool_state(&self) -> PoolStateInfo;
+}
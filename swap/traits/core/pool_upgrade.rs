@@ -0,0 +1,25 @@
+#![allow(non_snake_case)]
+use openbrush::traits::Hash;
+
+#[openbrush::wrapper]
+pub type PoolUpgradeRef = dyn PoolUpgrade;
+
+// @title Upgradeable pool code
+// @notice Contains pool methods that may only be called by the factory, once an upgrade has passed its timelock
+#[openbrush::trait_definition]
+pub trait PoolUpgrade {
+    /// @notice Replace the code of the pool, keeping its storage and address
+    /// @dev The new code must keep the storage layout of the pool, with new fields only appended
+    /// @param code_hash The hash of the uploaded pool code to switch to
+    #[ink(message)]
+    fn setCodeHash(&mut self, code_hash: Hash);
+
+    /// @notice Bring the storage up to the version of the current code
+    /// @dev Called by the factory right after setCodeHash, so it runs the new code. Does nothing when up to date
+    #[ink(message)]
+    fn migrate(&mut self);
+
+    /// @notice The version of the storage layout the pool was written with
+    #[ink(message)]
+    fn storageVersion(&self) -> u32;
+}
@@ -1,8 +1,9 @@
 use openbrush::{modifier_definition, contracts::traits::psp34::Id};
 use ink_env::DefaultEnvironment;
 use ink_prelude::string::String;
+use ink_prelude::vec::Vec;
 use ink_storage::traits::{SpreadAllocate, SpreadLayout};
-use primitives::{Address, Int24, Uint24, Uint256, Uint96, U256};
+use primitives::{Address, Int24, Uint24, Uint256, Uint96, U160, U256};
 
 #[cfg(feature = "std")]
 use ink_storage::traits::StorageLayout;
@@ -11,15 +12,25 @@ use scale::{Decode, Encode};
 #[openbrush::wrapper]
 pub type PositionManagerRef = dyn PositionManager;
 
+// scoped permissions on a position NFT, which can be granted to an account without approving it for the token
+pub const PERMISSION_COLLECT: u8 = 1;
+pub const PERMISSION_INCREASE: u8 = 1 << 1;
+pub const PERMISSION_DECREASE: u8 = 1 << 2;
+pub const PERMISSION_TRANSFER: u8 = 1 << 3;
+pub const PERMISSION_ALL: u8 = PERMISSION_COLLECT | PERMISSION_INCREASE | PERMISSION_DECREASE | PERMISSION_TRANSFER;
+
+/// @dev The owner and the approved operator of the token can do everything, other accounts need all of the
+/// permissions asked for
 #[modifier_definition]
-pub fn isAuthorizedForToken<T, F, R>(instance: &mut T, body: F, tokenId: Id) -> R
+pub fn isAuthorizedForToken<T, F, R>(instance: &mut T, body: F, tokenId: Id, permission: u8) -> R
 where
     T: PositionManager,
     F: FnOnce(&mut T) -> R,
 {
     let spender = ink_env::caller::<DefaultEnvironment>();
     assert!(
-        instance._isApprovedOrOwner(spender, tokenId),
+        instance._isApprovedOrOwner(spender, tokenId.clone())
+            || instance._hasPermission(spender, tokenId, permission),
         "Not approved"
     );
     body(instance)
@@ -136,6 +147,28 @@ pub trait PositionManager {
     ) -> (U256, U256);
 
     fn _isApprovedOrOwner(&self, spender: Address, tokenId: Id) -> bool;
+
+    /// @dev Returns whether `spender` was granted all of the permissions by the current owner of `tokenId`
+    fn _hasPermission(&self, spender: Address, tokenId: Id, permission: u8) -> bool;
+
+    /// @notice Grants scoped permissions on a position to an account, on top of those it already has
+    /// @dev Only the owner or the approved operator can grant. The grants belong to the current owner and do not apply
+    /// once the token has another owner
+    /// @param tokenId The ID of the token the permissions are for
+    /// @param grantee The account receiving the permissions
+    /// @param permissions A combination of the PERMISSION_ flags
+    #[ink(message)]
+    fn grantPermissions(&mut self, tokenId: Id, grantee: Address, permissions: u8);
+
+    /// @notice Revokes scoped permissions on a position from an account
+    /// @dev The owner, the approved operator and the grantee itself can revoke
+    /// @param permissions A combination of the PERMISSION_ flags
+    #[ink(message)]
+    fn revokePermissions(&mut self, tokenId: Id, grantee: Address, permissions: u8);
+
+    /// @notice Returns the permissions the current owner of the position granted to the account
+    #[ink(message)]
+    fn getPermissions(&self, tokenId: Id, grantee: Address) -> u8;
     /// @notice Returns the position information associated with a given token ID.
     /// @dev Throws if the token ID is not valid.
     /// @param tokenId The ID of the token that represents the position
@@ -215,12 +248,93 @@ pub trait PositionManager {
         amount1Max: u128,
     ) -> (U256, U256);
 
+    /// @notice Collects up to a maximum amount of fees owed to each of many positions, paying the recipient once per token
+    /// @param collects The ID of each NFT with the maximum amounts of token0 and token1 to collect from it
+    /// @param recipient The account that should receive the tokens
+    /// @return amounts The total amount collected of each token
+    #[ink(message, payable)]
+    fn collectMany(&mut self, collects: Vec<(Id, u128, u128)>, recipient: Address) -> Vec<(Address, U256)>;
+
     /// @notice Burns a token ID, which deletes it from the NFT contract. The token must have 0 liquidity and all tokens
     /// must be collected first.
     /// @param tokenId The ID of the token that is being burned
     #[ink(message, payable)]
     fn burn(&mut self,tokenId:Id);
 
+    /// @notice Moves a position to a new tick range of the same pool in one call. All liquidity and tokens owed are
+    /// withdrawn, optionally swapped through the pool to the ratio of the new range, and minted as a new position NFT
+    /// to the owner, who also gets back the tokens the new position could not use. The old token is burned
+    /// @dev The swap moves the pool price, so other accounts need PERMISSION_ALL, a grant to decrease and increase
+    /// is not enough
+    /// @param tokenId The ID of the token that is rebalanced
+    /// @param tickLower The lower tick of the new range
+    /// @param tickUpper The upper tick of the new range
+    /// @param swapSqrtPriceLimitX96 None to skip the swap, otherwise the price limit of the swap to the new ratio
+    /// @param amount0Min The minimum amount of token0 in the new position, which serves as a slippage check
+    /// @param amount1Min The minimum amount of token1 in the new position, which serves as a slippage check
+    /// @param deadline The time by which the transaction must be included to effect the change
+    /// @return tokenId The ID of the token that represents the new position
+    /// @return liquidity The amount of liquidity of the new position
+    /// @return amount0 The amount of token0 in the new position
+    /// @return amount1 The amount of token1 in the new position
+    #[ink(message, payable)]
+    fn rebalance(
+        &mut self,
+        tokenId: Id,
+        tickLower: Int24,
+        tickUpper: Int24,
+        swapSqrtPriceLimitX96: Option<U160>,
+        amount0Min: U256,
+        amount1Min: U256,
+        deadline: u64,
+    ) -> (Id, u128, U256, U256);
+
+    /// @notice Creates a new position wrapped in a NFT from a single token. Part of the input is swapped through the
+    /// pool to the ratio of the range, the rest is added as liquidity along with the swap output, and what the
+    /// position could not use is returned to the caller
+    /// @param tokenIn The token paid by the caller, which must be token0 or token1
+    /// @param amountIn The amount of tokenIn paid by the caller
+    /// @param swapSqrtPriceLimitX96 The price limit of the swap to the ratio of the range
+    /// @param amount0Min The minimum amount of token0 in the position, which serves as a slippage check
+    /// @param amount1Min The minimum amount of token1 in the position, which serves as a slippage check
+    /// @param recipient The account receiving the position NFT
+    /// @param deadline The time by which the transaction must be included to effect the change
+    /// @return tokenId The ID of the token that represents the minted position
+    /// @return liquidity The amount of liquidity for this position
+    /// @return amount0 The amount of token0 in the position
+    /// @return amount1 The amount of token1 in the position
+    #[ink(message, payable)]
+    fn zap(
+        &mut self,
+        tokenIn: Address,
+        token0: Address,
+        token1: Address,
+        fee: Uint24,
+        tickLower: Int24,
+        tickUpper: Int24,
+        amountIn: U256,
+        swapSqrtPriceLimitX96: U160,
+        amount0Min: U256,
+        amount1Min: U256,
+        recipient: Address,
+        deadline: u64,
+    ) -> (Id, u128, U256, U256);
+
+    /// @notice Moves a fraction of the liquidity and tokens owed of a position to a new position NFT on the same range
+    /// @param tokenId The ID of the token that is split
+    /// @param liquidityFraction The fraction moved to the new position, scaled by 1e18 and less than 1e18
+    /// @param recipient The account receiving the new position NFT
+    /// @return tokenId The ID of the new token
+    /// @return liquidity The liquidity of the new position
+    #[ink(message)]
+    fn split(&mut self, tokenId: Id, liquidityFraction: U256, recipient: Address) -> (Id, u128);
+
+    /// @notice Merges positions of the same owner, pool and range into the first of them, burning the others
+    /// @param tokenIds The IDs of the tokens that are merged, the first one keeps the merged position
+    /// @return liquidity The liquidity of the merged position
+    #[ink(message)]
+    fn merge(&mut self, tokenIds: Vec<Id>) -> u128;
+
     #[ink(message)]
     fn getApproved(&self,id:Id)->Address;
 }
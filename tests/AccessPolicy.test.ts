@@ -0,0 +1,100 @@
+import { blake2AsU8a } from '@polkadot/util-crypto'
+import { network } from 'redspot'
+import { expect, setupContract, fromSigner } from './helpers'
+import { positionManagerFixture, DEADLINE, SQRT_PRICE_1_1, ZERO_ADDRESS } from './shared/fixtures'
+
+const { api } = network
+
+// the RoleType of ink_lang::selector_id!(name)
+const roleId = (name: string): number => {
+  const [b0, b1, b2, b3] = blake2AsU8a(name, 256)
+  return ((b0 << 24) | (b1 << 16) | (b2 << 8) | b3) >>> 0
+}
+const LIQUIDITY_PROVIDER = roleId('LIQUIDITY_PROVIDER')
+const MANAGER = roleId('MANAGER')
+
+describe('AllowListPolicy', () => {
+  after(() => {
+    return api.disconnect()
+  })
+
+  const FEE_MEDIUM = 3000
+
+  // a permissioned 0.3% pool next to the fixture pool, only alice is on the list
+  async function setup(trustManager = true) {
+    await api.isReady
+    const fixture = await positionManagerFixture()
+    const { alice, factory, nft, nftQuery, nftTx, token0, token1 } = fixture
+    const { contract: policy, tx: policyTx } = await setupContract('AllowListPolicyContract', 'new')
+    const config = {
+      hooks: ZERO_ADDRESS,
+      dynamicFee: false,
+      feeController: ZERO_ADDRESS,
+      minFee: 0,
+      maxFee: 0,
+      accessPolicy: policy.address,
+    }
+    await factory.tx.createPoolWithConfig(FEE_MEDIUM, token0.address, token1.address, config, { value: 125000000 })
+    await nftTx.createAndInitializePoolIfNecessary(token0.address, token1.address, FEE_MEDIUM, SQRT_PRICE_1_1)
+    await policyTx.grantMany(LIQUIDITY_PROVIDER, [alice.address])
+    if (trustManager) {
+      await policyTx.grantRole(MANAGER, nft.address)
+    }
+    const mintArgs = (recipient) => [token0.address, token1.address, FEE_MEDIUM, -600, 600, 100000, 100000, 0, 0, recipient, DEADLINE]
+    // mints a position in the permissioned pool as alice, returns its token id
+    const mint = async (recipient) => {
+      const id = (await nftQuery.mint(...mintArgs(recipient))).output[0]
+      await nftTx.mint(...mintArgs(recipient))
+      return id
+    }
+    return { ...fixture, policyTx, mintArgs, mint }
+  }
+
+  it('rejects positions held by a manager it does not trust', async () => {
+    const { alice, nftTx, mintArgs } = await setup(false)
+    await expect(nftTx.mint(...mintArgs(alice.address))).to.eventually.be.rejected
+  })
+
+  it('checks the owner of a position minted by a trusted manager', async () => {
+    const { alice, bob, nft, nftQuery, mintArgs, mint } = await setup()
+    const id = await mint(alice.address)
+    expect((await nftQuery.ownerOf(id)).output.toString()).to.equal(alice.address.toString())
+
+    // bob is not on the list, neither as the owner of a position minted by alice nor minting himself
+    await expect(mint(bob.address)).to.eventually.be.rejected
+    await expect(fromSigner(nft, bob.address).tx.mint(...mintArgs(bob.address))).to.eventually.be.rejected
+  })
+
+  it('checks the owner when liquidity is added to a position', async () => {
+    const { alice, bob, nft, nftTx, policyTx, mint } = await setup()
+    const id = await mint(alice.address)
+    await nftTx.increaseLiquidity(id, 1000, 1000, 0, 0, DEADLINE)
+
+    // the owner left the list, a listed payer can not add liquidity for them either
+    await policyTx.grantMany(LIQUIDITY_PROVIDER, [bob.address])
+    await nftTx.transfer(bob.address, id, [])
+    await policyTx.revokeMany(LIQUIDITY_PROVIDER, [bob.address])
+    const PERMISSION_INCREASE = 2
+    await fromSigner(nft, bob.address).tx.grantPermissions(id, alice.address, PERMISSION_INCREASE)
+    await expect(fromSigner(nft, bob.address).tx.increaseLiquidity(id, 1000, 1000, 0, 0, DEADLINE)).to.eventually.be.rejected
+    await expect(nftTx.increaseLiquidity(id, 1000, 1000, 0, 0, DEADLINE)).to.eventually.be.rejected
+  })
+
+  it('only transfers positions to accounts on the list', async () => {
+    const { alice, bob, nftQuery, nftTx, policyTx, mint } = await setup()
+    const id = await mint(alice.address)
+    await expect(nftTx.transfer(bob.address, id, [])).to.eventually.be.rejected
+
+    await policyTx.grantMany(LIQUIDITY_PROVIDER, [bob.address])
+    await nftTx.transfer(bob.address, id, [])
+    expect((await nftQuery.ownerOf(id)).output.toString()).to.equal(bob.address.toString())
+  })
+
+  it('leaves positions in permissionless pools alone', async () => {
+    const { bob, token0, token1, nftQuery, nftTx } = await setup()
+    const args = [token0.address, token1.address, 500, -1000, 1000, 100000, 100000, 0, 0, bob.address, DEADLINE]
+    const id = (await nftQuery.mint(...args)).output[0]
+    await nftTx.mint(...args)
+    expect((await nftQuery.ownerOf(id)).output.toString()).to.equal(bob.address.toString())
+  })
+})
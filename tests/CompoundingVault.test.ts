@@ -0,0 +1,102 @@
+import BN from 'bn.js'
+import { network } from 'redspot'
+import { expect, setupContract, fromSigner } from './helpers'
+import { positionManagerFixture, DEADLINE, FEE_LOW } from './shared/fixtures'
+
+const { api, getSigners } = network
+
+describe('CompoundingVault', () => {
+  after(() => {
+    return api.disconnect()
+  })
+
+  const BOUNTY_BPS = 100
+
+  // alice's position in [-1000, 1000] sent to the vault, bob approved the vault to spend both tokens
+  async function setup() {
+    await api.isReady
+    const fixture = await positionManagerFixture()
+    const { alice, bob, token0, token1, nftQuery, nftTx } = fixture
+    const { contract: vault, query, tx } = await setupContract('CompoundingVaultContract', 'new', fixture.factory.address, fixture.nft.address, BOUNTY_BPS)
+    const args = [token0.address, token1.address, FEE_LOW, -1000, 1000, 100000, 100000, 0, 0, alice.address, DEADLINE]
+    const [id, liquidity] = (await nftQuery.mint(...args)).output
+    await nftTx.mint(...args)
+    await nftTx.transfer(vault.address, id, [])
+    for (const token of [token0, token1]) {
+      await fromSigner(token, bob.address).tx.approve(vault.address, 10000000)
+    }
+    const liquidityOf = async (): Promise<BN> => new BN((await nftQuery.positions(id)).output[7].toString())
+    const totalShares = async (): Promise<BN> => new BN((await query.getPosition(id)).output.unwrap().totalShares.toString())
+    const sharesOf = async (owner): Promise<BN> => new BN((await query.getShares(id, owner)).output.toString())
+    return { ...fixture, vault, query, tx, id, liquidity: new BN(liquidity.toString()), liquidityOf, totalShares, sharesOf }
+  }
+
+  it('issues shares equal to the liquidity of a position sent to it', async () => {
+    const { alice, vault, nftQuery, id, liquidity, totalShares, sharesOf } = await setup()
+    expect((await nftQuery.ownerOf(id)).output.toString()).to.equal(vault.address.toString())
+    expect((await sharesOf(alice.address)).eq(liquidity)).to.equal(true)
+    expect((await totalShares()).eq(liquidity)).to.equal(true)
+  })
+
+  it('issues shares in proportion to the value deposited and returns what is not used', async () => {
+    const { bob, vault, token0, token1, id, liquidity, liquidityOf, totalShares, sharesOf, balanceOf } = await setup()
+    const balance0Before = await balanceOf(token0, bob.address)
+    const balance1Before = await balanceOf(token1, bob.address)
+    const shares = new BN((await fromSigner(vault, bob.address).query.deposit(id, 50000, 60000, DEADLINE)).output.toString())
+    await expect(fromSigner(vault, bob.address).tx.deposit(id, 50000, 60000, DEADLINE)).to.emit(vault, 'Deposited')
+
+    // the shares follow the liquidity added, up to rounding
+    expect(shares.gtn(0)).to.equal(true)
+    expect((await sharesOf(bob.address)).eq(shares)).to.equal(true)
+    expect((await totalShares()).eq(liquidity.add(shares))).to.equal(true)
+    const added = (await liquidityOf()).sub(liquidity)
+    expect(shares.sub(added).abs().lte(added.divn(1000))).to.equal(true)
+    // the surplus token1 is returned
+    const spent0 = balance0Before.sub(await balanceOf(token0, bob.address))
+    const spent1 = balance1Before.sub(await balanceOf(token1, bob.address))
+    expect(spent0.lten(50000)).to.equal(true)
+    expect(spent1.lt(new BN(60000))).to.equal(true)
+    expect((await balanceOf(token0, vault.address)).toNumber()).to.equal(0)
+    expect((await balanceOf(token1, vault.address)).toNumber()).to.equal(0)
+
+    await expect(fromSigner(vault, bob.address).tx.deposit({ u128: 1000 }, 50000, 50000, DEADLINE)).to.eventually.be.rejected
+  })
+
+  it('withdraws the part of the liquidity of the shares redeemed', async () => {
+    const { alice, bob, vault, tx, nftQuery, token0, id, liquidity, liquidityOf, totalShares, sharesOf, balanceOf } = await setup()
+    const half = liquidity.divn(2)
+    await expect(fromSigner(vault, bob.address).tx.withdraw(id, 1, bob.address, DEADLINE)).to.eventually.be.rejected
+    await expect(tx.withdraw(id, liquidity.addn(1), alice.address, DEADLINE)).to.eventually.be.rejected
+
+    const balanceBefore = await balanceOf(token0, bob.address)
+    await expect(tx.withdraw(id, half, bob.address, DEADLINE)).to.emit(vault, 'Withdrawn')
+    expect((await balanceOf(token0, bob.address)).gt(balanceBefore)).to.equal(true)
+    expect((await liquidityOf()).eq(liquidity.sub(half))).to.equal(true)
+    expect((await totalShares()).eq(liquidity.sub(half))).to.equal(true)
+    expect((await sharesOf(alice.address)).eq(liquidity.sub(half))).to.equal(true)
+
+    // the last shares take the emptied position with them
+    await tx.withdraw(id, liquidity.sub(half), alice.address, DEADLINE)
+    expect((await liquidityOf()).toNumber()).to.equal(0)
+    expect((await nftQuery.ownerOf(id)).output.toString()).to.equal(alice.address.toString())
+  })
+
+  it('compounds the fees into the position and pays the caller the bounty', async () => {
+    const { vault, token0, token1, id, liquidity, liquidityOf, totalShares, moveToTick, balanceOf } = await setup()
+    // fees for both tokens
+    await moveToTick(200)
+    await moveToTick(-200)
+
+    const [, , charlie] = await getSigners()
+    const bounty0Before = await balanceOf(token0, charlie.address)
+    const bounty1Before = await balanceOf(token1, charlie.address)
+    await expect(fromSigner(vault, charlie.address).tx.compound(id, DEADLINE)).to.emit(vault, 'Compounded')
+
+    // more liquidity for the same shares
+    expect((await liquidityOf()).gt(liquidity)).to.equal(true)
+    expect((await totalShares()).eq(liquidity)).to.equal(true)
+    const bounty0 = (await balanceOf(token0, charlie.address)).sub(bounty0Before)
+    const bounty1 = (await balanceOf(token1, charlie.address)).sub(bounty1Before)
+    expect(bounty0.gtn(0) || bounty1.gtn(0)).to.equal(true)
+  })
+})
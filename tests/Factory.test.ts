@@ -0,0 +1,287 @@
+import BN from 'bn.js'
+import { artifacts, network, patract } from 'redspot'
+import { expect, fromSigner, setupContract } from './helpers'
+import { positionManagerFixture, DEADLINE, FEE_LOW, SQRT_PRICE_1_1, TICK_SPACING_LOW, ZERO_ADDRESS } from './shared/fixtures'
+
+const { api, getSigners } = network
+const { getContractAt } = patract
+
+describe('Factory', () => {
+  after(() => {
+    return api.disconnect()
+  })
+
+  const FEE_MEDIUM = 3000
+  const POOL_ENDOWMENT = 125000000
+
+  const defaultConfig = {
+    hooks: ZERO_ADDRESS,
+    dynamicFee: false,
+    feeController: ZERO_ADDRESS,
+    minFee: 0,
+    maxFee: 0,
+    accessPolicy: ZERO_ADDRESS,
+  }
+
+  async function setup() {
+    await api.isReady
+    const fixture = await positionManagerFixture()
+    const { alice, token0, token1, nftQuery, nftTx } = fixture
+    // liquidity for the swaps
+    const mintArgs = [token0.address, token1.address, FEE_LOW, -1000, 1000, 100000, 100000, 0, 0, alice.address, DEADLINE]
+    const [id, liquidity] = (await nftQuery.mint(...mintArgs)).output
+    await nftTx.mint(...mintArgs)
+    const [, , charlie] = await getSigners()
+    const pausedOf = async (pool): Promise<boolean> => (await pool.query.paused()).output.isTrue
+    return { ...fixture, charlie, mintArgs, id, liquidity, pausedOf }
+  }
+
+  it('only the owner creates pools with a non-default config', async () => {
+    const { bob, factory, token0, token1 } = await setup()
+    const config = { ...defaultConfig, dynamicFee: true, feeController: bob.address, minFee: 100, maxFee: 10000 }
+    await expect(
+      fromSigner(factory, bob.address).tx.createPoolWithConfig(FEE_MEDIUM, token0.address, token1.address, config, {
+        value: POOL_ENDOWMENT,
+      })
+    ).to.eventually.be.rejected
+    expect((await factory.query.getPool(FEE_MEDIUM, token0.address, token1.address)).output.toU8a()).to.deep.equal(ZERO_ADDRESS)
+
+    await factory.tx.createPoolWithConfig(FEE_MEDIUM, token0.address, token1.address, config, { value: POOL_ENDOWMENT })
+    expect((await factory.query.getPool(FEE_MEDIUM, token0.address, token1.address)).output.toU8a()).to.not.deep.equal(ZERO_ADDRESS)
+  })
+
+  it('anyone creates pools with the default config', async () => {
+    const { bob, factory, token0, token1 } = await setup()
+    await fromSigner(factory, bob.address).tx.createPoolWithConfig(FEE_MEDIUM, token0.address, token1.address, defaultConfig, {
+      value: POOL_ENDOWMENT,
+    })
+    expect((await factory.query.getPool(FEE_MEDIUM, token0.address, token1.address)).output.toU8a()).to.not.deep.equal(ZERO_ADDRESS)
+  })
+
+  const freeBalanceOf = async (address): Promise<BN> => new BN((await api.query.system.account(address)).data.free.toString())
+
+  it('defaults the pool creation cost to the endowment', async () => {
+    const { factory } = await setup()
+    expect((await factory.query.getPoolCreationFee()).output.toNumber()).to.equal(0)
+    expect((await factory.query.getPoolEndowment()).output.toNumber()).to.equal(POOL_ENDOWMENT)
+    expect((await factory.query.getPoolCreationCost()).output.toNumber()).to.equal(POOL_ENDOWMENT)
+  })
+
+  it('keeps the creation fee and refunds the value sent above the cost', async () => {
+    const { bob, factory, token0, token1 } = await setup()
+    const CREATION_FEE = 1000000
+    await factory.tx.setPoolCreationCost(CREATION_FEE, POOL_ENDOWMENT)
+    const cost = CREATION_FEE + POOL_ENDOWMENT
+    await expect(
+      fromSigner(factory, bob.address).tx.createPoolWithConfig(FEE_MEDIUM, token0.address, token1.address, defaultConfig, {
+        value: cost - 1,
+      })
+    ).to.eventually.be.rejected
+
+    const factoryBalanceBefore = await freeBalanceOf(factory.address)
+    await fromSigner(factory, bob.address).tx.createPoolWithConfig(FEE_MEDIUM, token0.address, token1.address, defaultConfig, {
+      value: cost * 3,
+    })
+    // the endowment goes to the pool and the excess back to bob, only the fee stays with the factory
+    expect((await freeBalanceOf(factory.address)).sub(factoryBalanceBefore).toNumber()).to.equal(CREATION_FEE)
+    expect((await factory.query.getCreationFeesCollected()).output.toNumber()).to.equal(CREATION_FEE)
+  })
+
+  it('only the owner withdraws the collected creation fees', async () => {
+    const { bob, charlie, factory, token0, token1 } = await setup()
+    const CREATION_FEE = 1000000
+    await factory.tx.setPoolCreationCost(CREATION_FEE, POOL_ENDOWMENT)
+    await factory.tx.createPoolWithConfig(FEE_MEDIUM, token0.address, token1.address, defaultConfig, {
+      value: CREATION_FEE + POOL_ENDOWMENT,
+    })
+    expect((await fromSigner(factory, bob.address).query.withdrawCreationFees(bob.address)).output.isErr).to.equal(true)
+
+    const charlieBalanceBefore = await freeBalanceOf(charlie.address)
+    await expect(factory.tx.withdrawCreationFees(charlie.address)).to.emit(factory, 'CreationFeesWithdrawn')
+    expect((await freeBalanceOf(charlie.address)).sub(charlieBalanceBefore).toNumber()).to.equal(CREATION_FEE)
+    expect((await factory.query.getCreationFeesCollected()).output.toNumber()).to.equal(0)
+
+    // nothing is paid twice
+    await factory.tx.withdrawCreationFees(charlie.address)
+    expect((await freeBalanceOf(charlie.address)).sub(charlieBalanceBefore).toNumber()).to.equal(CREATION_FEE)
+  })
+
+  it('the owner and the guardian pause and unpause a pool', async () => {
+    const { alice, bob, charlie, factory, pool, pausedOf, moveToTick } = await setup()
+    await expect(fromSigner(factory, bob.address).tx.pausePool(pool.address)).to.eventually.be.rejected
+    await factory.tx.setGuardian(bob.address)
+    expect((await factory.query.getGuardian()).output.toString()).to.equal(bob.address.toString())
+
+    await fromSigner(factory, bob.address).tx.pausePool(pool.address)
+    expect(await pausedOf(pool)).to.equal(true)
+    await expect(moveToTick(200)).to.eventually.be.rejected
+    await fromSigner(factory, alice.address).tx.unpausePool(pool.address)
+    expect(await pausedOf(pool)).to.equal(false)
+    await moveToTick(200)
+
+    await fromSigner(factory, alice.address).tx.pausePool(pool.address)
+    await fromSigner(factory, bob.address).tx.unpausePool(pool.address)
+    expect(await pausedOf(pool)).to.equal(false)
+
+    await expect(fromSigner(factory, charlie.address).tx.pausePool(pool.address)).to.eventually.be.rejected
+    await expect(fromSigner(pool, alice.address).tx.setPaused(true)).to.eventually.be.rejected
+    expect(await pausedOf(pool)).to.equal(false)
+  })
+
+  it('a paused pool still lets liquidity be withdrawn', async () => {
+    const { alice, factory, pool, nftTx, mintArgs, id, liquidity, pausedOf } = await setup()
+    await factory.tx.pausePool(pool.address)
+    expect(await pausedOf(pool)).to.equal(true)
+    await expect(nftTx.mint(...mintArgs)).to.eventually.be.rejected
+    await nftTx.decreaseLiquidity(id, liquidity, 0, 0, DEADLINE)
+    await nftTx.collect(id, alice.address, '340282366920938463463374607431768211455', '340282366920938463463374607431768211455')
+  })
+
+  it('pause_all and unpause_all work through the pools in pages', async () => {
+    const { alice, factory, token0, token1, pausedOf } = await setup()
+    for (const fee of [FEE_MEDIUM, 10000]) {
+      await factory.tx.createPool(fee, token0.address, token1.address, { value: POOL_ENDOWMENT })
+    }
+    expect((await factory.query.getAllPoolsLength()).output.toNumber()).to.equal(3)
+    const pools = []
+    for (let index = 0; index < 3; index++) {
+      pools.push(await getContractAt('pool', (await factory.query.getAllPools(index)).output, alice))
+    }
+    const paused = async () => Promise.all(pools.map(pausedOf))
+
+    await factory.tx.pauseAll(0, 2)
+    expect(await paused()).to.deep.equal([true, true, false])
+    // a page past the end is cut to the pools that exist
+    await factory.tx.pauseAll(2, 10)
+    expect(await paused()).to.deep.equal([true, true, true])
+    await factory.tx.unpauseAll(1, 1)
+    expect(await paused()).to.deep.equal([true, false, true])
+    await factory.tx.unpauseAll(0, 10)
+    expect(await paused()).to.deep.equal([false, false, false])
+  })
+
+  it('pause_all is rejected for accounts other than the owner and the guardian', async () => {
+    const { charlie, factory, pool, pausedOf } = await setup()
+    await expect(fromSigner(factory, charlie.address).tx.pauseAll(0, 10)).to.eventually.be.rejected
+    expect(await pausedOf(pool)).to.equal(false)
+  })
+
+  it('the circuit breaker pauses the pool after a swap moving the tick too far', async () => {
+    const { bob, charlie, factory, pool, pausedOf, getTick, moveToTick } = await setup()
+    await expect(fromSigner(factory, charlie.address).tx.setMaxTickMove(pool.address, 100)).to.eventually.be.rejected
+    await factory.tx.setGuardian(bob.address)
+    await fromSigner(factory, bob.address).tx.setMaxTickMove(pool.address, 100)
+    expect((await pool.query.maxTickMove()).output.toNumber()).to.equal(100)
+
+    // a move within the threshold leaves the pool open
+    await moveToTick(50)
+    expect(await pausedOf(pool)).to.equal(false)
+
+    // the swap crossing the threshold goes through, then the pool is paused
+    await moveToTick(500)
+    expect(await getTick()).to.be.gte(500)
+    expect(await pausedOf(pool)).to.equal(true)
+    await expect(moveToTick(0)).to.eventually.be.rejected
+
+    await factory.tx.unpausePool(pool.address)
+    await factory.tx.setMaxTickMove(pool.address, 0)
+    await moveToTick(0)
+    expect(await pausedOf(pool)).to.equal(false)
+  })
+
+  // a day in milliseconds, the minimum upgrade delay
+  const DAY = 86400000
+
+  it('schedules an upgrade of factory pools after the delay', async () => {
+    const { bob, factory, pool } = await setup()
+    const codeHash = (await artifacts.readArtifact('pool')).source.hash
+    await expect(fromSigner(factory, bob.address).tx.scheduleUpgrade(codeHash, [pool.address])).to.eventually.be.rejected
+
+    const now = (await api.query.timestamp.now()).toNumber()
+    const id = (await factory.query.scheduleUpgrade(codeHash, [pool.address])).output.asOk
+    await factory.tx.scheduleUpgrade(codeHash, [pool.address])
+    const upgrade = (await factory.query.getPendingUpgrade(id)).output.unwrap()
+    expect(upgrade.pools.map((address) => address.toString())).to.deep.equal([pool.address.toString()])
+    expect(upgrade.eta.toNumber()).to.be.gte(now + DAY)
+
+    // the timelock has not passed
+    await expect(factory.tx.executeUpgrade(id)).to.eventually.be.rejected
+    await expect(fromSigner(factory, bob.address).tx.cancelUpgrade(id)).to.eventually.be.rejected
+    await factory.tx.cancelUpgrade(id)
+    expect((await factory.query.getPendingUpgrade(id)).output.isNone).to.equal(true)
+    await expect(factory.tx.cancelUpgrade(id)).to.eventually.be.rejected
+    await expect(factory.tx.executeUpgrade(id)).to.eventually.be.rejected
+  })
+
+  it('rejects scheduling an upgrade of a pool the factory did not create', async () => {
+    const { factory, pool, token0, token1 } = await setup()
+    const codeHash = (await artifacts.readArtifact('pool')).source.hash
+    // a pool claiming the factory, tokens and fee tier of a real one
+    const { contract: fake } = await setupContract('pool', 'new', factory.address, token0.address, token1.address, FEE_LOW, TICK_SPACING_LOW, defaultConfig)
+    await expect(factory.tx.scheduleUpgrade(codeHash, [fake.address])).to.eventually.be.rejected
+    await expect(factory.tx.scheduleUpgrade(codeHash, [pool.address, fake.address])).to.eventually.be.rejected
+    await expect(factory.tx.scheduleUpgrade(codeHash, [])).to.eventually.be.rejected
+  })
+
+  it('keeps the upgrade delay at least a day', async () => {
+    const { bob, factory } = await setup()
+    expect((await factory.query.getUpgradeDelay()).output.toNumber()).to.equal(DAY)
+    await expect(factory.tx.setUpgradeDelay(DAY - 1)).to.eventually.be.rejected
+    await expect(fromSigner(factory, bob.address).tx.setUpgradeDelay(2 * DAY)).to.eventually.be.rejected
+    await factory.tx.setUpgradeDelay(2 * DAY)
+    expect((await factory.query.getUpgradeDelay()).output.toNumber()).to.equal(2 * DAY)
+  })
+
+  // the feeProtocol of slot0, feeProtocol0 in the low and feeProtocol1 in the high four bits
+  const feeProtocolOf = async (pool): Promise<number> => (await pool.query.getSlot0()).output.feeProtocol.toNumber()
+
+  it('initializes pools with the default protocol fee of their tier', async () => {
+    const { alice, factory, nftTx, token0, token1 } = await setup()
+    await expect(factory.tx.setFeeProtocolDefault(FEE_MEDIUM, 3, 4)).to.eventually.be.rejected
+    await expect(factory.tx.setFeeProtocolDefault(1234, 4, 4)).to.eventually.be.rejected
+    await factory.tx.setFeeProtocolDefault(FEE_MEDIUM, 4, 5)
+    expect((await factory.query.getFeeProtocolDefault(FEE_MEDIUM)).output.map((fee) => fee.toNumber())).to.deep.equal([4, 5])
+
+    await nftTx.createAndInitializePoolIfNecessary(token0.address, token1.address, FEE_MEDIUM, SQRT_PRICE_1_1, { value: 1000000000 })
+    const address = (await factory.query.getPool(FEE_MEDIUM, token0.address, token1.address)).output
+    expect(await feeProtocolOf(await getContractAt('pool', address, alice))).to.equal(4 + (5 << 4))
+  })
+
+  it('applies the default protocol fee to the existing pools of a tier in pages', async () => {
+    const { alice, factory, nftTx, weth9, token0, token1, pool: fixturePool } = await setup()
+    const sorted = (a, b) => (a.address.toHuman() < b.address.toHuman() ? [a, b] : [b, a])
+    const pairs = [sorted(token0, token1), sorted(token0, weth9), sorted(token1, weth9)]
+    // the first two pools are initialized, the last one is only created
+    for (const [tokenA, tokenB] of pairs.slice(0, 2)) {
+      await nftTx.createAndInitializePoolIfNecessary(tokenA.address, tokenB.address, FEE_MEDIUM, SQRT_PRICE_1_1, { value: 1000000000 })
+    }
+    await factory.tx.createPool(FEE_MEDIUM, pairs[2][0].address, pairs[2][1].address, { value: POOL_ENDOWMENT })
+    expect((await factory.query.getPoolsByFeeLength(FEE_MEDIUM)).output.toNumber()).to.equal(3)
+    const pools = []
+    for (let index = 0; index < 3; index++) {
+      pools.push(await getContractAt('pool', (await factory.query.getPoolsByFee(FEE_MEDIUM, index)).output, alice))
+    }
+    const feeProtocols = async () => Promise.all(pools.map(feeProtocolOf))
+    expect(await feeProtocols()).to.deep.equal([0, 0, 0])
+
+    await factory.tx.setFeeProtocolDefault(FEE_MEDIUM, 4, 4)
+    expect(await feeProtocols()).to.deep.equal([0, 0, 0])
+    await factory.tx.applyFeeProtocolDefault(FEE_MEDIUM, 0, 1)
+    expect(await feeProtocols()).to.deep.equal([68, 0, 0])
+    // the uninitialized pool is skipped, a page past the end is cut to the pools that exist
+    await factory.tx.applyFeeProtocolDefault(FEE_MEDIUM, 1, 10)
+    expect(await feeProtocols()).to.deep.equal([68, 68, 0])
+
+    // it gets the default when it is initialized
+    await nftTx.createAndInitializePoolIfNecessary(pairs[2][0].address, pairs[2][1].address, FEE_MEDIUM, SQRT_PRICE_1_1, { value: 1000000000 })
+    expect(await feeProtocols()).to.deep.equal([68, 68, 68])
+    // the pools of other tiers are left alone
+    expect(await feeProtocolOf(fixturePool)).to.equal(0)
+  })
+
+  it('applies the default protocol fee for the owner only', async () => {
+    const { bob, factory } = await setup()
+    await factory.tx.setFeeProtocolDefault(FEE_LOW, 4, 4)
+    expect((await fromSigner(factory, bob.address).query.applyFeeProtocolDefault(FEE_LOW, 0, 10)).output.isErr).to.equal(true)
+  })
+})
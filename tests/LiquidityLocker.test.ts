@@ -0,0 +1,86 @@
+import BN from 'bn.js'
+import { network } from 'redspot'
+import { expect, setupContract, fromSigner } from './helpers'
+import { positionManagerFixture, DEADLINE, FEE_LOW, ZERO_ADDRESS } from './shared/fixtures'
+
+const { api } = network
+
+describe('LiquidityLocker', () => {
+  after(() => {
+    return api.disconnect()
+  })
+
+  // a day in milliseconds, the unit of block timestamps
+  const DAY = 86400000
+
+  async function setup() {
+    await api.isReady
+    const fixture = await positionManagerFixture()
+    const { alice, token0, token1, nftQuery, nftTx } = fixture
+    const { contract: locker, query, tx } = await setupContract('LiquidityLockerContract', 'new', fixture.factory.address, fixture.nft.address)
+    const args = [token0.address, token1.address, FEE_LOW, -1000, 1000, 100000, 100000, 0, 0, alice.address, DEADLINE]
+    const id = (await nftQuery.mint(...args)).output[0]
+    await nftTx.mint(...args)
+    const now = async (): Promise<number> => (await api.query.timestamp.now()).toNumber()
+    // sends the position to the locker, the data is the encoded unlock time
+    const lock = async (unlockTime: number) =>
+      nftTx.transfer(locker.address, id, Array.from(api.createType('u64', unlockTime).toU8a()))
+    return { ...fixture, locker, query, tx, id, now, lock }
+  }
+
+  it('locks the position until the unlock time', async () => {
+    const { alice, locker, nftQuery, query, id, now, lock } = await setup()
+    const unlockTime = (await now()) + DAY
+    await lock(unlockTime)
+
+    expect((await nftQuery.ownerOf(id)).output.toString()).to.equal(locker.address.toString())
+    const locked = (await query.getLock(id)).output.unwrap()
+    expect(locked.owner.toString()).to.equal(alice.address.toString())
+    expect(locked.unlockTime.toNumber()).to.equal(unlockTime)
+    expect((await query.isLocked(id)).output.isTrue).to.equal(true)
+  })
+
+  it('rejects an unlock time in the past', async () => {
+    const { now, lock } = await setup()
+    await expect(lock((await now()) - 1)).to.eventually.be.rejected
+  })
+
+  it('does not release the position before the unlock time', async () => {
+    const { alice, bob, locker, nftQuery, tx, id, now, lock } = await setup()
+    await lock((await now()) + DAY)
+
+    await expect(tx.withdraw(id, alice.address, [])).to.eventually.be.rejected
+    await expect(fromSigner(locker, bob.address).tx.withdraw(id, bob.address, [])).to.eventually.be.rejected
+    expect((await nftQuery.ownerOf(id)).output.toString()).to.equal(locker.address.toString())
+  })
+
+  it('extends the lock but does not shorten it', async () => {
+    const { bob, locker, query, tx, id, now, lock } = await setup()
+    const unlockTime = (await now()) + DAY
+    await lock(unlockTime)
+
+    await expect(tx.extend(id, unlockTime - 1)).to.eventually.be.rejected
+    await expect(tx.extend(id, unlockTime)).to.eventually.be.rejected
+    await expect(fromSigner(locker, bob.address).tx.extend(id, unlockTime + DAY)).to.eventually.be.rejected
+    await tx.extend(id, unlockTime + DAY)
+    expect((await query.getLock(id)).output.unwrap().unlockTime.toNumber()).to.equal(unlockTime + DAY)
+  })
+
+  it('lets the owner collect the fees during the lock', async () => {
+    const { bob, locker, tx, token0, token1, id, now, lock, moveToTick, balanceOf } = await setup()
+    await lock((await now()) + DAY)
+    // fees for both tokens
+    await moveToTick(200)
+    await moveToTick(-200)
+
+    await expect(fromSigner(locker, bob.address).tx.collect(id, bob.address)).to.eventually.be.rejected
+    await expect(tx.collect(id, ZERO_ADDRESS)).to.eventually.be.rejected
+    await expect(tx.collect(id, locker.address)).to.eventually.be.rejected
+    const balance0Before = await balanceOf(token0, bob.address)
+    const balance1Before = await balanceOf(token1, bob.address)
+    await tx.collect(id, bob.address)
+    expect((await balanceOf(token0, bob.address)).gt(balance0Before)).to.equal(true)
+    expect((await balanceOf(token1, bob.address)).gt(balance1Before)).to.equal(true)
+    expect((await balanceOf(token0, locker.address)).eq(new BN(0))).to.equal(true)
+  })
+})
@@ -34,7 +34,7 @@ describe('positionManager initialize', () => {
   //   const { contract:weth9Contract} = await setupContract('weth9_contract','new','weth9','weth9');
   //   console.log("factory is:",2);
   //   // pub fn new(factory: AccountId, weth9: AccountId,tokenDescriptor:AccountId) -> Self {
-  //   const { contract:positionDescriptor} = await setupContract('NonfungibleTokenPositionDescriptor','new',weth9Contract.address,"_nativeCurrencyLabelBytes");
+  //   const { contract:positionDescriptor} = await setupContract('NonfungibleTokenPositionDescriptor','new',weth9Contract.address,"_nativeCurrencyLabelBytes",[]);
   //   console.log("factory is:",3);
   //   // pub fn new(factory: AccountId, weth9: AccountId,tokenDescriptor:AccountId) -> Self {
   //   const { query:positionManagerQuery,tx:positionManagerTx,alice,defaultSigner,contract:positionMangerContract } = await setupContract('NonfungiblePositionManager','new',factoryContract.address,weth9Contract.address,positionDescriptor.address,{value:1000000000});
@@ -89,7 +89,7 @@ describe('positionManager initialize', () => {
     console.log("alice1 is:",alice1.address.toString());
     const { contract:weth9Contract} = await setupContract('weth9_contract','new','weth9','weth9');
     // pub fn new(factory: AccountId, weth9: AccountId,tokenDescriptor:AccountId) -> Self {
-    const { contract:positionDescriptor,query:positionDescriptorQuery,tx:positionDescriptorTx} = await setupContract('NonfungibleTokenPositionDescriptor','new',weth9Contract.address,"_nativeCurrencyLabelBytes");
+    const { contract:positionDescriptor,query:positionDescriptorQuery,tx:positionDescriptorTx} = await setupContract('NonfungibleTokenPositionDescriptor','new',weth9Contract.address,"_nativeCurrencyLabelBytes",[]);
     // pub fn new(factory: AccountId, weth9: AccountId,tokenDescriptor:AccountId) -> Self {
     const { query:positionManagerQuery,tx:positionManagerTx,alice,defaultSigner,contract:positionMangerContract,bob } = await setupContract('NonfungiblePositionManager','new',factoryContract.address,weth9Contract.address,positionDescriptor.address,{value:1000000000});
     const { query:swapRouterQuery,tx:swapRouterTx,contract:swapRouterContract} = await setupContract('SwapRouterContract','new',factoryContract.address,weth9Contract.address,positionDescriptor.address,{value:1000000000});
@@ -122,6 +122,8 @@ describe('positionManager initialize', () => {
     // pool_code_hash = pool_code_hash.substring(2);
     
     await factoryTx.initial(pool_code_hash);
+    // no creation fee, endow new pools with 125000000 for their storage deposit, the rest of the value is refunded
+    await factoryTx.setPoolCreationCost(0,125000000);
     const poolCodeHash = await factoryQuery.getPoolCodeHash();
     console.log("poolCodeHash is:",poolCodeHash.output?.toHuman());
     // &mut self,fee:u32,token_a:Address,token_b:Address
@@ -0,0 +1,180 @@
+import BN from 'bn.js'
+import { network, patract } from 'redspot'
+import { expect, fromSigner } from './helpers'
+import { positionManagerFixture, DEADLINE, FEE_LOW, ZERO_ADDRESS } from './shared/fixtures'
+
+const { api, getSigners } = network
+const { getContractAt } = patract
+
+describe('Pool', () => {
+  after(() => {
+    return api.disconnect()
+  })
+
+  async function setup() {
+    await api.isReady
+    const fixture = await positionManagerFixture()
+    const { alice, token0, token1, nftTx, pool, balanceOf } = fixture
+    await nftTx.mint(token0.address, token1.address, FEE_LOW, -1000, 1000, 100000, 100000, 0, 0, alice.address, DEADLINE)
+    const reserves = async (): Promise<[string, string]> => {
+      const [reserve0, reserve1] = (await pool.query.getReserves()).output
+      return [reserve0.toString(), reserve1.toString()]
+    }
+    const balances = async (): Promise<[string, string]> => [
+      (await balanceOf(token0, pool.address)).toString(),
+      (await balanceOf(token1, pool.address)).toString(),
+    ]
+    return { ...fixture, reserves, balances }
+  }
+
+  // block timestamps only move with new blocks, waits a moment and seals one with a transfer
+  const nextBlock = async (token, to) => {
+    await new Promise((resolve) => setTimeout(resolve, 1000))
+    await token.tx.transfer(to, 1, [])
+  }
+
+  it('reserves follow the balances after mint and swap', async () => {
+    const { reserves, balances, moveToTick } = await setup()
+    expect(await reserves()).to.deep.equal(await balances())
+    expect((await reserves()).every((reserve) => new BN(reserve).gtn(0))).to.equal(true)
+
+    await moveToTick(200)
+    expect(await reserves()).to.deep.equal(await balances())
+    await moveToTick(-200)
+    expect(await reserves()).to.deep.equal(await balances())
+  })
+
+  it('reserves include the fee paid back by a flash', async () => {
+    const { bob, token0, token1, pool, reserves, balances, balanceOf } = await setup()
+    // there is no flash callback, the loan and its fee are paid into the pool up front
+    const [amount0, amount1] = [1000, 2000]
+    const [fee0, fee1] = [Math.ceil((amount0 * FEE_LOW) / 1e6), Math.ceil((amount1 * FEE_LOW) / 1e6)]
+    const [reserve0Before, reserve1Before] = await reserves()
+    await token0.tx.transfer(pool.address, amount0 + fee0, [])
+    await token1.tx.transfer(pool.address, amount1 + fee1, [])
+
+    const balance0Before = await balanceOf(token0, bob.address)
+    await pool.tx.flash(bob.address, amount0, amount1, [])
+    expect((await balanceOf(token0, bob.address)).sub(balance0Before).toNumber()).to.equal(amount0)
+    expect(await reserves()).to.deep.equal(await balances())
+    expect(await reserves()).to.deep.equal([
+      new BN(reserve0Before).addn(fee0).toString(),
+      new BN(reserve1Before).addn(fee1).toString(),
+    ])
+  })
+
+  it('flash is rejected when the fee is not paid', async () => {
+    const { bob, token0, pool } = await setup()
+    await token0.tx.transfer(pool.address, 1000, [])
+    await expect(pool.tx.flash(bob.address, 1000, 0, [])).to.eventually.be.rejected
+  })
+
+  it('skim sends the balances above the reserves and keeps the reserves', async () => {
+    const { bob, token0, token1, pool, reserves, balanceOf } = await setup()
+    const reservesBefore = await reserves()
+    await token0.tx.transfer(pool.address, 1000, [])
+    await token1.tx.transfer(pool.address, 2000, [])
+
+    const balance0Before = await balanceOf(token0, bob.address)
+    const balance1Before = await balanceOf(token1, bob.address)
+    await expect(pool.tx.skim(bob.address)).to.emit(pool, 'Skim')
+    expect((await balanceOf(token0, bob.address)).sub(balance0Before).toNumber()).to.equal(1000)
+    expect((await balanceOf(token1, bob.address)).sub(balance1Before).toNumber()).to.equal(2000)
+    expect(await reserves()).to.deep.equal(reservesBefore)
+
+    // nothing is left above the reserves
+    const [skimmed0, skimmed1] = (await pool.query.skim(bob.address)).output
+    expect([skimmed0.toNumber(), skimmed1.toNumber()]).to.deep.equal([0, 0])
+  })
+
+  it('sync raises the reserves to the balances', async () => {
+    const { token0, pool, reserves, balances } = await setup()
+    const [reserve0Before, reserve1Before] = await reserves()
+    await token0.tx.transfer(pool.address, 1000, [])
+
+    await expect(pool.tx.sync()).to.emit(pool, 'Sync')
+    expect(await reserves()).to.deep.equal(await balances())
+    expect(await reserves()).to.deep.equal([new BN(reserve0Before).addn(1000).toString(), reserve1Before])
+  })
+
+  it('snapshots the cumulatives inside a range while the price is in it', async () => {
+    const { bob, token0, pool, moveToTick } = await setup()
+    await expect(pool.tx.snapshotCumulativesInside(-2000, 2000)).to.eventually.be.rejected
+    const snapshot = async (): Promise<[BN, BN, number]> => {
+      const [tickCumulative, secondsPerLiquidity, seconds] = (await pool.query.snapshotCumulativesInside(-1000, 1000)).output
+      return [new BN(tickCumulative.toString()), new BN(secondsPerLiquidity.toString()), seconds.toNumber()]
+    }
+
+    await moveToTick(200)
+    const [tickCumulativeBefore, secondsPerLiquidityBefore, secondsBefore] = await snapshot()
+    await nextBlock(token0, bob.address)
+    const [tickCumulativeInRange, secondsPerLiquidityInRange, secondsInRange] = await snapshot()
+    expect(secondsInRange).to.be.gt(secondsBefore)
+    expect(secondsPerLiquidityInRange.gt(secondsPerLiquidityBefore)).to.equal(true)
+    expect(tickCumulativeInRange.gt(tickCumulativeBefore)).to.equal(true)
+
+    // out of the range the snapshot stands still
+    await moveToTick(1500)
+    const outOfRange = await snapshot()
+    await nextBlock(token0, bob.address)
+    expect((await snapshot()).map((value) => value.toString())).to.deep.equal(outOfRange.map((value) => value.toString()))
+  })
+
+  it('observes the tick and liquidity accumulators', async () => {
+    const { bob, token0, pool, moveToTick } = await setup()
+    const observe = async (): Promise<[BN, BN]> => {
+      const [[tickCumulative], [secondsPerLiquidity]] = (await pool.query.observe([0])).output
+      return [new BN(tickCumulative.toString()), new BN(secondsPerLiquidity.toString())]
+    }
+
+    await moveToTick(200)
+    const [tickCumulativeBefore, secondsPerLiquidityBefore] = await observe()
+    await nextBlock(token0, bob.address)
+    const [tickCumulative, secondsPerLiquidity] = await observe()
+    // the tick is positive, so the tick accumulator grows
+    expect(tickCumulative.gt(tickCumulativeBefore)).to.equal(true)
+    expect(secondsPerLiquidity.gt(secondsPerLiquidityBefore)).to.equal(true)
+
+    await moveToTick(-200)
+    const [tickCumulativeNegative] = await observe()
+    await nextBlock(token0, bob.address)
+    expect((await observe())[0].lt(tickCumulativeNegative)).to.equal(true)
+
+    // a day ago, before the pool and its only observation existed
+    await expect(pool.tx.observe([86400000])).to.eventually.be.rejected
+  })
+
+  it('only the fee controller and the factory owner set the fee of a dynamic fee pool, within its bounds', async () => {
+    const { alice, bob, factory, token0, token1 } = await setup()
+    const FEE_MEDIUM = 3000
+    const config = {
+      hooks: ZERO_ADDRESS,
+      dynamicFee: true,
+      feeController: bob.address,
+      minFee: 1000,
+      maxFee: 5000,
+      accessPolicy: ZERO_ADDRESS,
+    }
+    await factory.tx.createPoolWithConfig(FEE_MEDIUM, token0.address, token1.address, config, { value: 125000000 })
+    const address = (await factory.query.getPool(FEE_MEDIUM, token0.address, token1.address)).output
+    const pool = await getContractAt('pool', address, alice)
+    const feeOf = async (): Promise<number> => (await pool.query.fee()).output.toNumber()
+
+    await fromSigner(pool, bob.address).tx.setFee(1000)
+    expect(await feeOf()).to.equal(1000)
+    await fromSigner(pool, alice.address).tx.setFee(5000)
+    expect(await feeOf()).to.equal(5000)
+    expect((await pool.query.feeTier()).output.toNumber()).to.equal(FEE_MEDIUM)
+
+    const [, , charlie] = await getSigners()
+    await expect(fromSigner(pool, charlie.address).tx.setFee(2000)).to.eventually.be.rejected
+    await expect(fromSigner(pool, bob.address).tx.setFee(999)).to.eventually.be.rejected
+    await expect(fromSigner(pool, bob.address).tx.setFee(5001)).to.eventually.be.rejected
+    expect(await feeOf()).to.equal(5000)
+  })
+
+  it('the fee of a static fee pool can not be set', async () => {
+    const { alice, pool } = await setup()
+    await expect(fromSigner(pool, alice.address).tx.setFee(FEE_LOW)).to.eventually.be.rejected
+  })
+})
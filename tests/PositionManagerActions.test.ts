@@ -0,0 +1,302 @@
+import BN from 'bn.js'
+import { network, patract } from 'redspot'
+import { expect, fromSigner, setupContract } from './helpers'
+import { positionManagerFixture, sqrtPriceAtTick, DEADLINE, FEE_LOW, SQRT_PRICE_1_1 } from './shared/fixtures'
+
+const { api } = network
+const { getRandomSigner } = patract
+
+describe('NonfungiblePositionManager actions', () => {
+  after(() => {
+    return api.disconnect()
+  })
+
+  async function setup() {
+    await api.isReady
+    const fixture = await positionManagerFixture()
+    const { alice, token0, token1, nft, nftQuery, nftTx, balanceOf } = fixture
+    // mints a position of alice, returns its token id
+    const mint = async (tickLower = -1000, tickUpper = 1000, amount = 100000) => {
+      const args = [token0.address, token1.address, FEE_LOW, tickLower, tickUpper, amount, amount, 0, 0, alice.address, DEADLINE]
+      const id = (await nftQuery.mint(...args)).output[0]
+      await nftTx.mint(...args)
+      return id
+    }
+    const position = async (id) => (await nftQuery.positions(id)).output
+    const liquidityOf = async (id): Promise<BN> => new BN((await position(id))[7].toString())
+    const managerBalances = async (): Promise<[number, number]> => [
+      (await balanceOf(token0, nft.address)).toNumber(),
+      (await balanceOf(token1, nft.address)).toNumber(),
+    ]
+    return { ...fixture, mint, position, liquidityOf, managerBalances }
+  }
+
+  it('rebalance moves the liquidity to a new position and returns what it cannot use', async () => {
+    const { alice, nftQuery, nftTx, token1, mint, position, liquidityOf, managerBalances, balanceOf } = await setup()
+    const id = await mint()
+
+    const args = [id, -500, 500, null, 0, 0, DEADLINE]
+    const [newId] = (await nftQuery.rebalance(...args)).output
+    await nftTx.rebalance(...args)
+
+    expect((await nftQuery.ownerOf(id)).output.isNone).to.equal(true)
+    expect((await nftQuery.ownerOf(newId)).output.toString()).to.equal(alice.address.toString())
+    const moved = await position(newId)
+    expect(moved[5].toNumber()).to.equal(-500)
+    expect(moved[6].toNumber()).to.equal(500)
+    expect((await liquidityOf(newId)).gtn(0)).to.equal(true)
+    expect(await managerBalances()).to.deep.equal([0, 0])
+
+    // a range above the price holds only token0, so the token1 is swapped for it first
+    const balance1Before = await balanceOf(token1, alice.address)
+    const swapArgs = [newId, 100, 1000, sqrtPriceAtTick(2000), 0, 0, DEADLINE]
+    const [swappedId] = (await nftQuery.rebalance(...swapArgs)).output
+    await nftTx.rebalance(...swapArgs)
+    expect((await liquidityOf(swappedId)).gtn(0)).to.equal(true)
+    expect((await balanceOf(token1, alice.address)).sub(balance1Before).ltn(1000)).to.equal(true)
+    expect(await managerBalances()).to.deep.equal([0, 0])
+  })
+
+  it('rebalance checks the slippage of the new position', async () => {
+    const { nftTx, mint } = await setup()
+    const id = await mint()
+    await expect(nftTx.rebalance(id, -500, 500, null, 1000000, 0, DEADLINE)).to.eventually.be.rejected
+  })
+
+  it('rebalance is rejected for other accounts', async () => {
+    const { bob, nft, mint } = await setup()
+    const id = await mint()
+    await expect(fromSigner(nft, bob.address).tx.rebalance(id, -500, 500, null, 0, 0, DEADLINE)).to.eventually.be.rejected
+  })
+
+  it('zap mints a position from one token and refunds the dust', async () => {
+    const { alice, token0, token1, nftQuery, nftTx, mint, liquidityOf, managerBalances, balanceOf } = await setup()
+    // liquidity for the swap of the zap
+    await mint()
+    const balance0Before = await balanceOf(token0, alice.address)
+    const balance1Before = await balanceOf(token1, alice.address)
+
+    const args = [token0.address, token0.address, token1.address, FEE_LOW, -500, 500, 10000, sqrtPriceAtTick(-2000), 0, 0, alice.address, DEADLINE]
+    const [id] = (await nftQuery.zap(...args)).output
+    await nftTx.zap(...args)
+
+    expect((await liquidityOf(id)).gtn(0)).to.equal(true)
+    // the manager keeps nothing, no token1 is taken and at most the input is spent
+    expect(await managerBalances()).to.deep.equal([0, 0])
+    expect((await balanceOf(token1, alice.address)).gte(balance1Before)).to.equal(true)
+    const spent0 = balance0Before.sub(await balanceOf(token0, alice.address))
+    expect(spent0.lten(10000)).to.equal(true)
+  })
+
+  it('zap rejects a token outside the pool', async () => {
+    const { alice, weth9, token0, token1, nftTx, mint } = await setup()
+    await mint()
+    await expect(
+      nftTx.zap(weth9.address, token0.address, token1.address, FEE_LOW, -500, 500, 10000, sqrtPriceAtTick(-2000), 0, 0, alice.address, DEADLINE)
+    ).to.eventually.be.rejected
+  })
+
+  it('zap swaps no further than the price limit', async () => {
+    const { alice, token0, token1, nftTx, mint, getTick } = await setup()
+    await mint()
+    const zap = (sqrtPriceLimitX96) =>
+      nftTx.zap(token0.address, token0.address, token1.address, FEE_LOW, -500, 500, 10000, sqrtPriceLimitX96, 0, 0, alice.address, DEADLINE)
+    // selling token0 lowers the price, a limit above it is rejected by the pool
+    await expect(zap(sqrtPriceAtTick(10))).to.eventually.be.rejected
+
+    await zap(sqrtPriceAtTick(-5))
+    expect(await getTick()).to.be.gte(-5)
+  })
+
+  it('split and merge keep the pooled position and the fee snapshots consistent', async () => {
+    const { alice, nft, nftQuery, nftTx, pool, mint, position, liquidityOf, moveToTick } = await setup()
+    const id = await mint()
+    // fees for both tokens, not yet accounted to the position
+    await moveToTick(200)
+    await moveToTick(-200)
+    const pooled = async () => (await pool.query.positions(nft.address, -1000, 1000, null)).output
+
+    const splitArgs = [id, '250000000000000000', alice.address]
+    const [splitId] = (await nftQuery.split(...splitArgs)).output
+    await nftTx.split(...splitArgs)
+    const [kept, split] = [await position(id), await position(splitId)]
+    expect((await liquidityOf(id)).add(await liquidityOf(splitId)).toString()).to.equal((await pooled()).liquidity.toString())
+    expect(split[8].toString()).to.equal(kept[8].toString())
+    expect(split[9].toString()).to.equal(kept[9].toString())
+
+    await nftTx.merge([id, splitId])
+    expect((await nftQuery.ownerOf(splitId)).output.isNone).to.equal(true)
+    const [merged, info] = [await position(id), await pooled()]
+    expect(merged[7].toString()).to.equal(info.liquidity.toString())
+    expect(merged[8].toString()).to.equal(info.feeGrowthInside0LastX128.value.toString())
+    expect(merged[9].toString()).to.equal(info.feeGrowthInside1LastX128.value.toString())
+
+    // everything the manager accounts to the position can be taken out of the pool, leaving at most rounding dust
+    const U128_MAX = '340282366920938463463374607431768211455'
+    await nftTx.decreaseLiquidity(id, merged[7], 0, 0, DEADLINE)
+    await nftTx.collect(id, alice.address, U128_MAX, U128_MAX)
+    const emptied = await pooled()
+    expect(emptied.liquidity.toNumber()).to.equal(0)
+    expect(emptied.tokensOwed0.toNumber()).to.be.lte(2)
+    expect(emptied.tokensOwed1.toNumber()).to.be.lte(2)
+    await nftTx.burn(id)
+  })
+
+  it('merge rejects positions of different ranges', async () => {
+    const { nftTx, mint } = await setup()
+    const id = await mint()
+    const otherId = await mint(-500, 500)
+    await expect(nftTx.merge([id, otherId])).to.eventually.be.rejected
+    await expect(nftTx.merge([id, id])).to.eventually.be.rejected
+  })
+
+  it('positions_of follows burn, merge, rebalance and transfer', async () => {
+    const { alice, bob, nftQuery, nftTx, mint, liquidityOf } = await setup()
+    const idsOf = async (owner): Promise<string[]> =>
+      (await nftQuery.positionsOf(owner, 0, 10)).output.map(([id]) => id.toString())
+    const [first, second, third] = [await mint(), await mint(), await mint(-500, 500)]
+    expect(await idsOf(alice.address)).to.deep.equal([first, second, third].map((id) => id.toString()))
+
+    const U128_MAX = '340282366920938463463374607431768211455'
+    await nftTx.decreaseLiquidity(third, await liquidityOf(third), 0, 0, DEADLINE)
+    await nftTx.collect(third, alice.address, U128_MAX, U128_MAX)
+    await nftTx.burn(third)
+    expect(await idsOf(alice.address)).to.deep.equal([first, second].map((id) => id.toString()))
+
+    await nftTx.merge([first, second])
+    expect(await idsOf(alice.address)).to.deep.equal([first.toString()])
+
+    const args = [first, -500, 500, null, 0, 0, DEADLINE]
+    const [moved] = (await nftQuery.rebalance(...args)).output
+    await nftTx.rebalance(...args)
+    expect(await idsOf(alice.address)).to.deep.equal([moved.toString()])
+    const [[, position]] = (await nftQuery.positionsOf(alice.address, 0, 10)).output
+    expect(position.tickLower.toNumber()).to.equal(-500)
+    expect(position.tickUpper.toNumber()).to.equal(500)
+
+    await nftTx.transfer(bob.address, moved, [])
+    expect(await idsOf(alice.address)).to.deep.equal([])
+    expect(await idsOf(bob.address)).to.deep.equal([moved.toString()])
+    expect((await nftQuery.totalSupply()).output.toNumber()).to.equal(1)
+  })
+
+  it('the approved operator of a position does not outlive a transfer', async () => {
+    const { bob, nft, nftQuery, nftTx, mint, liquidityOf } = await setup()
+    const id = await mint()
+    await nftTx.approve(bob.address, id, true)
+    expect((await nftQuery.getApproved(id)).output.toString()).to.equal(bob.address.toString())
+
+    const buyer = await getRandomSigner()
+    await nftTx.transfer(buyer.address, id, [])
+    expect((await nftQuery.getApproved(id)).output.toString()).to.not.equal(bob.address.toString())
+    await expect(
+      fromSigner(nft, bob.address).tx.decreaseLiquidity(id, await liquidityOf(id), 0, 0, DEADLINE)
+    ).to.eventually.be.rejected
+    await expect(fromSigner(nft, bob.address).tx.transfer(bob.address, id, [])).to.eventually.be.rejected
+  })
+
+  it('an operator approved for all positions can manage them', async () => {
+    const { bob, nft, nftTx, mint, liquidityOf } = await setup()
+    const id = await mint()
+    await nftTx.approve(bob.address, null, true)
+    await fromSigner(nft, bob.address).tx.decreaseLiquidity(id, 1, 0, 0, DEADLINE)
+    expect((await liquidityOf(id)).gtn(0)).to.equal(true)
+
+    await nftTx.approve(bob.address, null, false)
+    await expect(fromSigner(nft, bob.address).tx.decreaseLiquidity(id, 1, 0, 0, DEADLINE)).to.eventually.be.rejected
+  })
+
+  it('rebalance needs all permissions', async () => {
+    const { bob, nft, nftTx, mint } = await setup()
+    const id = await mint()
+    const PERMISSION_INCREASE = 2
+    const PERMISSION_DECREASE = 4
+    const PERMISSION_ALL = 15
+    await nftTx.grantPermissions(id, bob.address, PERMISSION_INCREASE | PERMISSION_DECREASE)
+    await expect(fromSigner(nft, bob.address).tx.rebalance(id, -500, 500, null, 0, 0, DEADLINE)).to.eventually.be.rejected
+
+    await nftTx.grantPermissions(id, bob.address, PERMISSION_ALL)
+    await fromSigner(nft, bob.address).tx.rebalance(id, -500, 500, null, 0, 0, DEADLINE)
+  })
+
+  it('a grantee with the collect permission only collects', async () => {
+    const { bob, nft, nftQuery, nftTx, token0, mint, moveToTick, liquidityOf, balanceOf } = await setup()
+    const id = await mint()
+    const PERMISSION_COLLECT = 1
+    await moveToTick(200)
+    await moveToTick(-200)
+    await nftTx.grantPermissions(id, bob.address, PERMISSION_COLLECT)
+
+    const MAX = '340282366920938463463374607431768211455'
+    const balanceBefore = await balanceOf(token0, bob.address)
+    await fromSigner(nft, bob.address).tx.collect(id, bob.address, MAX, MAX)
+    expect((await balanceOf(token0, bob.address)).gt(balanceBefore)).to.equal(true)
+
+    await expect(fromSigner(nft, bob.address).tx.decreaseLiquidity(id, await liquidityOf(id), 0, 0, DEADLINE)).to.eventually.be.rejected
+    await expect(fromSigner(nft, bob.address).tx.transfer(bob.address, id, [])).to.eventually.be.rejected
+    expect((await nftQuery.ownerOf(id)).output.toString()).to.not.equal(bob.address.toString())
+  })
+
+  it('permissions do not come back when a position returns to its owner', async () => {
+    const { alice, bob, nft, nftQuery, nftTx, mint } = await setup()
+    const id = await mint()
+    const [, , charlie] = await network.getSigners()
+    const PERMISSION_ALL = 15
+    await nftTx.grantPermissions(id, charlie.address, PERMISSION_ALL)
+    expect((await nftQuery.getPermissions(id, charlie.address)).output.toNumber()).to.equal(PERMISSION_ALL)
+
+    await nftTx.transfer(bob.address, id, [])
+    await fromSigner(nft, bob.address).tx.transfer(alice.address, id, [])
+    expect((await nftQuery.ownerOf(id)).output.toString()).to.equal(alice.address.toString())
+    expect((await nftQuery.getPermissions(id, charlie.address)).output.toNumber()).to.equal(0)
+    await expect(fromSigner(nft, charlie.address).tx.transfer(charlie.address, id, [])).to.eventually.be.rejected
+  })
+
+  it('collectMany pays the fees of positions in several pools once per token', async () => {
+    const { alice, bob, nft, nftQuery, nftTx, router, routerTx, token0, token1, mint, moveToTick, managerBalances, balanceOf } = await setup()
+    // a second pool between token1 and a third token, alice holds all of the third token
+    const { contract: tokenC } = await setupContract('stable_coin_contract', 'new', 'CCC', 'CCC')
+    await tokenC.tx.approve(nft.address, 10000000)
+    await tokenC.tx.approve(router.address, 10000000)
+    const [tokenA, tokenB] = token1.address.toHuman() < tokenC.address.toHuman() ? [token1, tokenC] : [tokenC, token1]
+    await nftTx.createAndInitializePoolIfNecessary(tokenA.address, tokenB.address, FEE_LOW, SQRT_PRICE_1_1, { value: 1000000000 })
+    const mintArgs = [tokenA.address, tokenB.address, FEE_LOW, -1000, 1000, 100000, 100000, 0, 0, alice.address, DEADLINE]
+    const idC = (await nftQuery.mint(...mintArgs)).output[0]
+    await nftTx.mint(...mintArgs)
+    const [id0, id1] = [await mint(), await mint(-500, 500)]
+    const bobMintArgs = [token0.address, token1.address, FEE_LOW, -1000, 1000, 100000, 100000, 0, 0, bob.address, DEADLINE]
+    const idOfBob = (await nftQuery.mint(...bobMintArgs)).output[0]
+    await nftTx.mint(...bobMintArgs)
+
+    // fees for all three tokens
+    await moveToTick(200)
+    await moveToTick(-200)
+    for (const [tokenIn, tokenOut] of [[tokenA, tokenB], [tokenB, tokenA]]) {
+      const limit = tokenIn === tokenA ? sqrtPriceAtTick(-200) : sqrtPriceAtTick(200)
+      await routerTx.exactInputSingle(tokenIn.address, tokenOut.address, FEE_LOW, alice.address, DEADLINE, 10000, 0, limit)
+    }
+
+    const MAX = '340282366920938463463374607431768211455'
+    const collects = [id0, id1, idC].map((id) => [id, MAX, MAX])
+    // a position the caller may not collect fails the whole call
+    await expect(nftTx.collectMany([...collects, [idOfBob, MAX, MAX]], bob.address)).to.eventually.be.rejected
+
+    const amounts = (await nftQuery.collectMany(collects, bob.address)).output
+    // one entry per token, summed over the positions
+    const tokens = [token0, token1, tokenC]
+    expect(amounts.length).to.equal(tokens.length)
+    const amountOf = (token) => new BN(amounts.find(([t]) => t.toString() === token.address.toString())[1].toString())
+    const balancesBefore = await Promise.all(tokens.map((token) => balanceOf(token, bob.address)))
+
+    const result = await nftTx.collectMany(collects, bob.address)
+    const collected = result.events.filter((event) => event.name === 'Collect')
+    expect(collected.map((event) => event.args[0].toString())).to.deep.equal([id0, id1, idC].map((id) => id.toString()))
+    for (const [i, token] of tokens.entries()) {
+      const received = (await balanceOf(token, bob.address)).sub(balancesBefore[i])
+      expect(received.gtn(0)).to.equal(true)
+      expect(received.eq(amountOf(token))).to.equal(true)
+    }
+    expect(await managerBalances()).to.deep.equal([0, 0])
+    expect((await balanceOf(tokenC, nft.address)).toNumber()).to.equal(0)
+  })
+})
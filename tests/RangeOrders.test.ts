@@ -0,0 +1,120 @@
+import BN from 'bn.js'
+import { network } from 'redspot'
+import { expect, setupContract, fromSigner } from './helpers'
+import { positionManagerFixture, DEADLINE, FEE_LOW } from './shared/fixtures'
+
+const { api } = network
+
+describe('RangeOrders', () => {
+  after(() => {
+    return api.disconnect()
+  })
+
+  const KEEPER_TIP_BPS = 30
+
+  async function setup() {
+    await api.isReady
+    const fixture = await positionManagerFixture()
+    const { alice, token0, token1, nftTx } = fixture
+    const { contract: rangeOrders, query, tx } = await setupContract('RangeOrdersContract', 'new', fixture.factory.address, fixture.nft.address, KEEPER_TIP_BPS)
+    await token0.tx.approve(rangeOrders.address, 10000000)
+    await token1.tx.approve(rangeOrders.address, 10000000)
+    // liquidity around the price, so swaps can move it through the orders
+    await nftTx.mint(token0.address, token1.address, FEE_LOW, -1000, 1000, 100000, 100000, 0, 0, alice.address, DEADLINE)
+    // places an order selling token0 in [tickLower, tickLower + 10), returns its token id
+    const placeSellToken0 = async (tickLower: number, amountIn = 1000) => {
+      const tokenId = (await query.place(token0.address, token1.address, FEE_LOW, tickLower, true, amountIn, DEADLINE)).output
+      await tx.place(token0.address, token1.address, FEE_LOW, tickLower, true, amountIn, DEADLINE)
+      return tokenId
+    }
+    const isFilled = async (tokenId): Promise<boolean> => (await query.isFilled(tokenId)).output.isTrue
+    return { ...fixture, rangeOrders, query, tx, placeSellToken0, isFilled }
+  }
+
+  it('place rejects a range that is not one-sided', async () => {
+    const { tx, token0, token1 } = await setup()
+    // the pool is at tick 0
+    await expect(tx.place(token0.address, token1.address, FEE_LOW, 0, true, 1000, DEADLINE)).to.eventually.be.rejected
+    await expect(tx.place(token0.address, token1.address, FEE_LOW, -10, true, 1000, DEADLINE)).to.eventually.be.rejected
+    await expect(tx.place(token0.address, token1.address, FEE_LOW, -10, false, 1000, DEADLINE)).to.eventually.be.rejected
+    await expect(tx.place(token0.address, token1.address, FEE_LOW, 5, true, 1000, DEADLINE)).to.eventually.be.rejected
+  })
+
+  it('place refunds the unused input', async () => {
+    const { alice, rangeOrders, token0, token1, nftQuery, placeSellToken0, balanceOf } = await setup()
+    const balance0Before = await balanceOf(token0, alice.address)
+    const balance1Before = await balanceOf(token1, alice.address)
+    const tokenId = await placeSellToken0(100, 1001)
+
+    const position = (await nftQuery.positions(tokenId)).output
+    const spent = balance0Before.sub(await balanceOf(token0, alice.address))
+    expect(spent.lte(new BN(1001))).to.equal(true)
+    expect(spent.gtn(0)).to.equal(true)
+    expect(new BN(position[7].toString()).gtn(0)).to.equal(true)
+    // nothing of the input stays in the contract, and no token1 is taken
+    expect((await balanceOf(token0, rangeOrders.address)).toNumber()).to.equal(0)
+    expect((await balanceOf(token1, alice.address)).eq(balance1Before)).to.equal(true)
+  })
+
+  it('is filled once the tick reaches the upper tick', async () => {
+    const { placeSellToken0, moveToTick, getTick, isFilled } = await setup()
+    const tokenId = await placeSellToken0(100)
+    expect(await isFilled(tokenId)).to.equal(false)
+
+    await moveToTick(105)
+    expect(await isFilled(tokenId)).to.equal(false)
+    await moveToTick(109)
+    expect(await getTick()).to.equal(109)
+    expect(await isFilled(tokenId)).to.equal(false)
+    await moveToTick(110)
+    expect(await getTick()).to.equal(110)
+    expect(await isFilled(tokenId)).to.equal(true)
+  })
+
+  it('execute sends the proceeds to the owner and the tip to the keeper', async () => {
+    const { alice, bob, rangeOrders, token0, token1, placeSellToken0, moveToTick, balanceOf } = await setup()
+    const tokenId = await placeSellToken0(100, 100000)
+    await moveToTick(120)
+
+    const owner0Before = await balanceOf(token0, alice.address)
+    const owner1Before = await balanceOf(token1, alice.address)
+    const keeper1Before = await balanceOf(token1, bob.address)
+    await fromSigner(rangeOrders, bob.address).tx.execute(tokenId)
+
+    const owner1 = (await balanceOf(token1, alice.address)).sub(owner1Before)
+    const tip1 = (await balanceOf(token1, bob.address)).sub(keeper1Before)
+    const proceeds1 = owner1.add(tip1)
+    expect(proceeds1.gtn(0)).to.equal(true)
+    expect(tip1.eq(proceeds1.muln(KEEPER_TIP_BPS).divn(10000))).to.equal(true)
+    // the filled position holds no token0
+    expect((await balanceOf(token0, alice.address)).eq(owner0Before)).to.equal(true)
+    expect((await balanceOf(token1, rangeOrders.address)).toNumber()).to.equal(0)
+    expect((await rangeOrders.query.getOrder(tokenId)).output.isNone).to.equal(true)
+  })
+
+  it('execute rejects an order that is not filled', async () => {
+    const { bob, rangeOrders, placeSellToken0, moveToTick } = await setup()
+    const tokenId = await placeSellToken0(100)
+    await moveToTick(105)
+    await expect(fromSigner(rangeOrders, bob.address).tx.execute(tokenId)).to.eventually.be.rejected
+  })
+
+  it('cancel rejects an order that is filled', async () => {
+    const { tx, placeSellToken0, moveToTick } = await setup()
+    const tokenId = await placeSellToken0(100)
+    await moveToTick(110)
+    await expect(tx.cancel(tokenId)).to.eventually.be.rejected
+  })
+
+  it('cancel returns the tokens of an order that is not filled', async () => {
+    const { alice, bob, rangeOrders, tx, token0, placeSellToken0, moveToTick, balanceOf } = await setup()
+    const tokenId = await placeSellToken0(100)
+    await moveToTick(105)
+    await expect(fromSigner(rangeOrders, bob.address).tx.cancel(tokenId)).to.eventually.be.rejected
+
+    const balance0Before = await balanceOf(token0, alice.address)
+    await tx.cancel(tokenId)
+    expect((await balanceOf(token0, alice.address)).gt(balance0Before)).to.equal(true)
+    expect((await rangeOrders.query.getOrder(tokenId)).output.isNone).to.equal(true)
+  })
+})
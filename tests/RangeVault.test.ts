@@ -0,0 +1,129 @@
+import BN from 'bn.js'
+import { network } from 'redspot'
+import { expect, setupContract, fromSigner } from './helpers'
+import { positionManagerFixture, DEADLINE, FEE_LOW } from './shared/fixtures'
+
+const { api } = network
+
+describe('RangeVault', () => {
+  after(() => {
+    return api.disconnect()
+  })
+
+  const MINIMUM_SHARES = 1000
+
+  // a vault on [-1000, 1000] managed by alice, next to liquidity of alice's own for the rebalancing swaps
+  async function setup() {
+    await api.isReady
+    const fixture = await positionManagerFixture()
+    const { alice, bob, factory, token0, token1, nftTx } = fixture
+    await nftTx.mint(token0.address, token1.address, FEE_LOW, -1000, 1000, 100000, 100000, 0, 0, alice.address, DEADLINE)
+    const { contract: vault, query, tx } = await setupContract(
+      'RangeVaultContract',
+      'new',
+      factory.address,
+      token0.address,
+      token1.address,
+      FEE_LOW,
+      -1000,
+      1000,
+      'Range Vault',
+      'RV'
+    )
+    for (const token of [token0, token1]) {
+      for (const signer of [alice, bob]) {
+        await fromSigner(token, signer.address).tx.approve(vault.address, 10000000)
+      }
+    }
+    const sharesOf = async (owner): Promise<BN> => new BN((await query.balanceOf(owner)).output.toString())
+    const totalSupply = async (): Promise<BN> => new BN((await query.totalSupply()).output.toString())
+    const liquidityOf = async (): Promise<BN> => new BN((await query.getLiquidity()).output.toString())
+    // deposits as the signer, returns the shares issued
+    const deposit = async (signer, amount0: number, amount1: number): Promise<BN> => {
+      const asSigner = fromSigner(vault, signer.address)
+      const [shares] = (await asSigner.query.deposit(amount0, amount1, 0, 0, signer.address)).output
+      await asSigner.tx.deposit(amount0, amount1, 0, 0, signer.address)
+      return new BN(shares.toString())
+    }
+    return { ...fixture, vault, query, tx, sharesOf, totalSupply, liquidityOf, deposit }
+  }
+
+  it('locks the minimum shares of the first deposit in the vault', async () => {
+    const { alice, vault, tx, sharesOf, totalSupply, liquidityOf, deposit } = await setup()
+    // too little liquidity to cover the locked shares
+    await expect(tx.deposit(10, 10, 0, 0, alice.address)).to.eventually.be.rejected
+
+    const shares = await deposit(alice, 100000, 100000)
+    const liquidity = await liquidityOf()
+    expect(shares.eq(liquidity.subn(MINIMUM_SHARES))).to.equal(true)
+    expect((await sharesOf(alice.address)).eq(shares)).to.equal(true)
+    expect((await sharesOf(vault.address)).toNumber()).to.equal(MINIMUM_SHARES)
+    expect((await totalSupply()).eq(liquidity)).to.equal(true)
+  })
+
+  it('tokens sent to the vault before a deposit do not take it', async () => {
+    const { alice, bob, vault, token0, token1, sharesOf, deposit, balanceOf } = await setup()
+    // the smallest first deposit, followed by a large donation of both tokens
+    await deposit(bob, 100, 100)
+    await fromSigner(token0, bob.address).tx.transfer(vault.address, 1000000, [])
+    await fromSigner(token1, bob.address).tx.transfer(vault.address, 1000000, [])
+
+    const balance0Before = await balanceOf(token0, alice.address)
+    const balance1Before = await balanceOf(token1, alice.address)
+    const shares = await deposit(alice, 100000, 100000)
+    expect(shares.gtn(0)).to.equal(true)
+    const spent = balance0Before.sub(await balanceOf(token0, alice.address)).add(balance1Before.sub(await balanceOf(token1, alice.address)))
+
+    await fromSigner(vault, alice.address).tx.withdraw(shares, 0, 0, alice.address)
+    expect((await sharesOf(alice.address)).toNumber()).to.equal(0)
+    const received = (await balanceOf(token0, alice.address)).sub(balance0Before).add((await balanceOf(token1, alice.address)).sub(balance1Before)).add(spent)
+    // at price 1 the value is the sum of the amounts, the rounding of the shares costs alice at most 2%
+    expect(received.muln(100).gte(spent.muln(98))).to.equal(true)
+  })
+
+  it('issues later shares in proportion to the value added', async () => {
+    const { alice, bob, sharesOf, totalSupply, deposit } = await setup()
+    const first = await deposit(alice, 100000, 100000)
+    const second = await deposit(bob, 50000, 50000)
+    // half the value of the vault buys half of its supply, up to rounding
+    const expected = first.addn(MINIMUM_SHARES).divn(2)
+    expect(second.sub(expected).abs().lte(expected.divn(1000))).to.equal(true)
+    expect((await sharesOf(bob.address)).eq(second)).to.equal(true)
+    expect((await totalSupply()).eq(first.add(second).addn(MINIMUM_SHARES))).to.equal(true)
+  })
+
+  it('withdraws the part of the position of the shares redeemed', async () => {
+    const { alice, bob, vault, tx, token0, token1, sharesOf, totalSupply, liquidityOf, deposit, balanceOf } = await setup()
+    const shares = await deposit(alice, 100000, 100000)
+    const liquidityBefore = await liquidityOf()
+    const supplyBefore = await totalSupply()
+    const half = shares.divn(2)
+
+    await expect(fromSigner(vault, bob.address).tx.withdraw(half, 0, 0, bob.address)).to.eventually.be.rejected
+    await expect(tx.withdraw(shares.addn(1), 0, 0, alice.address)).to.eventually.be.rejected
+    await expect(tx.withdraw(half, 100000, 0, alice.address)).to.eventually.be.rejected
+
+    const balance0Before = await balanceOf(token0, bob.address)
+    const balance1Before = await balanceOf(token1, bob.address)
+    await expect(tx.withdraw(half, 0, 0, bob.address)).to.emit(vault, 'Withdrawn')
+    expect((await balanceOf(token0, bob.address)).gt(balance0Before)).to.equal(true)
+    expect((await balanceOf(token1, bob.address)).gt(balance1Before)).to.equal(true)
+    expect((await sharesOf(alice.address)).eq(shares.sub(half))).to.equal(true)
+    expect((await totalSupply()).eq(supplyBefore.sub(half))).to.equal(true)
+    const burned = liquidityBefore.sub(await liquidityOf())
+    expect(burned.eq(liquidityBefore.mul(half).div(supplyBefore))).to.equal(true)
+  })
+
+  it('only the manager moves the liquidity to a new range', async () => {
+    const { alice, bob, vault, query, tx, liquidityOf, deposit } = await setup()
+    await deposit(alice, 100000, 100000)
+    expect((await fromSigner(vault, bob.address).query.rebalance(0, 2000, 0)).output.isErr).to.equal(true)
+    await expect(tx.rebalance(0, 2005, 0)).to.eventually.be.rejected
+    await expect(tx.rebalance(0, 2000, (await liquidityOf()).muln(100))).to.eventually.be.rejected
+
+    await expect(tx.rebalance(0, 2000, 0)).to.emit(vault, 'Rebalanced')
+    const [tickLower, tickUpper] = (await query.getRange()).output
+    expect([tickLower.toNumber(), tickUpper.toNumber()]).to.deep.equal([0, 2000])
+    expect((await liquidityOf()).gtn(0)).to.equal(true)
+  })
+})
@@ -0,0 +1,175 @@
+import BN from 'bn.js'
+import { network } from 'redspot'
+import { expect, setupContract, fromSigner } from './helpers'
+import { positionManagerFixture, DEADLINE, FEE_LOW } from './shared/fixtures'
+
+const { api } = network
+
+describe('Staker', () => {
+  after(() => {
+    return api.disconnect()
+  })
+
+  const DAY = 86400000
+  const REWARD = 1000000
+  // block timestamps can not be moved, incentives start and end a few seconds after they are created
+  const START_DELAY = 3000
+  const DURATION = 10000
+
+  const now = async (): Promise<number> => (await api.query.timestamp.now()).toNumber()
+
+  async function setup() {
+    await api.isReady
+    const fixture = await positionManagerFixture()
+    const { alice, factory, nft, nftQuery, nftTx, pool, token0, token1 } = fixture
+    const { contract: staker, query, tx } = await setupContract('StakerContract', 'new', factory.address, nft.address, DAY, DAY)
+    const { contract: rewardToken } = await setupContract('stable_coin_contract', 'new', 'RWD', 'RWD')
+    await rewardToken.tx.approve(staker.address, REWARD * 10)
+
+    const args = [token0.address, token1.address, FEE_LOW, -1000, 1000, 100000, 100000, 0, 0, alice.address, DEADLINE]
+    const id = (await nftQuery.mint(...args)).output[0]
+    await nftTx.mint(...args)
+
+    const startTime = (await now()) + START_DELAY
+    const key = {
+      rewardToken: rewardToken.address,
+      pool: pool.address,
+      startTime,
+      endTime: startTime + DURATION,
+      refundee: alice.address,
+    }
+    await tx.createIncentive(key, REWARD)
+    const incentiveId = (await query.getIncentiveId(key)).output
+
+    // seals blocks until the chain reaches the timestamp
+    const waitUntil = async (timestamp: number) => {
+      while ((await now()) < timestamp) {
+        await new Promise((resolve) => setTimeout(resolve, 1000))
+        await token0.tx.transfer(fixture.bob.address, 1, [])
+      }
+    }
+    // the data of a transfer to the staker, the encoded incentive keys to stake the position into
+    const stakeData = (keys) =>
+      Array.from(
+        api
+          .createType(
+            'Vec<(AccountId, AccountId, u64, u64, AccountId)>',
+            keys.map((k) => [k.rewardToken, k.pool, k.startTime, k.endTime, k.refundee])
+          )
+          .toU8a()
+      )
+    const deposit = async (data = []) => nftTx.transfer(staker.address, id, data)
+    return { ...fixture, staker, query, tx, rewardToken, id, key, incentiveId, waitUntil, stakeData, deposit }
+  }
+
+  it('records a position deposited by transfer', async () => {
+    const { alice, staker, query, nftQuery, id, deposit } = await setup()
+    await expect(deposit()).to.emit(staker, 'DepositTransferred')
+
+    expect((await nftQuery.ownerOf(id)).output.toString()).to.equal(staker.address.toString())
+    const recorded = (await query.getDeposit(id)).output.unwrap()
+    expect(recorded.owner.toString()).to.equal(alice.address.toString())
+    expect(recorded.numberOfStakes.toNumber()).to.equal(0)
+    expect([recorded.tickLower.toNumber(), recorded.tickUpper.toNumber()]).to.deep.equal([-1000, 1000])
+  })
+
+  it('stakes a deposited position into the incentives given with the transfer', async () => {
+    const { query, id, key, incentiveId, waitUntil, stakeData, deposit } = await setup()
+    // the incentive has not started
+    await expect(deposit(stakeData([key]))).to.eventually.be.rejected
+
+    await waitUntil(key.startTime)
+    await deposit(stakeData([key]))
+    expect((await query.getDeposit(id)).output.unwrap().numberOfStakes.toNumber()).to.equal(1)
+    expect((await query.getStake(id, incentiveId)).output.isSome).to.equal(true)
+    expect((await query.getIncentive(incentiveId)).output.numberOfStakes.toNumber()).to.equal(1)
+  })
+
+  it('only the deposit owner stakes, once per incentive', async () => {
+    const { staker, query, tx, bob, id, key, incentiveId, waitUntil, deposit } = await setup()
+    await deposit()
+    await waitUntil(key.startTime)
+
+    await expect(fromSigner(staker, bob.address).tx.stakeToken(key, id)).to.eventually.be.rejected
+    await expect(tx.stakeToken(key, id)).to.emit(staker, 'TokenStaked')
+    expect((await query.getStake(id, incentiveId)).output.unwrap().liquidity.toString()).to.not.equal('0')
+    await expect(tx.stakeToken(key, id)).to.eventually.be.rejected
+  })
+
+  it('does not withdraw a staked position', async () => {
+    const { alice, nftQuery, staker, tx, id, key, waitUntil, deposit } = await setup()
+    await deposit()
+    await waitUntil(key.startTime)
+    await tx.stakeToken(key, id)
+
+    await expect(tx.withdrawToken(id, alice.address, [])).to.eventually.be.rejected
+    expect((await nftQuery.ownerOf(id)).output.toString()).to.equal(staker.address.toString())
+
+    await tx.unstakeToken(key, id)
+    await tx.withdrawToken(id, alice.address, [])
+    expect((await nftQuery.ownerOf(id)).output.toString()).to.equal(alice.address.toString())
+  })
+
+  it('only the owner unstakes before the end time, anyone after it', async () => {
+    const { alice, bob, staker, query, tx, id, key, incentiveId, waitUntil, deposit } = await setup()
+    await deposit()
+    await waitUntil(key.startTime)
+    await tx.stakeToken(key, id)
+
+    await expect(fromSigner(staker, bob.address).tx.unstakeToken(key, id)).to.eventually.be.rejected
+    await expect(tx.unstakeToken(key, id)).to.emit(staker, 'TokenUnstaked')
+    expect((await query.getStake(id, incentiveId)).output.isNone).to.equal(true)
+    const earlyReward = (await query.getRewards(key.rewardToken, alice.address)).output.toNumber()
+    expect(earlyReward).to.be.gt(0)
+
+    await tx.stakeToken(key, id)
+    await waitUntil(key.endTime)
+    await fromSigner(staker, bob.address).tx.unstakeToken(key, id)
+    // the reward is credited to the deposit owner, not the caller
+    expect((await query.getRewards(key.rewardToken, bob.address)).output.toNumber()).to.equal(0)
+    expect((await query.getRewards(key.rewardToken, alice.address)).output.toNumber()).to.be.gt(earlyReward)
+    expect((await query.getIncentive(incentiveId)).output.numberOfStakes.toNumber()).to.equal(0)
+  })
+
+  it('claims the rewards owed to the caller', async () => {
+    const { alice, bob, staker, query, tx, rewardToken, id, key, waitUntil, deposit, balanceOf } = await setup()
+    await deposit()
+    await waitUntil(key.startTime)
+    await tx.stakeToken(key, id)
+    await waitUntil(key.endTime)
+    await tx.unstakeToken(key, id)
+    const owed = (await query.getRewards(key.rewardToken, alice.address)).output.toNumber()
+
+    // bob is owed nothing
+    expect((await fromSigner(staker, bob.address).query.claimReward(key.rewardToken, bob.address, 0)).output.toNumber()).to.equal(0)
+
+    const balanceBefore = await balanceOf(rewardToken, bob.address)
+    await tx.claimReward(key.rewardToken, bob.address, 1)
+    await expect(tx.claimReward(key.rewardToken, bob.address, 0)).to.emit(staker, 'RewardClaimed')
+    expect((await balanceOf(rewardToken, bob.address)).sub(balanceBefore).eq(new BN(owed))).to.equal(true)
+    expect((await query.getRewards(key.rewardToken, alice.address)).output.toNumber()).to.equal(0)
+  })
+
+  it('ends the incentive after its end time once nothing is staked', async () => {
+    const { alice, staker, query, tx, rewardToken, id, key, incentiveId, waitUntil, deposit, balanceOf } = await setup()
+    await deposit()
+    await waitUntil(key.startTime)
+    await tx.stakeToken(key, id)
+    await expect(tx.endIncentive(key)).to.eventually.be.rejected
+
+    await waitUntil(key.endTime)
+    // the position is still staked
+    await expect(tx.endIncentive(key)).to.eventually.be.rejected
+    await tx.unstakeToken(key, id)
+    const reward = (await query.getRewards(key.rewardToken, alice.address)).output.toNumber()
+
+    const balanceBefore = await balanceOf(rewardToken, alice.address)
+    await expect(tx.endIncentive(key)).to.emit(staker, 'IncentiveEnded')
+    const refund = (await balanceOf(rewardToken, alice.address)).sub(balanceBefore).toNumber()
+    // the reward for the seconds before the stake is refunded
+    expect(refund).to.be.gt(0)
+    expect(refund + reward).to.equal(REWARD)
+    expect((await query.getIncentive(incentiveId)).output.totalRewardUnclaimed.toNumber()).to.equal(0)
+    await expect(tx.endIncentive(key)).to.eventually.be.rejected
+  })
+})
@@ -0,0 +1,107 @@
+import BN from 'bn.js'
+import { network } from 'redspot'
+import { expect, setupContract, fromSigner } from './helpers'
+import { positionManagerFixture, TICK_SPACING_LOW, DEADLINE, FEE_LOW, ZERO_ADDRESS } from './shared/fixtures'
+
+const { api } = network
+
+describe('Treasury', () => {
+  after(() => {
+    return api.disconnect()
+  })
+
+  async function setup() {
+    await api.isReady
+    const fixture = await positionManagerFixture()
+    const { alice, factory, token0, token1, nftTx, pool, moveToTick } = fixture
+    const { contract: treasury, query, tx } = await setupContract('TreasuryContract', 'new', factory.address)
+    await factory.tx.setFeeCollector(treasury.address)
+    await pool.tx.setFeeProtocol(4, 4)
+    await nftTx.mint(token0.address, token1.address, FEE_LOW, -1000, 1000, 100000, 100000, 0, 0, alice.address, DEADLINE)
+    // protocol fees for both tokens
+    const swapBackAndForth = async () => {
+      await moveToTick(200)
+      await moveToTick(-200)
+    }
+    await swapBackAndForth()
+    const accrued = async (): Promise<[number, number]> => [
+      (await query.getAccrued(token0.address)).output.toNumber(),
+      (await query.getAccrued(token1.address)).output.toNumber(),
+    ]
+    return { ...fixture, treasury, query, tx, swapBackAndForth, accrued }
+  }
+
+  it('collects the protocol fees of the given pools', async () => {
+    const { treasury, query, tx, pool, token0, token1, accrued, balanceOf } = await setup()
+    const protocolFees = (await pool.query.protocolFees()).output
+    const [fees0, fees1] = [protocolFees.token0.toNumber(), protocolFees.token1.toNumber()]
+    expect(fees0).to.be.gt(0)
+    expect(fees1).to.be.gt(0)
+
+    const [[amount0, amount1]] = (await query.collect([pool.address])).output
+    await tx.collect([pool.address])
+    // the pool keeps one unit of each token, see collectProtocol
+    expect([amount0.toNumber(), amount1.toNumber()]).to.deep.equal([fees0 - 1, fees1 - 1])
+    expect(await accrued()).to.deep.equal([fees0 - 1, fees1 - 1])
+    expect((await balanceOf(token0, treasury.address)).toNumber()).to.equal(fees0 - 1)
+    expect((await balanceOf(token1, treasury.address)).toNumber()).to.equal(fees1 - 1)
+  })
+
+  it('rejects collecting from a pool the factory did not create', async () => {
+    const { factory, tx, token0, token1 } = await setup()
+    const { contract: fake } = await setupContract('pool', 'new', factory.address, token0.address, token1.address, FEE_LOW, TICK_SPACING_LOW, {
+      hooks: ZERO_ADDRESS,
+      dynamicFee: false,
+      feeController: ZERO_ADDRESS,
+      minFee: 0,
+      maxFee: 0,
+      accessPolicy: ZERO_ADDRESS,
+    })
+    await expect(tx.collect([fake.address])).to.eventually.be.rejected
+  })
+
+  it('collects the protocol fees of a range of the factory pools', async () => {
+    const { query, tx, accrued, swapBackAndForth } = await setup()
+    await tx.collectRange(0, 10)
+    const [accrued0, accrued1] = await accrued()
+    expect(accrued0).to.be.gt(0)
+    expect(accrued1).to.be.gt(0)
+
+    // a range past the end is cut to the pools that exist
+    expect((await query.collectRange(1, 10)).output.length).to.equal(0)
+    await swapBackAndForth()
+    const [[amount0, amount1]] = (await query.collectRange(0, 1)).output
+    await tx.collectRange(0, 1)
+    expect(await accrued()).to.deep.equal([accrued0 + amount0.toNumber(), accrued1 + amount1.toNumber()])
+  })
+
+  it('only the owner approves recipients', async () => {
+    const { bob, treasury, query, tx } = await setup()
+    expect((await fromSigner(treasury, bob.address).query.setRecipient(bob.address, true)).output.isErr).to.equal(true)
+    expect((await query.isApprovedRecipient(bob.address)).output.isTrue).to.equal(false)
+
+    await tx.setRecipient(bob.address, true)
+    expect((await query.isApprovedRecipient(bob.address)).output.isTrue).to.equal(true)
+    await tx.setRecipient(bob.address, false)
+    expect((await query.isApprovedRecipient(bob.address)).output.isTrue).to.equal(false)
+  })
+
+  it('distributes collected fees to approved recipients only', async () => {
+    const { bob, treasury, query, tx, pool, token0, balanceOf } = await setup()
+    await tx.collect([pool.address])
+    const collected = (await query.getAccrued(token0.address)).output.toNumber()
+    const amount = Math.floor(collected / 2)
+
+    await expect(tx.distribute(token0.address, bob.address, amount)).to.eventually.be.rejected
+    await tx.setRecipient(bob.address, true)
+    expect((await fromSigner(treasury, bob.address).query.distribute(token0.address, bob.address, amount)).output.isErr).to.equal(true)
+
+    const balanceBefore = await balanceOf(token0, bob.address)
+    await tx.distribute(token0.address, bob.address, amount)
+    expect((await balanceOf(token0, bob.address)).sub(balanceBefore).eq(new BN(amount))).to.equal(true)
+    expect((await query.getDistributed(token0.address)).output.toNumber()).to.equal(amount)
+    expect((await balanceOf(token0, treasury.address)).toNumber()).to.equal(collected - amount)
+    // more than the treasury holds
+    await expect(tx.distribute(token0.address, bob.address, collected)).to.eventually.be.rejected
+  })
+})
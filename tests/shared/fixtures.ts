@@ -0,0 +1,77 @@
+import BN from 'bn.js'
+import Decimal from 'decimal.js'
+import { artifacts, patract } from 'redspot'
+import { setupContract, fromSigner } from '../helpers'
+
+const { getContractAt } = patract
+
+export const ZERO_ADDRESS = new Uint8Array(32)
+export const DEADLINE = 9652429262733
+export const FEE_LOW = 500
+export const TICK_SPACING_LOW = 10
+// encodePriceSqrt(1, 1), the pool starts at tick 0
+export const SQRT_PRICE_1_1 = new BN('79228162514264337593543950336')
+
+// the sqrt price of a tick as a Q64.96, used as swap price limit to move the pool to a given tick
+export const sqrtPriceAtTick = (tick: number): BN =>
+  new BN(new Decimal(1.0001).pow(tick / 2).mul(new Decimal(2).pow(96)).floor().toFixed())
+
+export const tokenId = (id: number | string) => ({ u128: id })
+
+// factory, position manager and swap router with an initialized 0.05% pool of two test tokens at price 1. Alice and
+// bob hold both tokens and approved the manager and the router
+export const positionManagerFixture = async () => {
+  const { alice, bob, contract: factory, tx: factoryTx } = await setupContract('factory', 'new')
+  const { contract: weth9 } = await setupContract('weth9_contract', 'new', 'weth9', 'weth9')
+  const { contract: descriptor } = await setupContract('NonfungibleTokenPositionDescriptor', 'new', weth9.address, 'ETH', [])
+  const { contract: nft, query: nftQuery, tx: nftTx } = await setupContract('NonfungiblePositionManager', 'new', factory.address, weth9.address, descriptor.address, { value: 1000000000 })
+  const { contract: router, tx: routerTx } = await setupContract('SwapRouterContract', 'new', factory.address, weth9.address, descriptor.address, { value: 1000000000 })
+  const { contract: tokenA } = await setupContract('stable_coin_contract', 'new', 'AAA', 'AAA')
+  const { contract: tokenB } = await setupContract('stable_coin_contract', 'new', 'BBB', 'BBB')
+  const [token0, token1] = tokenA.address.toHuman() < tokenB.address.toHuman() ? [tokenA, tokenB] : [tokenB, tokenA]
+
+  // uploads the pool code, the factory instantiates pools from its hash
+  await setupContract('pool', 'new', factory.address, token0.address, token1.address, FEE_LOW, TICK_SPACING_LOW, {
+    hooks: ZERO_ADDRESS,
+    dynamicFee: false,
+    feeController: ZERO_ADDRESS,
+    minFee: 0,
+    maxFee: 0,
+    accessPolicy: ZERO_ADDRESS,
+  })
+  const poolCodeHash = (await artifacts.readArtifact('pool')).source.hash
+  await factoryTx.initial(poolCodeHash)
+  await nftTx.createAndInitializePoolIfNecessary(token0.address, token1.address, FEE_LOW, SQRT_PRICE_1_1, { value: 1000000000 })
+  const poolAddress = (await factory.query.getPool(FEE_LOW, token0.address, token1.address)).output
+  const pool = fromSigner(await getContractAt('pool', poolAddress, alice), alice.address)
+
+  for (const token of [token0, token1]) {
+    await token.tx.mint(bob.address, 10000000)
+    for (const signer of [alice, bob]) {
+      await fromSigner(token, signer.address).tx.approve(nft.address, 10000000)
+      await fromSigner(token, signer.address).tx.approve(router.address, 10000000)
+    }
+  }
+
+  const getTick = async (): Promise<number> => (await pool.query.getSlot0()).output.tick.toNumber()
+
+  // swaps until the pool reaches the given tick, or the amount is used up. The price limit is half way into the tick,
+  // so rounding of the limit cannot end the swap in a neighbouring tick
+  const moveToTick = async (tick: number, amountIn = 1000000) => {
+    const oneForZero = tick > (await getTick())
+    await routerTx.exactInputSingle(
+      oneForZero ? token1.address : token0.address,
+      oneForZero ? token0.address : token1.address,
+      FEE_LOW,
+      alice.address,
+      DEADLINE,
+      amountIn,
+      0,
+      sqrtPriceAtTick(tick + 0.5)
+    )
+  }
+
+  const balanceOf = async (token, owner): Promise<BN> => new BN((await token.query.balanceOf(owner)).output.toString())
+
+  return { alice, bob, factory, weth9, descriptor, nft, nftQuery, nftTx, router, routerTx, token0, token1, pool, getTick, moveToTick, balanceOf }
+}